@@ -12,11 +12,12 @@ fn main() {
         "src/qml/Main.qml",
         "include/escucha/gui_bridge.h",
         "Cargo.toml",
-        "Cargo.lock",
     ] {
         println!("cargo:rerun-if-changed={path}");
     }
 
+    emit_build_info();
+
     CxxQtBuilder::new()
         .qt_module("Widgets")
         .qml_module(QmlModule {
@@ -32,3 +33,32 @@ fn main() {
         })
         .build();
 }
+
+/// Expose build provenance to `--build-info`: the locked whisper-rs version
+/// and the cargo features this build was compiled with.
+fn emit_build_info() {
+    // Re-read after a dependency bump so the reported versions stay current.
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let whisper_rs = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| {
+            let mut lines = lock.lines();
+            while let Some(line) = lines.next() {
+                if line == "name = \"whisper-rs\"" {
+                    return lines
+                        .next()
+                        .and_then(|v| v.strip_prefix("version = \""))
+                        .map(|v| v.trim_end_matches('"').to_string());
+                }
+            }
+            None
+        })
+        .unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=ESCUCHA_WHISPER_RS_VERSION={whisper_rs}");
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(k, _)| k.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase()))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=ESCUCHA_FEATURES={}", features.join(","));
+}
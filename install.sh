@@ -98,7 +98,8 @@ Description=Escucha speech-to-text service
 After=graphical-session.target ydotoold.service
 
 [Service]
-Type=simple
+Type=notify
+TimeoutStartSec=infinity
 ExecStart=$INSTALL_DIR/escucha
 Restart=on-failure
 RestartSec=5
@@ -1,78 +1,668 @@
 use anyhow::{Context, Result, bail};
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::DirBuilderExt;
 use std::path::{Path, PathBuf};
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-/// Handle to an in-progress audio recording via arecord.
+/// ALSA buffer and period sizes for arecord, in microseconds. Zero leaves
+/// the value to arecord. With a PipeWire `source`, pw-record captures instead.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureTiming {
+    pub buffer_us: u32,
+    pub period_us: u32,
+    /// PipeWire node to record from (`audio_source`).
+    pub source: Option<String>,
+}
+
+impl CaptureTiming {
+    /// Read `arecord_buffer_us` / `arecord_period_us`, rejecting a period
+    /// longer than the buffer it has to fit in.
+    pub fn from_settings(settings: &crate::config::Settings) -> Result<Self> {
+        let source = settings.audio_source.trim();
+        let timing = Self {
+            buffer_us: settings.arecord_buffer_us,
+            period_us: settings.arecord_period_us,
+            source: (!source.is_empty()).then(|| source.to_string()),
+        };
+        if timing.buffer_us > 0 && timing.period_us > timing.buffer_us {
+            bail!(
+                "arecord_period_us ({}) must not exceed arecord_buffer_us ({})",
+                timing.period_us,
+                timing.buffer_us
+            );
+        }
+        Ok(timing)
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.buffer_us > 0 {
+            args.push(format!("--buffer-time={}", self.buffer_us));
+        }
+        if self.period_us > 0 {
+            args.push(format!("--period-time={}", self.period_us));
+        }
+        args
+    }
+
+    /// The capture program and its arguments, up to the output path.
+    fn recorder(&self, format: &RecordingFormat) -> (&'static str, Vec<String>) {
+        match &self.source {
+            Some(node) => {
+                let mut args = vec!["--target".into(), node.clone()];
+                args.extend(format.pw_record_args());
+                ("pw-record", args)
+            }
+            None => {
+                let mut args = self.args();
+                args.extend(format.args());
+                args.extend(["-t".into(), "wav".into()]);
+                ("arecord", args)
+            }
+        }
+    }
+}
+
+/// Sample rate Whisper expects its input at.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Capture rates accepted for `recording_rate`.
+const RECORDING_RATES: [u32; 5] = [16000, 22050, 32000, 44100, 48000];
+
+/// Channel count and sample rate arecord captures S16_LE audio at. Anything
+/// other than 16kHz mono is converted before it reaches Whisper.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordingFormat {
+    pub channels: u16,
+    pub rate: u32,
+}
+
+impl Default for RecordingFormat {
+    fn default() -> Self {
+        Self {
+            channels: 1,
+            rate: WHISPER_SAMPLE_RATE,
+        }
+    }
+}
+
+impl RecordingFormat {
+    /// Read `recording_channels` / `recording_rate`. Rates below 16kHz are
+    /// rejected: Whisper would only get an upsampled, duller signal.
+    pub fn from_settings(settings: &crate::config::Settings) -> Result<Self> {
+        let channels = match settings.recording_channels {
+            1 => 1,
+            2 => 2,
+            other => bail!("recording_channels must be 1 or 2, got {other}"),
+        };
+        if !RECORDING_RATES.contains(&settings.recording_rate) {
+            bail!(
+                "recording_rate must be one of {RECORDING_RATES:?}, got {}",
+                settings.recording_rate
+            );
+        }
+        Ok(Self {
+            channels,
+            rate: settings.recording_rate,
+        })
+    }
+
+    /// Whether recordings are already in Whisper's 16kHz mono format.
+    pub fn is_native(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn args(&self) -> Vec<String> {
+        vec![
+            "-f".into(),
+            "S16_LE".into(),
+            "-r".into(),
+            self.rate.to_string(),
+            "-c".into(),
+            self.channels.to_string(),
+        ]
+    }
+
+    fn pw_record_args(&self) -> Vec<String> {
+        vec![
+            "--format".into(),
+            "s16".into(),
+            "--rate".into(),
+            self.rate.to_string(),
+            "--channels".into(),
+            self.channels.to_string(),
+        ]
+    }
+
+    /// Convert interleaved samples in this format to 16kHz mono.
+    pub fn to_whisper(&self, samples: &[i16]) -> Vec<i16> {
+        if self.is_native() {
+            return samples.to_vec();
+        }
+        let floats: Vec<f32> = samples.iter().map(|&s| f32::from(s)).collect();
+        to_samples(resample(
+            &downmix(&floats, self.channels),
+            self.rate,
+            WHISPER_SAMPLE_RATE,
+        ))
+    }
+}
+
+/// Converts audio in a `RecordingFormat` to 16kHz mono piece by piece, as it
+/// is read from a growing recording. Interpolation runs across the pieces,
+/// so the output matches converting the whole stream at once.
+pub struct StreamConverter {
+    format: RecordingFormat,
+    /// Last mono sample of the previous piece, still needed to interpolate.
+    carry: Option<f32>,
+    /// Output samples produced so far.
+    produced: u64,
+    /// Mono source samples before `carry`, no longer kept.
+    dropped: u64,
+}
+
+impl StreamConverter {
+    pub fn new(format: RecordingFormat) -> Self {
+        Self {
+            format,
+            carry: None,
+            produced: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Convert the next whole frames of interleaved audio.
+    pub fn push(&mut self, samples: &[i16]) -> Vec<i16> {
+        if self.format.is_native() {
+            return samples.to_vec();
+        }
+        let floats: Vec<f32> = samples.iter().map(|&s| f32::from(s)).collect();
+        let mut mono: Vec<f32> = self.carry.into_iter().collect();
+        mono.extend(downmix(&floats, self.format.channels));
+        let Some(&last) = mono.last() else {
+            return Vec::new();
+        };
+        let step = f64::from(self.format.rate) / f64::from(WHISPER_SAMPLE_RATE);
+        let mut out = Vec::new();
+        loop {
+            // Same positions as `resample` over the whole stream.
+            let pos = self.produced as f64 * step - self.dropped as f64;
+            let idx = pos as usize;
+            if idx + 1 >= mono.len() {
+                break;
+            }
+            let frac = (pos - idx as f64) as f32;
+            out.push(mono[idx] + (mono[idx + 1] - mono[idx]) * frac);
+            self.produced += 1;
+        }
+        self.dropped += (mono.len() - 1) as u64;
+        self.carry = Some(last);
+        to_samples(out)
+    }
+}
+
+fn to_samples(floats: Vec<f32>) -> Vec<i16> {
+    floats
+        .into_iter()
+        .map(|s| s.round().clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16)
+        .collect()
+}
+
+/// Average interleaved frames of `channels` samples down to mono.
+pub fn downmix(samples: &[f32], channels: u16) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks(usize::from(channels))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Resample mono audio by linear interpolation. There is no anti-aliasing
+/// filter; that is good enough for speech headed to Whisper.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+    let step = f64::from(from_rate) / f64::from(to_rate);
+    let len = (samples.len() as u64 * u64::from(to_rate) / u64::from(from_rate)) as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// Handle to an in-progress audio recording via arecord (or pw-record).
 pub struct Recording {
     child: Child,
+    /// Capture program (`arecord` or `pw-record`), for error messages.
+    program: &'static str,
+    /// Reader collecting the recorder's last stderr lines.
+    stderr: Option<JoinHandle<String>>,
     path: PathBuf,
+    format: RecordingFormat,
 }
 
 impl Recording {
-    /// Start recording audio to a WAV file using arecord.
-    /// Format: 16kHz, mono, S16_LE PCM.
-    pub fn start(output_path: &Path) -> Result<Self> {
-        let child = Command::new("arecord")
-            .args([
-                "-f",
-                "S16_LE",
-                "-r",
-                "16000",
-                "-c",
-                "1",
-                "-t",
-                "wav",
-                output_path.to_str().unwrap_or("recording.wav"),
-            ])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .context("Failed to start arecord. Is alsa-utils installed?")?;
+    /// Start recording S16_LE audio in `format` to a WAV file using arecord,
+    /// or pw-record when `timing` names a PipeWire source.
+    pub fn start(
+        output_path: &Path,
+        timing: &CaptureTiming,
+        format: &RecordingFormat,
+    ) -> Result<Self> {
+        let (program, args) = timing.recorder(format);
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .arg(output_path.to_str().unwrap_or("recording.wav"));
+        Self::spawn(&mut command, program, output_path, format)
+    }
+
+    /// Run `command` as the recorder `program`, writing to `output_path`.
+    pub(crate) fn spawn(
+        command: &mut Command,
+        program: &'static str,
+        output_path: &Path,
+        format: &RecordingFormat,
+    ) -> Result<Self> {
+        let (child, stderr) = spawn_recorder(command, program, RECORDER_STARTUP_CHECK)?;
 
         Ok(Self {
             child,
+            program,
+            stderr,
             path: output_path.to_path_buf(),
+            format: *format,
         })
     }
 
-    /// Stop recording and return the path to the WAV file.
-    pub fn stop(mut self) -> Result<PathBuf> {
+    /// Stop recording and return the path to the WAV file. Fails, with the
+    /// recorder's last stderr lines, if it had already died or left no file.
+    pub fn stop(self) -> Result<PathBuf> {
+        self.finish().map(|(path, _)| path)
+    }
+
+    /// Like `stop`, but also fail when not a single sample was captured,
+    /// for checks that record for a fixed time (`--smoke-test`). A quick
+    /// key tap can legitimately end that way, so `stop` allows it.
+    pub fn stop_checked(self) -> Result<PathBuf> {
+        let program = self.program;
+        let (path, stderr) = self.finish()?;
+        if let Err(e) = check_has_audio(program, &path) {
+            cleanup_recording(&path);
+            bail!("{}", with_recorder_log(e, &stderr));
+        }
+        Ok(path)
+    }
+
+    /// Stop the recorder and collect its stderr.
+    fn finish(mut self) -> Result<(PathBuf, String)> {
+        let exited = self.child.try_wait().ok().flatten();
+        if exited.is_none() {
+            self.terminate();
+        }
+        self.child
+            .wait()
+            .context("Failed to wait for the recorder to stop")?;
+        // The recorder is gone, so its stderr is closed and the reader ends.
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default();
+
+        if let Some(failure) = stop_failure(self.program, &self.path, exited) {
+            if exited.is_some() {
+                cleanup_recording(&self.path);
+            }
+            bail!("{}", with_recorder_log(failure, &stderr));
+        }
+        Ok((self.path, stderr))
+    }
+
+    fn terminate(&mut self) {
         // Send SIGTERM for graceful shutdown
         let pid = self.child.id();
         if let Err(e) = nix::sys::signal::kill(
             nix::unistd::Pid::from_raw(pid as i32),
             nix::sys::signal::Signal::SIGTERM,
         ) {
-            log::warn!("Failed to send SIGTERM to arecord (pid {pid}): {e}");
+            log::warn!("Failed to send SIGTERM to the recorder (pid {pid}): {e}");
             // Try regular kill as fallback
             let _ = self.child.kill();
         }
+    }
 
-        self.child
-            .wait()
-            .context("Failed to wait for arecord to stop")?;
+    /// Get the output file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Input level of the most recently written audio, from 0.0 (silence) to 1.0.
+    /// Reads the tail of the WAV file arecord is still writing, so no second
+    /// capture stream is needed.
+    pub fn current_level(&self) -> f32 {
+        let window = LEVEL_WINDOW_SAMPLES * self.format.rate as usize
+            / WHISPER_SAMPLE_RATE as usize
+            * usize::from(self.format.channels);
+        read_tail_samples(&self.path, window)
+            .map(|samples| level_from_rms(rms(&samples)))
+            .unwrap_or(0.0)
+    }
+
+    /// All audio captured so far as 16kHz mono, read from the WAV file
+    /// arecord is still writing.
+    pub fn samples_so_far(&self) -> Result<Vec<i16>> {
+        Ok(self
+            .format
+            .to_whisper(&read_tail_samples(&self.path, usize::MAX)?))
+    }
+}
+
+/// How long a new recorder must stay up to count as capturing. With a bad
+/// device or no permission, arecord and pw-record exit well within this.
+const RECORDER_STARTUP_CHECK: Duration = Duration::from_millis(50);
+
+/// Most recent recorder stderr lines kept for error messages.
+const RECORDER_STDERR_LINES: usize = 5;
+
+/// Spawn a recorder and make sure it is still running after `check`, so a
+/// capture failure is reported right away with the recorder's own error
+/// instead of surfacing later as an empty recording.
+fn spawn_recorder(
+    command: &mut Command,
+    program: &str,
+    check: Duration,
+) -> Result<(Child, Option<JoinHandle<String>>)> {
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| match program {
+            "arecord" => "Failed to start arecord. Is alsa-utils installed?".to_string(),
+            other => format!("Failed to start {other}. Is PipeWire installed?"),
+        })?;
+    let stderr = child.stderr.take().map(drain_stderr);
+    std::thread::sleep(check);
+    check_started(child, stderr, program)
+}
+
+/// Fail with the recorder's stderr if it has already exited.
+fn check_started(
+    mut child: Child,
+    stderr: Option<JoinHandle<String>>,
+    program: &str,
+) -> Result<(Child, Option<JoinHandle<String>>)> {
+    let Some(status) = child
+        .try_wait()
+        .with_context(|| format!("Failed to check on {program}"))?
+    else {
+        return Ok((child, stderr));
+    };
+    // It has exited, so its stderr is closed and the reader finishes.
+    let output = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    bail!(
+        "{}",
+        with_recorder_log(
+            format!("{program} stopped right after starting ({status})"),
+            &output
+        )
+    );
+}
 
-        if !self.path.exists() {
-            bail!("Recording file not found: {}", self.path.display());
+/// Read a recorder's stderr until it exits, logging each line and keeping
+/// the last few. Reading keeps the recorder from blocking on a full pipe.
+fn drain_stderr(stderr: impl Read + Send + 'static) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        use std::io::BufRead;
+        let mut lines = std::collections::VecDeque::new();
+        for line in std::io::BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+        {
+            let line = line.trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            log::debug!("recorder: {line}");
+            if lines.len() == RECORDER_STDERR_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line);
         }
+        Vec::from(lines).join("; ")
+    })
+}
 
-        Ok(self.path)
+/// `message`, followed by what the recorder printed to stderr, if anything.
+fn with_recorder_log(message: String, stderr: &str) -> String {
+    if stderr.is_empty() {
+        message
+    } else {
+        format!("{message}: {stderr}")
     }
+}
 
-    /// Get the output file path.
-    pub fn path(&self) -> &Path {
-        &self.path
+/// Why a stopped recording is unusable: the recorder exited on its own
+/// before it was stopped (`exited`), or left no file.
+fn stop_failure(program: &str, path: &Path, exited: Option<ExitStatus>) -> Option<String> {
+    match exited {
+        Some(status) => Some(format!("{program} stopped during recording ({status})")),
+        None if !path.exists() => Some(format!("Recording file not found: {}", path.display())),
+        None => None,
     }
 }
 
-/// Create a temporary WAV file path for recording.
-pub fn temp_wav_path() -> Result<PathBuf> {
-    let dir = tempfile::tempdir().context("Failed to create temp dir")?;
-    // We leak the tempdir so it doesn't get cleaned up
-    // The caller is responsible for cleaning up the WAV file
-    let path = dir.path().join("escucha_recording.wav");
-    std::mem::forget(dir);
-    Ok(path)
+/// Fail when a WAV file holds no samples after its header.
+fn check_has_audio(program: &str, path: &Path) -> std::result::Result<(), String> {
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if size <= WAV_HEADER_BYTES {
+        return Err(format!("{program} recorded no audio ({size} bytes)"));
+    }
+    Ok(())
+}
+
+/// Canonical WAV header size written by arecord.
+const WAV_HEADER_BYTES: u64 = 44;
+
+/// Samples used for a level reading: 100ms at 16kHz.
+const LEVEL_WINDOW_SAMPLES: usize = 1600;
+
+/// Read up to `count` trailing S16_LE samples from a WAV file that may still be growing.
+fn read_tail_samples(path: &Path, count: usize) -> Result<Vec<i16>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len <= WAV_HEADER_BYTES {
+        return Ok(Vec::new());
+    }
+
+    let data_len = (len - WAV_HEADER_BYTES) & !1;
+    let want = (count as u64).saturating_mul(2).min(data_len);
+    file.seek(SeekFrom::Start(WAV_HEADER_BYTES + data_len - want))?;
+
+    let mut buf = vec![0u8; want as usize];
+    file.read_exact(&mut buf)?;
+    Ok(le_samples(&buf))
+}
+
+/// Read the S16_LE samples from index `start` on, from a WAV file that may
+/// still be growing.
+pub fn read_samples_from(path: &Path, start: usize) -> Result<Vec<i16>> {
+    let mut file = std::fs::File::open(path)?;
+    let data_len = file.metadata()?.len().saturating_sub(WAV_HEADER_BYTES) & !1;
+    let offset = (start as u64).saturating_mul(2);
+    if offset >= data_len {
+        return Ok(Vec::new());
+    }
+    file.seek(SeekFrom::Start(WAV_HEADER_BYTES + offset))?;
+    let mut buf = vec![0u8; (data_len - offset) as usize];
+    file.read_exact(&mut buf)?;
+    Ok(le_samples(&buf))
+}
+
+fn le_samples(buf: &[u8]) -> Vec<i16> {
+    buf.chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// Root-mean-square amplitude of 16-bit samples, normalized to 0.0..=1.0.
+pub fn rms(samples: &[i16]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples
+        .iter()
+        .map(|&s| {
+            let v = s as f64 / 32768.0;
+            v * v
+        })
+        .sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Convert a normalized RMS amplitude to dBFS (negative infinity for silence).
+pub fn dbfs(rms: f32) -> f32 {
+    if rms <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    20.0 * rms.log10()
+}
+
+/// Map an RMS amplitude onto a 0.0..=1.0 meter scale covering -60 dBFS to 0 dBFS.
+pub fn level_from_rms(rms: f32) -> f32 {
+    if rms <= 0.0 {
+        return 0.0;
+    }
+    ((dbfs(rms) + 60.0) / 60.0).clamp(0.0, 1.0)
+}
+
+/// Default recording directory: `$XDG_RUNTIME_DIR/escucha`, falling back to the
+/// cache dir and then the system temp dir.
+pub fn default_recording_dir() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("escucha")
+}
+
+/// Resolve the `recording_dir` setting ("auto" or a path, `~/` expanded).
+pub fn recording_dir(setting: &str) -> PathBuf {
+    match setting.trim() {
+        "" | "auto" => default_recording_dir(),
+        dir => crate::logging::expand_home(dir),
+    }
+}
+
+/// Path of this process's recording file in `dir`. Every recording reuses
+/// (overwrites) the same file, so at most one WAV per process is ever on disk.
+pub fn temp_wav_path(dir: &Path) -> Result<PathBuf> {
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir.join(format!("escucha_recording_{}.wav", std::process::id())))
+}
+
+/// Default directory for kept recordings: `$XDG_STATE_HOME/escucha/recordings`,
+/// falling back to the local data dir.
+pub fn default_keep_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("escucha")
+        .join("recordings")
+}
+
+/// Where recordings go instead of being deleted (`keep_recordings`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingArchive {
+    pub dir: PathBuf,
+    /// Most recordings retained; older ones are pruned.
+    pub max: u32,
+}
+
+impl RecordingArchive {
+    /// Read `keep_recordings` / `recordings_keep_dir` / `keep_recordings_max`.
+    /// `None` when recordings should be deleted as usual.
+    pub fn from_settings(settings: &crate::config::Settings) -> Result<Option<Self>> {
+        if !settings.keep_recordings {
+            return Ok(None);
+        }
+        if settings.keep_recordings_max == 0 {
+            bail!("keep_recordings_max must be at least 1 when keep_recordings is on");
+        }
+        let dir = match settings.recordings_keep_dir.trim() {
+            "" | "auto" => default_keep_dir(),
+            dir => crate::logging::expand_home(dir),
+        };
+        Ok(Some(Self {
+            dir,
+            max: settings.keep_recordings_max,
+        }))
+    }
+
+    /// Move `path` into the archive under a timestamped name, then prune the
+    /// oldest recordings beyond `max`. Returns the new path.
+    pub fn keep(&self, path: &Path) -> Result<PathBuf> {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let dest = self.dir.join(format!("escucha_{millis}.wav"));
+        // The recording dir is often a tmpfs, so fall back to copying.
+        if std::fs::rename(path, &dest).is_err() {
+            std::fs::copy(path, &dest)
+                .with_context(|| format!("Failed to copy recording to {}", dest.display()))?;
+            cleanup_recording(path);
+        }
+        self.prune();
+        Ok(dest)
+    }
+
+    /// Kept recordings, oldest first.
+    fn recordings(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("escucha_") && name.ends_with(".wav"))
+            })
+            .collect();
+        // Names embed a millisecond timestamp of fixed width, so they sort by age.
+        paths.sort();
+        paths
+    }
+
+    fn prune(&self) {
+        let paths = self.recordings();
+        let excess = paths.len().saturating_sub(self.max as usize);
+        for path in &paths[..excess] {
+            cleanup_recording(path);
+        }
+    }
 }
 
 /// Clean up a recording file.
@@ -82,10 +672,51 @@ pub fn cleanup_recording(path: &Path) {
     {
         log::warn!("Failed to clean up {}: {e}", path.display());
     }
-    // Also try to remove the parent temp directory
-    if let Some(parent) = path.parent() {
-        let _ = std::fs::remove_dir(parent);
+}
+
+/// Read all 16-bit samples from a WAV file (as written by arecord),
+/// converted to 16kHz mono.
+pub fn read_wav_samples(path: &Path) -> Result<Vec<i16>> {
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let spec = reader.spec();
+    let samples: Vec<i16> = reader
+        .into_samples::<i16>()
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Failed to read samples from {}", path.display()))?;
+    let format = RecordingFormat {
+        channels: spec.channels,
+        rate: spec.sample_rate,
+    };
+    Ok(format.to_whisper(&samples))
+}
+
+/// Playback length of a WAV file, from its header.
+pub fn wav_duration(path: &Path) -> Result<Duration> {
+    let reader = hound::WavReader::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    let spec = reader.spec();
+    let frames = u64::from(reader.duration());
+    Ok(Duration::from_millis(
+        frames * 1000 / u64::from(spec.sample_rate.max(1)),
+    ))
+}
+
+/// Write 16kHz mono S16_LE samples to a WAV file, replacing it if present.
+pub fn write_wav_samples(path: &Path, samples: &[i16]) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    for &sample in samples {
+        writer.write_sample(sample)?;
     }
+    writer.finalize()?;
+    Ok(())
 }
 
 /// Check if arecord is available on the system.
@@ -103,14 +734,216 @@ mod tests {
         let _available = check_arecord();
     }
 
+    #[test]
+    fn test_drain_stderr_keeps_last_lines() {
+        let output = "Recording WAVE 'x.wav' : Signed 16 bit Little Endian\n\n\
+                      overrun!!! (at least 1.2 ms long)\n\
+                      overrun!!! (at least 3.4 ms long)\n\
+                      Status:\n  state : XRUN\n\
+                      arecord: pcm_read:2221: read error: Input/output error\n";
+        let tail = drain_stderr(std::io::Cursor::new(output)).join().unwrap();
+        assert_eq!(
+            tail,
+            "overrun!!! (at least 1.2 ms long); overrun!!! (at least 3.4 ms long); \
+             Status:; state : XRUN; arecord: pcm_read:2221: read error: Input/output error"
+        );
+        assert_eq!(drain_stderr(std::io::empty()).join().unwrap(), "");
+    }
+
+    #[test]
+    fn test_stop_failure() {
+        use std::os::unix::process::ExitStatusExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.wav");
+
+        let died = ExitStatus::from_raw(1 << 8);
+        assert_eq!(
+            stop_failure("arecord", &path, Some(died)).unwrap(),
+            "arecord stopped during recording (exit status: 1)"
+        );
+        assert!(
+            stop_failure("pw-record", &path, None)
+                .unwrap()
+                .starts_with("Recording file not found")
+        );
+
+        std::fs::write(&path, vec![0u8; WAV_HEADER_BYTES as usize]).unwrap();
+        assert_eq!(stop_failure("arecord", &path, None), None);
+        assert_eq!(
+            check_has_audio("pw-record", &path).unwrap_err(),
+            "pw-record recorded no audio (44 bytes)"
+        );
+        std::fs::write(&path, vec![0u8; 1000]).unwrap();
+        assert!(check_has_audio("arecord", &path).is_ok());
+
+        assert_eq!(with_recorder_log("failed".into(), ""), "failed");
+        assert_eq!(
+            with_recorder_log("failed".into(), "audio open error"),
+            "failed: audio open error"
+        );
+    }
+
+    #[test]
+    fn test_stop_reports_exited_recorder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.wav");
+        std::fs::write(&path, vec![0u8; 100]).unwrap();
+        let (child, stderr) =
+            exited_recorder("arecord: main:831: audio open error: Device or resource busy\n");
+        let rec = Recording {
+            child,
+            program: "arecord",
+            stderr,
+            path: path.clone(),
+            format: RecordingFormat::default(),
+        };
+        let err = rec.stop().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "arecord stopped during recording (exit status: 1): \
+             arecord: main:831: audio open error: Device or resource busy"
+        );
+        // The partial file is removed.
+        assert!(!path.exists());
+    }
+
+    /// A recorder that already exited, with what it printed on the way out.
+    fn exited_recorder(stderr: &'static str) -> (Child, Option<JoinHandle<String>>) {
+        let mut child = Command::new("false").spawn().unwrap();
+        child.wait().unwrap();
+        (child, Some(drain_stderr(std::io::Cursor::new(stderr))))
+    }
+
+    #[test]
+    fn test_check_started_reports_early_exit() {
+        let (child, stderr) = exited_recorder(
+            "Recording WAVE stdin\n\
+             arecord: main:831: audio open error: No such file or directory\n",
+        );
+        let err = check_started(child, stderr, "arecord")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "arecord stopped right after starting (exit status: 1): Recording WAVE stdin; \
+             arecord: main:831: audio open error: No such file or directory"
+        );
+
+        let (child, stderr) = exited_recorder("");
+        let err = check_started(child, stderr, "pw-record")
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "pw-record stopped right after starting (exit status: 1)"
+        );
+    }
+
+    #[test]
+    fn test_spawn_recorder_running() {
+        let mut command = Command::new("sleep");
+        command.arg("5");
+        let (mut child, _) = spawn_recorder(&mut command, "arecord", Duration::ZERO).unwrap();
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let mut command = Command::new("/nonexistent/arecord");
+        let err = spawn_recorder(&mut command, "arecord", Duration::ZERO).unwrap_err();
+        assert!(err.to_string().contains("alsa-utils"));
+    }
+
     #[test]
     fn test_temp_wav_path() {
-        let path = temp_wav_path().unwrap();
-        assert!(path.to_string_lossy().contains("escucha_recording.wav"));
+        let dir = default_recording_dir();
+        let path = temp_wav_path(&dir).unwrap();
+        assert!(path.to_string_lossy().contains("escucha_recording_"));
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert!(dir.is_dir());
         // Clean up
         cleanup_recording(&path);
     }
 
+    #[test]
+    fn test_temp_wav_path_is_stable() {
+        let dir = default_recording_dir();
+        assert_eq!(temp_wav_path(&dir).unwrap(), temp_wav_path(&dir).unwrap());
+    }
+
+    #[test]
+    fn test_temp_wav_path_custom_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("recordings");
+        let path = temp_wav_path(&recording_dir(dir.to_str().unwrap())).unwrap();
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert!(dir.is_dir());
+    }
+
+    #[test]
+    fn test_recording_dir_auto() {
+        assert_eq!(recording_dir("auto"), default_recording_dir());
+        assert_eq!(recording_dir(""), default_recording_dir());
+        assert_eq!(recording_dir("/mnt/ram"), PathBuf::from("/mnt/ram"));
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(recording_dir("~/rec"), home.join("rec"));
+    }
+
+    #[test]
+    fn test_cleanup_keeps_recording_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = temp_wav_path(tmp.path()).unwrap();
+        std::fs::write(&path, b"fake wav data").unwrap();
+        cleanup_recording(&path);
+        assert!(!path.exists());
+        assert!(tmp.path().is_dir());
+    }
+
+    #[test]
+    fn test_recording_archive_from_settings() {
+        let mut settings = crate::config::Settings::default();
+        assert_eq!(RecordingArchive::from_settings(&settings).unwrap(), None);
+
+        settings.keep_recordings = true;
+        let archive = RecordingArchive::from_settings(&settings).unwrap().unwrap();
+        assert_eq!(archive.dir, default_keep_dir());
+        assert_eq!(archive.max, 20);
+
+        settings.recordings_keep_dir = "/tmp/kept".into();
+        let archive = RecordingArchive::from_settings(&settings).unwrap().unwrap();
+        assert_eq!(archive.dir, PathBuf::from("/tmp/kept"));
+        settings.recordings_keep_dir = "~/kept".into();
+        let archive = RecordingArchive::from_settings(&settings).unwrap().unwrap();
+        assert_eq!(archive.dir, dirs::home_dir().unwrap().join("kept"));
+
+        settings.keep_recordings_max = 0;
+        assert!(RecordingArchive::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_recording_archive_keep_moves_and_prunes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = RecordingArchive {
+            dir: tmp.path().join("kept"),
+            max: 2,
+        };
+        std::fs::create_dir_all(&archive.dir).unwrap();
+        std::fs::write(archive.dir.join("escucha_1000000000000.wav"), b"old").unwrap();
+        std::fs::write(archive.dir.join("escucha_1000000000001.wav"), b"older").unwrap();
+        std::fs::write(archive.dir.join("notes.txt"), b"mine").unwrap();
+
+        let path = temp_wav_path(tmp.path()).unwrap();
+        std::fs::write(&path, b"fake wav data").unwrap();
+        let kept = archive.keep(&path).unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&kept).unwrap(), b"fake wav data");
+        assert_eq!(
+            archive.recordings(),
+            vec![archive.dir.join("escucha_1000000000001.wav"), kept]
+        );
+        assert!(archive.dir.join("notes.txt").exists());
+    }
+
     #[test]
     fn test_cleanup_nonexistent() {
         // Should not panic
@@ -126,4 +959,247 @@ mod tests {
         cleanup_recording(&path);
         assert!(!path.exists());
     }
+
+    #[test]
+    fn test_wav_samples_roundtrip_and_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wav");
+
+        write_wav_samples(&path, &[1, 2, 3]).unwrap();
+        let mut joined = read_wav_samples(&path).unwrap();
+        write_wav_samples(&path, &[-4, 5]).unwrap();
+        joined.extend(read_wav_samples(&path).unwrap());
+
+        write_wav_samples(&path, &joined).unwrap();
+        assert_eq!(read_wav_samples(&path).unwrap(), vec![1, 2, 3, -4, 5]);
+    }
+
+    #[test]
+    fn test_recording_format_from_settings() {
+        let mut settings = crate::config::Settings::default();
+        let format = RecordingFormat::from_settings(&settings).unwrap();
+        assert!(format.is_native());
+        assert_eq!(format.args(), ["-f", "S16_LE", "-r", "16000", "-c", "1"]);
+
+        settings.recording_channels = 2;
+        settings.recording_rate = 48000;
+        let format = RecordingFormat::from_settings(&settings).unwrap();
+        assert!(!format.is_native());
+        assert_eq!(format.args(), ["-f", "S16_LE", "-r", "48000", "-c", "2"]);
+
+        settings.recording_channels = 6;
+        assert!(RecordingFormat::from_settings(&settings).is_err());
+        settings.recording_channels = 1;
+        settings.recording_rate = 8000;
+        assert!(RecordingFormat::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_downmix_and_resample() {
+        assert_eq!(downmix(&[1.0, 3.0, -2.0, 2.0], 2), vec![2.0, 0.0]);
+        assert_eq!(downmix(&[1.0, 3.0], 1), vec![1.0, 3.0]);
+        assert_eq!(
+            resample(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0], 48000, 16000),
+            vec![0.0, 3.0]
+        );
+        assert_eq!(
+            resample(&[0.0, 2.0], 16000, 32000),
+            vec![0.0, 1.0, 2.0, 2.0]
+        );
+        assert_eq!(resample(&[1.0, 2.0], 16000, 16000), vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_to_whisper_converts_stereo_48k() {
+        let format = RecordingFormat {
+            channels: 2,
+            rate: 48000,
+        };
+        // 0.25 s of stereo audio: left 100, right 300.
+        let samples: Vec<i16> = [100, 300].repeat(12000);
+        let mono = format.to_whisper(&samples);
+        assert_eq!(mono.len(), 4000);
+        assert!(mono.iter().all(|&s| s == 200));
+        assert_eq!(RecordingFormat::default().to_whisper(&[1, 2]), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_stream_converter_matches_whole_conversion() {
+        let format = RecordingFormat {
+            channels: 2,
+            rate: 44100,
+        };
+        let samples: Vec<i16> = (0..44100).map(|i| ((i * 37) % 2000) as i16).collect();
+        let whole = format.to_whisper(&samples);
+
+        let mut converter = StreamConverter::new(format);
+        let mut pieces = Vec::new();
+        // Odd piece sizes, in whole stereo frames.
+        for piece in samples.chunks(2 * 331) {
+            pieces.extend(converter.push(piece));
+        }
+        // At most the final sample waits for audio that never comes.
+        assert!(whole.len() - pieces.len() <= 1);
+        assert_eq!(pieces[..], whole[..pieces.len()]);
+        assert!(converter.push(&[]).is_empty());
+
+        let mut native = StreamConverter::new(RecordingFormat::default());
+        assert_eq!(native.push(&[1, 2]), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_capture_timing_args() {
+        assert!(CaptureTiming::default().args().is_empty());
+        let timing = CaptureTiming {
+            buffer_us: 40000,
+            period_us: 10000,
+            source: None,
+        };
+        assert_eq!(
+            timing.args(),
+            vec!["--buffer-time=40000", "--period-time=10000"]
+        );
+        let period_only = CaptureTiming {
+            buffer_us: 0,
+            period_us: 5000,
+            source: None,
+        };
+        assert_eq!(period_only.args(), vec!["--period-time=5000"]);
+    }
+
+    #[test]
+    fn test_capture_timing_recorder() {
+        let format = RecordingFormat {
+            channels: 2,
+            rate: 48000,
+        };
+        let (program, args) = CaptureTiming::default().recorder(&format);
+        assert_eq!(program, "arecord");
+        assert_eq!(
+            args,
+            vec!["-f", "S16_LE", "-r", "48000", "-c", "2", "-t", "wav"]
+        );
+
+        let pipewire = CaptureTiming {
+            source: Some("alsa_input.usb-Yeti-00.analog-stereo".into()),
+            ..CaptureTiming::default()
+        };
+        let (program, args) = pipewire.recorder(&format);
+        assert_eq!(program, "pw-record");
+        assert_eq!(
+            args,
+            vec![
+                "--target",
+                "alsa_input.usb-Yeti-00.analog-stereo",
+                "--format",
+                "s16",
+                "--rate",
+                "48000",
+                "--channels",
+                "2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_capture_timing_from_settings() {
+        let settings = crate::config::Settings {
+            arecord_buffer_us: 20000,
+            arecord_period_us: 5000,
+            ..Default::default()
+        };
+        assert_eq!(
+            CaptureTiming::from_settings(&settings).unwrap(),
+            CaptureTiming {
+                buffer_us: 20000,
+                period_us: 5000,
+                source: None,
+            }
+        );
+        let inverted = crate::config::Settings {
+            arecord_buffer_us: 5000,
+            arecord_period_us: 20000,
+            ..Default::default()
+        };
+        assert!(CaptureTiming::from_settings(&inverted).is_err());
+    }
+
+    #[test]
+    fn test_wav_duration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wav");
+        write_wav_samples(&path, &vec![0; 24000]).unwrap();
+        assert_eq!(
+            wav_duration(&path).unwrap(),
+            std::time::Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn test_rms() {
+        assert_eq!(rms(&[]), 0.0);
+        assert_eq!(rms(&[0, 0, 0]), 0.0);
+        assert!((rms(&[16384, -16384]) - 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_dbfs() {
+        assert_eq!(dbfs(0.0), f32::NEG_INFINITY);
+        assert!((dbfs(1.0)).abs() < 0.001);
+        assert!((dbfs(0.1) + 20.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_level_from_rms() {
+        assert_eq!(level_from_rms(0.0), 0.0);
+        assert_eq!(level_from_rms(1.0), 1.0);
+        assert!((level_from_rms(0.001) - 0.0).abs() < 0.001);
+        assert!((level_from_rms(0.0316) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_read_tail_samples() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wav");
+
+        let mut bytes = vec![0u8; WAV_HEADER_BYTES as usize];
+        for s in [1i16, 2, 3, 4] {
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(read_tail_samples(&path, 2).unwrap(), vec![3, 4]);
+        assert_eq!(read_tail_samples(&path, 10).unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(
+            read_tail_samples(&path, usize::MAX).unwrap(),
+            vec![1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_read_tail_samples_header_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wav");
+        std::fs::write(&path, vec![0u8; WAV_HEADER_BYTES as usize]).unwrap();
+        assert!(read_tail_samples(&path, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_samples_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.wav");
+
+        let mut bytes = vec![0u8; WAV_HEADER_BYTES as usize];
+        for s in [1i16, 2, 3, 4] {
+            bytes.extend_from_slice(&s.to_le_bytes());
+        }
+        // A sample arecord has only half written yet.
+        bytes.push(5);
+        std::fs::write(&path, &bytes).unwrap();
+
+        assert_eq!(read_samples_from(&path, 0).unwrap(), vec![1, 2, 3, 4]);
+        assert_eq!(read_samples_from(&path, 3).unwrap(), vec![4]);
+        assert!(read_samples_from(&path, 4).unwrap().is_empty());
+        assert!(read_samples_from(&path, 9).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,198 @@
+use crate::config::Settings;
+use crate::transcribe::{self, Transcriber};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Models benchmarked when none are given on the command line.
+pub const DEFAULT_MODELS: &[&str] = &["tiny.en", "base.en", "small.en"];
+
+/// Transcriptions timed per model (after one warm-up run).
+const RUNS: usize = 5;
+
+/// Length of the generated sample clip.
+const SAMPLE_SECS: u32 = 5;
+const SAMPLE_RATE: u32 = 16_000;
+
+#[derive(Serialize)]
+pub struct BenchmarkResult {
+    model: String,
+    runs: usize,
+    median_ms: Option<u128>,
+    real_time_factor: Option<f64>,
+    error: Option<String>,
+}
+
+/// Download/load each model with the configured language, GPU and thread
+/// settings, transcribe a sample clip `RUNS` times and report median latency
+/// and real-time factor (latency / audio length).
+pub fn run(models: &[String], settings: &Settings) -> Result<Vec<BenchmarkResult>> {
+    let dir = tempfile::tempdir().context("Failed to create temp dir")?;
+    let sample = dir.path().join("benchmark.wav");
+    write_sample_wav(&sample)?;
+    let audio_len = Duration::from_secs(u64::from(SAMPLE_SECS));
+
+    Ok(models
+        .iter()
+        .map(|model| match bench_model(model, settings, &sample) {
+            Ok(median) => BenchmarkResult {
+                model: model.clone(),
+                runs: RUNS,
+                median_ms: Some(median.as_millis()),
+                real_time_factor: Some(real_time_factor(median, audio_len)),
+                error: None,
+            },
+            Err(e) => BenchmarkResult {
+                model: model.clone(),
+                runs: 0,
+                median_ms: None,
+                real_time_factor: None,
+                error: Some(format!("{e:#}")),
+            },
+        })
+        .collect())
+}
+
+pub fn run_and_print(models: &[String], settings: &Settings, json: bool) -> Result<()> {
+    let results = run(models, settings)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        print!("{}", format_table(&results));
+    }
+    Ok(())
+}
+
+fn bench_model(model: &str, settings: &Settings, sample: &Path) -> Result<Duration> {
+    let model_path =
+        transcribe::ensure_model(model, &transcribe::DownloadOptions::from_settings(settings))?;
+    let transcriber = Transcriber::new(&model_path, &settings.language, settings.use_gpu)?
+        .with_threads(settings.n_threads)
+        .with_decode_options(transcribe::DecodeOptions::from_settings(settings)?);
+
+    // Warm-up run so one-time allocations don't skew the first sample.
+    transcriber.transcribe(sample)?;
+
+    let mut timings = Vec::with_capacity(RUNS);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        transcriber.transcribe(sample)?;
+        timings.push(start.elapsed());
+    }
+    Ok(median(&mut timings))
+}
+
+/// Write a speech-like test clip: a few seconds of gliding, amplitude-modulated
+/// tones at 16kHz mono. Whisper's cost depends on clip length, not content, so
+/// this stands in for a recorded sample without shipping audio in the package.
+fn write_sample_wav(path: &Path) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    for i in 0..SAMPLE_RATE * SAMPLE_SECS {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let pitch = 140.0 + 40.0 * (t * 1.3 * std::f32::consts::TAU).sin();
+        let envelope = 0.5 + 0.5 * (t * 4.0 * std::f32::consts::TAU).sin();
+        let sample = (pitch * t * std::f32::consts::TAU).sin() * envelope * 0.3;
+        writer.write_sample((sample * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+fn median(timings: &mut [Duration]) -> Duration {
+    if timings.is_empty() {
+        return Duration::ZERO;
+    }
+    timings.sort();
+    let mid = timings.len() / 2;
+    if timings.len().is_multiple_of(2) {
+        (timings[mid - 1] + timings[mid]) / 2
+    } else {
+        timings[mid]
+    }
+}
+
+/// Processing time divided by audio duration; below 1.0 is faster than real time.
+fn real_time_factor(latency: Duration, audio: Duration) -> f64 {
+    latency.as_secs_f64() / audio.as_secs_f64()
+}
+
+fn format_table(results: &[BenchmarkResult]) -> String {
+    let mut out = format!("{:<12} {:>10} {:>8}\n", "model", "median", "RTF");
+    for r in results {
+        match (&r.error, r.median_ms, r.real_time_factor) {
+            (None, Some(ms), Some(rtf)) => {
+                out.push_str(&format!("{:<12} {:>8}ms {:>8.2}\n", r.model, ms, rtf));
+            }
+            (err, _, _) => {
+                out.push_str(&format!(
+                    "{:<12} failed: {}\n",
+                    r.model,
+                    err.as_deref().unwrap_or("unknown error")
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_median_odd_and_even() {
+        let ms = Duration::from_millis;
+        assert_eq!(median(&mut [ms(30), ms(10), ms(20)]), ms(20));
+        assert_eq!(median(&mut [ms(40), ms(10), ms(20), ms(30)]), ms(25));
+        assert_eq!(median(&mut []), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_real_time_factor() {
+        let rtf = real_time_factor(Duration::from_millis(500), Duration::from_secs(5));
+        assert!((rtf - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sample_wav_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sample.wav");
+        write_sample_wav(&path).unwrap();
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+        assert_eq!(reader.duration(), SAMPLE_RATE * SAMPLE_SECS);
+    }
+
+    #[test]
+    fn test_format_table() {
+        let results = vec![
+            BenchmarkResult {
+                model: "tiny.en".into(),
+                runs: 5,
+                median_ms: Some(420),
+                real_time_factor: Some(0.084),
+                error: None,
+            },
+            BenchmarkResult {
+                model: "nope".into(),
+                runs: 0,
+                median_ms: None,
+                real_time_factor: None,
+                error: Some("download failed".into()),
+            },
+        ];
+        let table = format_table(&results);
+        assert!(table.contains("tiny.en"));
+        assert!(table.contains("420ms"));
+        assert!(table.contains("0.08"));
+        assert!(table.contains("nope         failed: download failed"));
+    }
+}
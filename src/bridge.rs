@@ -3,6 +3,8 @@ pub mod qobject {
     unsafe extern "C++" {
         include!("cxx-qt-lib/qstring.h");
         type QString = cxx_qt_lib::QString;
+        include!("cxx-qt-lib/qstringlist.h");
+        type QStringList = cxx_qt_lib::QStringList;
     }
 
     #[auto_cxx_name]
@@ -12,14 +14,30 @@ pub mod qobject {
         #[qproperty(QString, status_text)]
         #[qproperty(QString, status_detail)]
         #[qproperty(QString, device_name)]
+        #[qproperty(QString, trigger_key_label)]
+        #[qproperty(QString, paste_method_label)]
         #[qproperty(QString, transcription)]
         #[qproperty(QString, status_icon_name)]
         #[qproperty(bool, show_spinner)]
         #[qproperty(bool, show_fix_button)]
         #[qproperty(bool, show_paste_fix_button)]
+        #[qproperty(bool, show_key_fix_button)]
         #[qproperty(bool, is_recording)]
         #[qproperty(bool, is_stopped)]
         #[qproperty(bool, is_ready)]
+        #[qproperty(bool, is_downloading)]
+        #[qproperty(QString, config_key)]
+        #[qproperty(QString, config_model)]
+        #[qproperty(QStringList, model_names)]
+        #[qproperty(QString, config_language)]
+        #[qproperty(QString, config_paste_method)]
+        #[qproperty(QStringList, paste_methods)]
+        #[qproperty(QStringList, input_devices)]
+        #[qproperty(QString, active_device)]
+        #[qproperty(f32, audio_level)]
+        #[qproperty(QStringList, history)]
+        #[qproperty(bool, capturing_key)]
+        #[qproperty(bool, start_minimized)]
         type EscuchaBackend = super::EscuchaBackendRust;
 
         #[qinvokable]
@@ -31,6 +49,36 @@ pub mod qobject {
         #[qinvokable]
         fn request_shutdown(self: Pin<&mut EscuchaBackend>);
 
+        #[qinvokable]
+        fn reload_config(self: Pin<&mut EscuchaBackend>);
+
+        #[qinvokable]
+        fn update_key(self: Pin<&mut EscuchaBackend>, key: &QString);
+
+        #[qinvokable]
+        fn update_model(self: Pin<&mut EscuchaBackend>, model: &QString);
+
+        #[qinvokable]
+        fn update_language(self: Pin<&mut EscuchaBackend>, language: &QString);
+
+        #[qinvokable]
+        fn update_paste_method(self: Pin<&mut EscuchaBackend>, paste_method: &QString);
+
+        #[qinvokable]
+        fn select_device(self: Pin<&mut EscuchaBackend>, path: &QString);
+
+        #[qinvokable]
+        fn copy_entry(self: Pin<&mut EscuchaBackend>, index: i32);
+
+        #[qinvokable]
+        fn repaste_last(self: Pin<&mut EscuchaBackend>);
+
+        #[qinvokable]
+        fn begin_key_capture(self: Pin<&mut EscuchaBackend>);
+
+        #[qinvokable]
+        fn cancel_key_capture(self: Pin<&mut EscuchaBackend>);
+
         #[qsignal]
         fn error_occurred(self: Pin<&mut EscuchaBackend>, message: QString);
     }
@@ -41,7 +89,7 @@ pub mod qobject {
 
 use core::pin::Pin;
 use cxx_qt::{CxxQtType, Threading};
-use cxx_qt_lib::QString;
+use cxx_qt_lib::{QList, QString, QStringList};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -62,9 +110,21 @@ const SG_REEXEC_ENV: &str = "ESCUCHA_SG_REEXECED";
 const GUI_AUTOSTART_DESKTOP_FILE: &str = "io.github.escucha.desktop";
 const APP_ICON_NAME: &str = "io.github.escucha";
 
-fn shell_quote(arg: &str) -> String {
-    let escaped = arg.replace('\'', "'\"'\"'");
-    format!("'{escaped}'")
+/// Tray icon for each service state, so recording is visible at a glance.
+fn status_icon_name(status: ServiceStatus) -> &'static str {
+    match status {
+        ServiceStatus::Starting | ServiceStatus::Ready => APP_ICON_NAME,
+        ServiceStatus::Downloading => "folder-download",
+        ServiceStatus::Recording => "media-record",
+        ServiceStatus::Transcribing => "view-refresh",
+        ServiceStatus::Stopping | ServiceStatus::Stopped => "media-playback-stop",
+    }
+}
+
+/// Elapsed recording time as "m:ss".
+fn format_elapsed(elapsed_ms: u64) -> String {
+    let secs = elapsed_ms / 1000;
+    format!("{}:{:02}", secs / 60, secs % 60)
 }
 
 /// Restart the application by re-executing itself with the new group membership active.
@@ -74,9 +134,9 @@ fn restart_app() {
 
     let mut cmd_parts = vec![
         format!("{SG_REEXEC_ENV}=1"),
-        shell_quote(&exe.to_string_lossy()),
+        crate::privileged::shell_quote(&exe.to_string_lossy()),
     ];
-    cmd_parts.extend(args[1..].iter().map(|s| shell_quote(s)));
+    cmd_parts.extend(args[1..].iter().map(|s| crate::privileged::shell_quote(s)));
     let full_cmd = cmd_parts.join(" ");
 
     let success = std::process::Command::new("sg")
@@ -91,6 +151,12 @@ fn restart_app() {
     std::process::exit(0);
 }
 
+/// How long the "press a key to bind" flow waits for a key press.
+const KEY_CAPTURE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Number of recent transcriptions kept in the GUI history list.
+const HISTORY_LIMIT: usize = 20;
+
 const FIRST_RUN_MARKER: &str = "first-run-onboarding-v2.done";
 
 fn escucha_state_dir() -> PathBuf {
@@ -189,26 +255,18 @@ fn user_listed_in_input_group(user: &str) -> bool {
 }
 
 fn attempt_input_permission_fix(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) -> bool {
-    let user = std::env::var("USER").unwrap_or_default();
-    if user.is_empty() {
-        let _ = qt_thread.queue(move |mut qobject| {
-            qobject
-                .as_mut()
-                .error_occurred(QString::from("Could not determine current username"));
-        });
-        return false;
-    }
-    if !user
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
-    {
-        let _ = qt_thread.queue(move |mut qobject| {
-            qobject.as_mut().error_occurred(QString::from(
-                "Unsafe username; cannot run privileged setup",
-            ));
-        });
-        return false;
-    }
+    let user = match crate::privileged::current_user() {
+        Ok(user) => user,
+        Err(e) => {
+            let message = format!("{e:#}");
+            let _ = qt_thread.queue(move |mut qobject| {
+                qobject
+                    .as_mut()
+                    .error_occurred(QString::from(message.as_str()));
+            });
+            return false;
+        }
+    };
 
     // If /etc/group already has the user but this session still lacks access,
     // first try a self re-exec under `sg input` before asking for logout.
@@ -241,8 +299,10 @@ fn attempt_input_permission_fix(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaB
 
     let script = format!(
         "set -e; \
-         usermod -aG input {user}; \
-         if command -v setfacl >/dev/null 2>&1; then setfacl -m u:{user}:rw /dev/input/event* || true; fi"
+         usermod -aG input {}; \
+         if command -v setfacl >/dev/null 2>&1; then setfacl -m {} /dev/input/event* || true; fi",
+        crate::privileged::shell_quote(&user),
+        crate::privileged::shell_quote(&format!("u:{user}:rw")),
     );
     let ok = std::process::Command::new("pkexec")
         .args(["/bin/sh", "-c", &script])
@@ -368,15 +428,35 @@ pub struct EscuchaBackendRust {
     status_text: QString,
     status_detail: QString,
     device_name: QString,
+    trigger_key_label: QString,
+    paste_method_label: QString,
     transcription: QString,
     status_icon_name: QString,
     show_spinner: bool,
     show_fix_button: bool,
     show_paste_fix_button: bool,
+    show_key_fix_button: bool,
     is_recording: bool,
     is_stopped: bool,
     is_ready: bool,
+    is_downloading: bool,
+    config_key: QString,
+    config_model: QString,
+    model_names: QStringList,
+    config_language: QString,
+    config_paste_method: QString,
+    paste_methods: QStringList,
+    input_devices: QStringList,
+    active_device: QString,
+    audio_level: f32,
+    history: QStringList,
+    history_entries: Vec<String>,
+    capturing_key: bool,
+    start_minimized: bool,
+    capture_cancel: Arc<AtomicBool>,
     shutdown_flag: Option<Arc<AtomicBool>>,
+    restart_flag: Arc<AtomicBool>,
+    service_active: Arc<AtomicBool>,
 }
 
 impl qobject::EscuchaBackend {
@@ -393,6 +473,228 @@ impl qobject::EscuchaBackend {
         }
     }
 
+    /// Re-read `config.ini` and restart the service in-process, e.g. after
+    /// editing the file by hand.
+    pub fn reload_config(self: Pin<&mut Self>) {
+        log::info!("Reloading config from {}", config::config_path().display());
+        self.restart_service();
+    }
+
+    pub fn update_key(mut self: Pin<&mut Self>, key: &QString) {
+        let key = key.to_string().trim().to_string();
+        if let Err(e) = crate::input::resolve_key_chord(&key) {
+            self.as_mut()
+                .error_occurred(QString::from(format!("{e:#}").as_str()));
+            return;
+        }
+        self.apply_setting(move |s| s.key = key);
+    }
+
+    pub fn update_model(mut self: Pin<&mut Self>, model: &QString) {
+        let model = model.to_string().trim().to_string();
+        if let Err(e) = crate::transcribe::check_model_setting(&model) {
+            self.as_mut()
+                .error_occurred(QString::from(format!("{e:#}").as_str()));
+            return;
+        }
+        self.apply_setting(move |s| s.model = model);
+    }
+
+    pub fn update_language(mut self: Pin<&mut Self>, language: &QString) {
+        let language = language.to_string().trim().to_string();
+        if language.is_empty() {
+            self.as_mut()
+                .error_occurred(QString::from("Language cannot be empty"));
+            return;
+        }
+        self.apply_setting(move |s| s.language = language);
+    }
+
+    pub fn update_paste_method(mut self: Pin<&mut Self>, paste_method: &QString) {
+        let paste_method = paste_method.to_string();
+        if !crate::paste::PASTE_METHOD_SETTINGS.contains(&paste_method.as_str()) {
+            self.as_mut().error_occurred(QString::from(
+                format!("Unknown paste method: {paste_method}").as_str(),
+            ));
+            return;
+        }
+        self.apply_setting(move |s| s.paste_method = paste_method);
+    }
+
+    pub fn select_device(mut self: Pin<&mut Self>, path: &QString) {
+        let path = path.to_string();
+        if path != "auto" && !path.starts_with("/dev/input/") {
+            self.as_mut().error_occurred(QString::from(
+                format!("Invalid input device: {path}").as_str(),
+            ));
+            return;
+        }
+        self.apply_setting(move |s| s.keyboard_device = path);
+    }
+
+    pub fn copy_entry(self: Pin<&mut Self>, index: i32) {
+        let Some(text) = usize::try_from(index)
+            .ok()
+            .and_then(|i| self.rust().history_entries.get(i).cloned())
+        else {
+            return;
+        };
+        let paste_method = self.rust().config_paste_method.to_string();
+        let qt_thread = self.qt_thread();
+        std::thread::spawn(move || {
+            // Only the clipboard tool is needed, not an injection tool.
+            let method = crate::paste::pick_clipboard_method(&paste_method);
+            let result = crate::paste::copy_to_clipboard(&text, method);
+            let _ = qt_thread.queue(move |mut qobject| match result {
+                Ok(()) => qobject
+                    .as_mut()
+                    .set_status_detail(QString::from("Copied to clipboard")),
+                Err(e) => qobject
+                    .as_mut()
+                    .error_occurred(QString::from(format!("Copy failed: {e}").as_str())),
+            });
+        });
+    }
+
+    pub fn repaste_last(self: Pin<&mut Self>) {
+        let Some(text) = self.rust().history_entries.first().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        std::thread::spawn(move || {
+            // Let the tray menu close so focus returns to the target window.
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            match config::load_settings()
+                .and_then(|settings| crate::service::Output::from_settings(&settings))
+            {
+                Ok(output) => output.deliver(&text, &mut RepasteCallbacks { qt_thread }),
+                Err(e) => {
+                    let _ = qt_thread.queue(move |mut qobject| {
+                        qobject
+                            .as_mut()
+                            .error_occurred(QString::from(format!("Paste failed: {e}").as_str()));
+                    });
+                }
+            }
+        });
+    }
+
+    pub fn begin_key_capture(mut self: Pin<&mut Self>) {
+        if *self.capturing_key() {
+            return;
+        }
+        self.as_mut().set_capturing_key(true);
+        self.as_mut().set_status_detail(QString::from(
+            "Press the key you want to hold for dictation...",
+        ));
+
+        let cancel = self.rust().capture_cancel.clone();
+        cancel.store(false, Ordering::Relaxed);
+        let qt_thread = self.qt_thread();
+        std::thread::spawn(move || {
+            let settings = config::load_settings().unwrap_or_default();
+            let result = crate::input::resolve_key_chord(&settings.key)
+                .and_then(|keys| {
+                    let primary = keys[keys.len() - 1];
+                    crate::input::pick_keyboard_device(&settings.keyboard_device, primary)
+                })
+                .and_then(|path| {
+                    crate::input::capture_key_press(&path, KEY_CAPTURE_TIMEOUT, &cancel)
+                });
+
+            let _ = qt_thread.queue(move |mut qobject| {
+                qobject.as_mut().set_capturing_key(false);
+                match result {
+                    Ok(Some(key)) => {
+                        let name = crate::input::key_to_name(key);
+                        if crate::input::resolve_key(&name).is_ok() {
+                            qobject.as_mut().apply_setting(move |s| s.key = name);
+                        } else {
+                            qobject.as_mut().error_occurred(QString::from(
+                                format!("{name} cannot be used as a dictation key").as_str(),
+                            ));
+                        }
+                    }
+                    Ok(None) => qobject
+                        .as_mut()
+                        .set_status_detail(QString::from("Key capture cancelled")),
+                    Err(e) => qobject
+                        .as_mut()
+                        .error_occurred(QString::from(format!("Key capture failed: {e}").as_str())),
+                }
+            });
+        });
+    }
+
+    pub fn cancel_key_capture(self: Pin<&mut Self>) {
+        self.rust().capture_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Forget all transcriptions in the history list.
+    fn clear_history(mut self: Pin<&mut Self>) {
+        self.as_mut().rust_mut().history_entries.clear();
+        self.set_history(QStringList::default());
+    }
+
+    /// Add a transcription to the front of the history list.
+    fn push_history(mut self: Pin<&mut Self>, text: String) {
+        let mut rust = self.as_mut().rust_mut();
+        rust.history_entries.insert(0, text);
+        rust.history_entries.truncate(HISTORY_LIMIT);
+        let list = to_qstringlist(&rust.history_entries);
+        self.set_history(list);
+    }
+
+    /// Load the config, apply `update`, persist it, and restart the service
+    /// so the new values take effect.
+    fn apply_setting(mut self: Pin<&mut Self>, update: impl FnOnce(&mut config::Settings)) {
+        // File values only, so environment overrides aren't saved back.
+        let mut settings = match config::load_settings_from(config::config_path()) {
+            Ok(s) => s,
+            Err(e) => {
+                self.as_mut()
+                    .error_occurred(QString::from(format!("Config error: {e}").as_str()));
+                return;
+            }
+        };
+
+        let before = settings.clone();
+        update(&mut settings);
+        if settings == before {
+            return;
+        }
+
+        if let Err(e) = config::save_changed_settings(&before, &settings) {
+            self.as_mut()
+                .error_occurred(QString::from(format!("{e:#}").as_str()));
+            return;
+        }
+
+        show_settings(self.as_mut(), &settings);
+        self.restart_service();
+    }
+
+    /// Stop the running service loop and start a fresh one with the saved config.
+    fn restart_service(mut self: Pin<&mut Self>) {
+        self.rust().restart_flag.store(true, Ordering::Relaxed);
+        self.as_mut()
+            .set_status_detail(QString::from("Applying new settings..."));
+
+        if self.rust().service_active.load(Ordering::Relaxed) {
+            if let Some(flag) = &self.rust().shutdown_flag {
+                flag.store(true, Ordering::Relaxed);
+            }
+            return;
+        }
+
+        let qt_thread = self.qt_thread();
+        let restart = self.rust().restart_flag.clone();
+        let active = self.rust().service_active.clone();
+        std::thread::spawn(move || {
+            run_service_sessions(&qt_thread, &restart, &active);
+        });
+    }
+
     pub fn fix_paste_setup(self: Pin<&mut Self>) {
         let qt_thread = self.qt_thread();
         std::thread::spawn(move || {
@@ -400,16 +702,11 @@ impl qobject::EscuchaBackend {
             let _ = qt_thread.queue(move |mut qobject| {
                 if ok {
                     qobject.as_mut().set_show_paste_fix_button(false);
+                    // The service picks its paste method at startup.
+                    qobject.as_mut().restart_service();
                     qobject.as_mut().set_status_detail(QString::from(
                         "Paste setup fixed. Restarting...",
                     ));
-                    let _ = std::process::Command::new(
-                        std::env::current_exe()
-                            .unwrap_or_else(|_| std::path::PathBuf::from("escucha")),
-                    )
-                    .args(std::env::args().skip(1))
-                    .spawn();
-                    std::process::exit(0);
                 } else {
                     qobject.as_mut().error_occurred(QString::from(
                         "Could not fix paste setup automatically. Please verify /dev/uinput access and run: systemctl --user enable --now ydotoold.service",
@@ -426,17 +723,90 @@ impl cxx_qt::Initialize for qobject::EscuchaBackend {
         self.as_mut()
             .set_status_icon_name(QString::from(APP_ICON_NAME));
         self.as_mut().set_show_spinner(true);
+        let settings = config::load_settings().unwrap_or_default();
+        let key_label = QString::from(crate::input::key_label(&settings.key).as_str());
         self.as_mut()
-            .set_transcription(QString::from("Hold Right Ctrl and speak..."));
+            .set_transcription(idle_transcription(&key_label));
+        self.as_mut().set_trigger_key_label(key_label);
+        self.as_mut().set_start_minimized(settings.start_minimized);
 
         let qt_thread = self.qt_thread();
+        let restart = self.rust().restart_flag.clone();
+        let active = self.rust().service_active.clone();
         std::thread::spawn(move || {
-            run_service_thread(qt_thread);
+            run_service_thread(qt_thread, restart, active);
         });
     }
 }
 
-fn run_service_thread(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) {
+/// Placeholder shown instead of a transcription while idle.
+fn idle_transcription(key_label: &QString) -> QString {
+    QString::from(format!("Hold {key_label} and speak...").as_str())
+}
+
+/// Status detail shown while the service is ready.
+fn ready_detail(key_label: &QString) -> QString {
+    QString::from(format!("Hold {key_label} to speak").as_str())
+}
+
+/// Mirror the editable settings into the backend's QML-facing properties.
+fn show_settings(mut qobject: Pin<&mut qobject::EscuchaBackend>, settings: &config::Settings) {
+    qobject
+        .as_mut()
+        .set_config_key(QString::from(settings.key.as_str()));
+    let mut models = crate::transcribe::model_choices();
+    // A path set by hand still shows as the current choice.
+    if !models.contains(&settings.model) {
+        models.push(settings.model.clone());
+    }
+    qobject.as_mut().set_model_names(to_qstringlist(&models));
+    qobject
+        .as_mut()
+        .set_config_model(QString::from(settings.model.as_str()));
+    qobject
+        .as_mut()
+        .set_config_language(QString::from(settings.language.as_str()));
+    let paste_methods: Vec<String> = crate::paste::PASTE_METHOD_SETTINGS
+        .iter()
+        .map(|method| method.to_string())
+        .collect();
+    qobject
+        .as_mut()
+        .set_paste_methods(to_qstringlist(&paste_methods));
+    qobject
+        .as_mut()
+        .set_config_paste_method(QString::from(settings.paste_method.as_str()));
+}
+
+fn to_qstringlist(items: &[String]) -> QStringList {
+    let mut list = QList::<QString>::default();
+    for item in items {
+        list.append(QString::from(item.as_str()));
+    }
+    QStringList::from(&list)
+}
+
+/// Labels for the keyboards the user can pick from, in `list_devices_cli` order.
+fn keyboard_device_labels() -> Vec<String> {
+    let settings = crate::config::load_settings().unwrap_or_default();
+    let trigger = crate::input::trigger_key(&settings.key);
+    match crate::input::list_input_devices() {
+        Ok(devices) => crate::input::filter_keyboards(&devices, trigger)
+            .iter()
+            .map(|d| d.label())
+            .collect(),
+        Err(e) => {
+            log::warn!("Could not list input devices: {e}");
+            Vec::new()
+        }
+    }
+}
+
+fn run_service_thread(
+    qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>,
+    restart: Arc<AtomicBool>,
+    active: Arc<AtomicBool>,
+) {
     first_launch_onboarding(&qt_thread);
 
     // Run preflight checks
@@ -467,7 +837,7 @@ fn run_service_thread(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) {
             qobject.as_mut().set_is_stopped(true);
             qobject
                 .as_mut()
-                .set_status_icon_name(QString::from(APP_ICON_NAME));
+                .set_status_icon_name(QString::from(status_icon_name(ServiceStatus::Stopped)));
             if input_failed {
                 qobject.as_mut().set_show_fix_button(true);
             }
@@ -500,6 +870,41 @@ fn run_service_thread(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) {
         }
     }
 
+    run_service_sessions(&qt_thread, &restart, &active);
+}
+
+/// Run the dictation service, starting it again whenever a settings change
+/// requests a restart.
+fn run_service_sessions(
+    qt_thread: &cxx_qt::CxxQtThread<qobject::EscuchaBackend>,
+    restart: &Arc<AtomicBool>,
+    active: &Arc<AtomicBool>,
+) {
+    if active.swap(true, Ordering::Relaxed) {
+        return;
+    }
+
+    loop {
+        restart.store(false, Ordering::Relaxed);
+        run_service_session(qt_thread, restart);
+
+        let _ = qt_thread.queue(move |mut qobject| {
+            qobject.as_mut().rust_mut().shutdown_flag = None;
+        });
+
+        if !restart.load(Ordering::Relaxed) {
+            break;
+        }
+        log::info!("Restarting service with updated settings");
+    }
+
+    active.store(false, Ordering::Relaxed);
+}
+
+fn run_service_session(
+    qt_thread: &cxx_qt::CxxQtThread<qobject::EscuchaBackend>,
+    restart: &Arc<AtomicBool>,
+) {
     let settings = match config::load_settings() {
         Ok(s) => s,
         Err(e) => {
@@ -510,32 +915,68 @@ fn run_service_thread(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) {
                 qobject.as_mut().set_is_stopped(true);
                 qobject
                     .as_mut()
-                    .set_status_icon_name(QString::from(APP_ICON_NAME));
+                    .set_status_icon_name(QString::from(status_icon_name(ServiceStatus::Stopped)));
                 qobject.as_mut().error_occurred(QString::from(msg.as_str()));
             });
             return;
         }
     };
+    // Before `show_settings`, so aliases added since the last run are listed.
+    // An invalid entry is reported by `DictationService::new` below.
+    if let Err(e) = crate::transcribe::set_model_aliases(&settings.model_aliases) {
+        log::warn!("{e:#}");
+    }
+
+    let shown = settings.clone();
+    let device_labels = keyboard_device_labels();
+    let _ = qt_thread.queue(move |mut qobject| {
+        show_settings(qobject.as_mut(), &shown);
+        qobject
+            .as_mut()
+            .set_input_devices(to_qstringlist(&device_labels));
+    });
 
     match crate::service::DictationService::new(settings) {
         Ok(service) => {
             let device_label = service.device_label();
             let display_name = strip_device_prefix(&device_label).to_string();
+            let key_label = service.trigger_key_label();
+            let paste_label = service.paste_method_label();
             let _ = qt_thread.queue(move |mut qobject| {
                 qobject
                     .as_mut()
                     .set_device_name(QString::from(display_name.as_str()));
+                qobject
+                    .as_mut()
+                    .set_trigger_key_label(QString::from(key_label.as_str()));
+                qobject
+                    .as_mut()
+                    .set_paste_method_label(QString::from(paste_label.as_str()));
+                qobject
+                    .as_mut()
+                    .set_active_device(QString::from(device_label.as_str()));
             });
 
             // Set up shutdown bridge: store the service's shutdown handle into the QObject
             let svc_shutdown = service.shutdown_handle();
+            if restart.load(Ordering::Relaxed) {
+                // Settings changed again while this session was starting up.
+                svc_shutdown.store(true, Ordering::Relaxed);
+            }
             let gui_shutdown = svc_shutdown.clone();
             let _ = qt_thread.queue(move |mut qobject| {
                 qobject.as_mut().rust_mut().shutdown_flag = Some(gui_shutdown);
             });
 
+            let privacy = service.privacy_mode();
+            if privacy {
+                let _ = qt_thread.queue(move |mut qobject| {
+                    qobject.as_mut().clear_history();
+                });
+            }
             let mut callbacks = BridgeCallbacks {
                 qt_thread: qt_thread.clone(),
+                privacy,
             };
             if let Err(e) = service.run_loop(&mut callbacks) {
                 log::error!("Service error: {e}");
@@ -549,15 +990,47 @@ fn run_service_thread(qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>) {
                 qobject.as_mut().set_is_stopped(true);
                 qobject
                     .as_mut()
-                    .set_status_icon_name(QString::from(APP_ICON_NAME));
+                    .set_status_icon_name(QString::from(status_icon_name(ServiceStatus::Stopped)));
                 qobject.as_mut().error_occurred(QString::from(msg.as_str()));
             });
         }
     }
 }
 
+/// Reports a "Paste last" delivery without touching the service state or
+/// adding the text to the history again.
+struct RepasteCallbacks {
+    qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>,
+}
+
+impl ServiceCallbacks for RepasteCallbacks {
+    fn on_status(&mut self, _status: ServiceStatus) {}
+
+    fn on_status_msg(&mut self, msg: &str) {
+        let msg = msg.to_string();
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject
+                .as_mut()
+                .set_status_detail(QString::from(msg.as_str()));
+        });
+    }
+
+    fn on_text(&mut self, _text: &str) {}
+
+    fn on_error(&mut self, error: &str) {
+        let error = error.to_string();
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject
+                .as_mut()
+                .error_occurred(QString::from(error.as_str()));
+        });
+    }
+}
+
 struct BridgeCallbacks {
     qt_thread: cxx_qt::CxxQtThread<qobject::EscuchaBackend>,
+    /// Don't keep transcriptions in the history list (`privacy_mode`).
+    privacy: bool,
 }
 
 impl ServiceCallbacks for BridgeCallbacks {
@@ -567,15 +1040,17 @@ impl ServiceCallbacks for BridgeCallbacks {
             qobject.as_mut().set_is_recording(false);
             qobject.as_mut().set_is_stopped(false);
             qobject.as_mut().set_is_ready(false);
+            qobject.as_mut().set_is_downloading(false);
+            qobject.as_mut().set_audio_level(0.0);
+            qobject
+                .as_mut()
+                .set_status_icon_name(QString::from(status_icon_name(status)));
 
             match status {
                 ServiceStatus::Stopped => {
                     qobject.as_mut().set_status_text(QString::from("Stopped"));
                     qobject.as_mut().set_show_spinner(false);
                     qobject.as_mut().set_is_stopped(true);
-                    qobject
-                        .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
                     qobject.as_mut().set_status_detail(QString::from(""));
                 }
                 ServiceStatus::Starting => {
@@ -583,22 +1058,24 @@ impl ServiceCallbacks for BridgeCallbacks {
                         .as_mut()
                         .set_status_text(QString::from("Starting..."));
                     qobject.as_mut().set_show_spinner(true);
+                }
+                ServiceStatus::Downloading => {
                     qobject
                         .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
+                        .set_status_text(QString::from("Downloading model..."));
+                    // The download window shows progress instead.
+                    qobject.as_mut().set_show_spinner(false);
+                    qobject.as_mut().set_is_downloading(true);
                 }
                 ServiceStatus::Ready => {
                     qobject.as_mut().set_status_text(QString::from("Ready"));
                     qobject.as_mut().set_show_spinner(false);
                     qobject.as_mut().set_is_ready(true);
-                    qobject
-                        .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
-                    qobject
-                        .as_mut()
-                        .set_status_detail(QString::from("Hold Right Ctrl to speak"));
+                    let detail = ready_detail(qobject.trigger_key_label());
+                    qobject.as_mut().set_status_detail(detail);
                     qobject.as_mut().set_show_fix_button(false);
                     qobject.as_mut().set_show_paste_fix_button(false);
+                    qobject.as_mut().set_show_key_fix_button(false);
                 }
                 ServiceStatus::Recording => {
                     qobject
@@ -606,9 +1083,6 @@ impl ServiceCallbacks for BridgeCallbacks {
                         .set_status_text(QString::from("Recording..."));
                     qobject.as_mut().set_show_spinner(false);
                     qobject.as_mut().set_is_recording(true);
-                    qobject
-                        .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
                     qobject
                         .as_mut()
                         .set_status_detail(QString::from("Release to transcribe"));
@@ -618,9 +1092,6 @@ impl ServiceCallbacks for BridgeCallbacks {
                         .as_mut()
                         .set_status_text(QString::from("Transcribing..."));
                     qobject.as_mut().set_show_spinner(true);
-                    qobject
-                        .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
                     qobject.as_mut().set_status_detail(QString::from(""));
                 }
                 ServiceStatus::Stopping => {
@@ -629,9 +1100,6 @@ impl ServiceCallbacks for BridgeCallbacks {
                         .set_status_text(QString::from("Stopping..."));
                     qobject.as_mut().set_show_spinner(true);
                     qobject.as_mut().set_is_stopped(true);
-                    qobject
-                        .as_mut()
-                        .set_status_icon_name(QString::from(APP_ICON_NAME));
                     qobject.as_mut().set_status_detail(QString::from(""));
                 }
             }
@@ -649,19 +1117,63 @@ impl ServiceCallbacks for BridgeCallbacks {
 
     fn on_text(&mut self, text: &str) {
         let text = text.to_string();
+        let privacy = self.privacy;
         let _ = self.qt_thread.queue(move |mut qobject| {
             if text.is_empty() {
-                qobject
-                    .as_mut()
-                    .set_transcription(QString::from("Hold Right Ctrl and speak..."));
+                let idle = idle_transcription(qobject.trigger_key_label());
+                qobject.as_mut().set_transcription(idle);
             } else {
                 qobject
                     .as_mut()
                     .set_transcription(QString::from(text.as_str()));
+                if !privacy {
+                    qobject.as_mut().push_history(text);
+                }
             }
         });
     }
 
+    fn on_partial_text(&mut self, text: &str) {
+        let text = format!("{text}...");
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject
+                .as_mut()
+                .set_transcription(QString::from(text.as_str()));
+        });
+    }
+
+    fn on_audio_level(&mut self, level: f32) {
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject.as_mut().set_audio_level(level);
+        });
+    }
+
+    fn on_recording_progress(&mut self, elapsed_ms: u64) {
+        let text = format!("Recording {}", format_elapsed(elapsed_ms));
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject
+                .as_mut()
+                .set_status_text(QString::from(text.as_str()));
+        });
+    }
+
+    fn on_key_unsupported(&mut self, message: &str) {
+        let message = message.to_string();
+        let _ = self.qt_thread.queue(move |mut qobject| {
+            qobject
+                .as_mut()
+                .set_status_text(QString::from("Trigger key not found"));
+            qobject.as_mut().set_is_ready(false);
+            qobject
+                .as_mut()
+                .set_status_detail(QString::from(message.as_str()));
+            qobject.as_mut().set_show_key_fix_button(true);
+            qobject.as_mut().error_occurred(QString::from(
+                "The trigger key isn't on the selected keyboard. Choose another key.",
+            ));
+        });
+    }
+
     fn on_error(&mut self, error: &str) {
         let error = error.to_string();
         let _ = self.qt_thread.queue(move |mut qobject| {
@@ -688,4 +1200,26 @@ mod tests {
         );
         assert_eq!(strip_device_prefix(""), "");
     }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(0), "0:00");
+        assert_eq!(format_elapsed(4_300), "0:04");
+        assert_eq!(format_elapsed(75_000), "1:15");
+        assert_eq!(format_elapsed(600_999), "10:00");
+    }
+
+    #[test]
+    fn test_status_icon_name() {
+        assert_eq!(status_icon_name(ServiceStatus::Ready), APP_ICON_NAME);
+        assert_eq!(status_icon_name(ServiceStatus::Recording), "media-record");
+        assert_eq!(
+            status_icon_name(ServiceStatus::Downloading),
+            "folder-download"
+        );
+        assert_eq!(
+            status_icon_name(ServiceStatus::Stopped),
+            status_icon_name(ServiceStatus::Stopping)
+        );
+    }
 }
@@ -1,36 +1,168 @@
 use anyhow::{Context, Result};
 use ini::Ini;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const SECTION: &str = "escucha";
 
+/// Section mapping window classes / app ids to paste methods.
+const PASTE_OVERRIDES_SECTION: &str = "paste_overrides";
+
+/// Section mapping model aliases to model files and download URLs.
+const MODEL_ALIASES_SECTION: &str = "model_aliases";
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Settings {
     pub key: String,
+    pub cancel_key: String,
+    pub grab_key: bool,
     pub keyboard_device: String,
+    pub trigger_backend: String,
     pub model: String,
+    pub fallback_model: String,
+    pub download_retries: u32,
+    pub download_retry_delay_ms: u32,
+    pub confirm_download: bool,
+    pub offline: bool,
     pub language: String,
+    pub auto_language_sticky: bool,
+    pub numbers_as_digits: bool,
+    pub use_gpu: bool,
+    pub temperature: f32,
+    pub temperature_inc: f32,
+    pub suppress_non_speech: bool,
+    pub trim_result: bool,
+    pub max_segment_len: u32,
+    pub debug_print_whisper: bool,
+    pub denoise: String,
+    pub normalize_audio: bool,
+    pub min_chars: u32,
+    pub drop_fillers: bool,
+    pub post_hook: String,
+    pub post_hook_timeout_ms: u32,
+    pub pre_record_hook: String,
+    pub post_record_hook: String,
+    pub n_threads: u32,
     pub paste_method: String,
+    pub auto_paste: bool,
+    pub output_sink: String,
+    pub sink_file: String,
     pub paste_hotkey: String,
+    pub paste_hotkey_uses_keycode: bool,
+    pub terminal_paste_hotkey: String,
+    pub ydotool_socket: String,
     pub clipboard_paste: String,
     pub clipboard_paste_delay_ms: u32,
+    pub trailing_space: bool,
+    pub paste_suffix: String,
+    pub terminal_safe_paste: bool,
+    pub require_focus: bool,
+    pub min_hold_ms: u32,
+    pub auto_stop_silence_ms: u32,
+    pub busy_press: String,
+    pub queue_depth: u32,
+    pub release_padding_ms: u32,
+    pub arecord_buffer_us: u32,
+    pub arecord_period_us: u32,
+    pub audio_source: String,
+    pub recording_channels: u32,
+    pub recording_rate: u32,
+    pub append_window_ms: u32,
+    pub streaming: bool,
+    pub incremental_paste: bool,
+    pub confirm_before_paste: bool,
+    pub confirm_window_ms: u32,
+    pub log_transcription_detail: bool,
+    pub privacy_mode: bool,
+    pub start_minimized: bool,
+    pub retry_preflight: bool,
+    pub retry_preflight_timeout_secs: u32,
     pub log_file: String,
+    pub recording_dir: String,
+    pub keep_recordings: bool,
+    pub recordings_keep_dir: String,
+    pub keep_recordings_max: u32,
     pub log_level: String,
+    /// `[paste_overrides]` entries: (window class or app id, paste method).
+    pub paste_overrides: Vec<(String, String)>,
+    /// `[model_aliases]` entries: (alias, model file and/or download URL).
+    pub model_aliases: Vec<(String, String)>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             key: "KEY_RIGHTCTRL".into(),
+            cancel_key: String::new(),
+            grab_key: false,
             keyboard_device: "auto".into(),
+            trigger_backend: "evdev".into(),
             model: "base.en".into(),
+            fallback_model: String::new(),
+            download_retries: 3,
+            download_retry_delay_ms: 2000,
+            confirm_download: false,
+            offline: false,
             language: "en".into(),
+            auto_language_sticky: false,
+            numbers_as_digits: false,
+            use_gpu: false,
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            suppress_non_speech: false,
+            trim_result: true,
+            max_segment_len: 0,
+            debug_print_whisper: false,
+            denoise: "off".into(),
+            normalize_audio: false,
+            min_chars: 0,
+            drop_fillers: false,
+            post_hook: String::new(),
+            post_hook_timeout_ms: 5000,
+            pre_record_hook: String::new(),
+            post_record_hook: String::new(),
+            n_threads: 0,
             paste_method: "auto".into(),
+            auto_paste: true,
+            output_sink: "paste".into(),
+            sink_file: "auto".into(),
             paste_hotkey: "ctrl+v".into(),
+            paste_hotkey_uses_keycode: false,
+            terminal_paste_hotkey: String::new(),
+            ydotool_socket: String::new(),
             clipboard_paste: "auto".into(),
             clipboard_paste_delay_ms: 75,
+            trailing_space: true,
+            paste_suffix: String::new(),
+            terminal_safe_paste: false,
+            require_focus: false,
+            min_hold_ms: 0,
+            auto_stop_silence_ms: 0,
+            busy_press: "queue".into(),
+            queue_depth: 3,
+            release_padding_ms: 0,
+            arecord_buffer_us: 0,
+            arecord_period_us: 0,
+            audio_source: String::new(),
+            recording_channels: 1,
+            recording_rate: 16000,
+            append_window_ms: 0,
+            streaming: false,
+            incremental_paste: false,
+            confirm_before_paste: false,
+            confirm_window_ms: 5000,
+            log_transcription_detail: false,
+            privacy_mode: false,
+            start_minimized: true,
+            retry_preflight: false,
+            retry_preflight_timeout_secs: 300,
             log_file: default_log_file(),
+            recording_dir: "auto".into(),
+            keep_recordings: false,
+            recordings_keep_dir: "auto".into(),
+            keep_recordings_max: 20,
             log_level: "info".into(),
+            paste_overrides: Vec::new(),
+            model_aliases: Vec::new(),
         }
     }
 }
@@ -41,8 +173,20 @@ pub fn config_dir() -> PathBuf {
         .join("escucha")
 }
 
+/// Environment variable naming an alternative config file (absolute path).
+pub const CONFIG_ENV: &str = "ESCUCHA_CONFIG";
+
+/// The config file: `$ESCUCHA_CONFIG` if set to an absolute path, else
+/// `config.ini` in `config_dir()`.
 pub fn config_path() -> PathBuf {
-    config_dir().join("config.ini")
+    config_path_with_override(std::env::var_os(CONFIG_ENV))
+}
+
+fn config_path_with_override(env: Option<std::ffi::OsString>) -> PathBuf {
+    match env.map(PathBuf::from) {
+        Some(path) if path.is_absolute() => path,
+        _ => config_dir().join("config.ini"),
+    }
 }
 
 fn default_log_file() -> String {
@@ -61,42 +205,321 @@ fn get_or_default(ini: &Ini, key: &str, default: &str) -> String {
         .to_string()
 }
 
+fn get_bool_or_default(ini: &Ini, key: &str, default: bool) -> bool {
+    match ini
+        .get_from(Some(SECTION), key)
+        .map(|v| v.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("true" | "yes" | "on" | "1") => true,
+        Some("false" | "no" | "off" | "0") => false,
+        _ => default,
+    }
+}
+
 fn get_u32_or_default(ini: &Ini, key: &str, default: u32) -> u32 {
     ini.get_from(Some(SECTION), key)
         .and_then(|v| v.parse().ok())
         .unwrap_or(default)
 }
 
+fn get_f32_or_default(ini: &Ini, key: &str, default: f32) -> f32 {
+    ini.get_from(Some(SECTION), key)
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Keys of the `[escucha]` section, each overridable by an
+/// `ESCUCHA_<UPPERCASE_KEY>` environment variable.
+const KEYS: &[&str] = &[
+    "key",
+    "cancel_key",
+    "grab_key",
+    "keyboard_device",
+    "trigger_backend",
+    "model",
+    "fallback_model",
+    "download_retries",
+    "download_retry_delay_ms",
+    "confirm_download",
+    "offline",
+    "language",
+    "auto_language_sticky",
+    "numbers_as_digits",
+    "use_gpu",
+    "temperature",
+    "temperature_inc",
+    "suppress_non_speech",
+    "trim_result",
+    "max_segment_len",
+    "debug_print_whisper",
+    "denoise",
+    "normalize_audio",
+    "min_chars",
+    "drop_fillers",
+    "post_hook",
+    "post_hook_timeout_ms",
+    "pre_record_hook",
+    "post_record_hook",
+    "n_threads",
+    "paste_method",
+    "auto_paste",
+    "output_sink",
+    "sink_file",
+    "paste_hotkey",
+    "paste_hotkey_uses_keycode",
+    "terminal_paste_hotkey",
+    "ydotool_socket",
+    "clipboard_paste",
+    "clipboard_paste_delay_ms",
+    "trailing_space",
+    "paste_suffix",
+    "terminal_safe_paste",
+    "require_focus",
+    "min_hold_ms",
+    "auto_stop_silence_ms",
+    "busy_press",
+    "queue_depth",
+    "release_padding_ms",
+    "arecord_buffer_us",
+    "arecord_period_us",
+    "audio_source",
+    "recording_channels",
+    "recording_rate",
+    "append_window_ms",
+    "streaming",
+    "incremental_paste",
+    "confirm_before_paste",
+    "confirm_window_ms",
+    "log_transcription_detail",
+    "privacy_mode",
+    "start_minimized",
+    "retry_preflight",
+    "retry_preflight_timeout_secs",
+    "log_file",
+    "recording_dir",
+    "keep_recordings",
+    "recordings_keep_dir",
+    "keep_recordings_max",
+    "log_level",
+];
+
+/// Environment variable overriding a setting, e.g. `ESCUCHA_MODEL`.
+pub fn env_var_name(key: &str) -> String {
+    format!("ESCUCHA_{}", key.to_ascii_uppercase())
+}
+
+/// Load settings with precedence defaults < config file < environment.
 pub fn load_settings() -> Result<Settings> {
-    load_settings_from(config_path())
+    load_settings_with(&config_path(), |name| std::env::var(name).ok())
+}
+
+fn load_settings_with(path: &Path, lookup: impl Fn(&str) -> Option<String>) -> Result<Settings> {
+    let mut ini = read_ini(path)?;
+    apply_env_overrides(&mut ini, lookup);
+    Ok(settings_from_ini(&ini))
 }
 
+/// The settings escucha would run with (defaults, config file and
+/// environment overrides) as INI, for `--print-config`.
+pub fn effective_config() -> Result<String> {
+    Ok(settings_to_string(&load_settings()?))
+}
+
+fn settings_to_string(settings: &Settings) -> String {
+    let mut buf = Vec::new();
+    // Writing to a Vec can't fail.
+    let _ = settings_to_ini(settings).write_to(&mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+/// Load settings from a config file only, ignoring environment overrides.
+/// Use this before saving so overrides aren't written back to the file.
 pub fn load_settings_from(path: PathBuf) -> Result<Settings> {
-    let defaults = Settings::default();
+    Ok(settings_from_ini(&read_ini(&path)?))
+}
 
+fn read_ini(path: &Path) -> Result<Ini> {
     if !path.exists() {
-        return Ok(defaults);
+        return Ok(Ini::new());
     }
+    Ini::load_from_file(path)
+        .with_context(|| format!("Failed to load config from {}", path.display()))
+}
+
+fn apply_env_overrides(ini: &mut Ini, lookup: impl Fn(&str) -> Option<String>) {
+    for key in KEYS {
+        if let Some(value) = lookup(&env_var_name(key)) {
+            ini.with_section(Some(SECTION)).set(*key, value);
+        }
+    }
+}
 
-    let ini = Ini::load_from_file(&path)
-        .with_context(|| format!("Failed to load config from {}", path.display()))?;
+fn settings_from_ini(ini: &Ini) -> Settings {
+    let defaults = Settings::default();
 
-    Ok(Settings {
-        key: get_or_default(&ini, "key", &defaults.key),
-        keyboard_device: get_or_default(&ini, "keyboard_device", &defaults.keyboard_device),
-        model: get_or_default(&ini, "model", &defaults.model),
-        language: get_or_default(&ini, "language", &defaults.language),
-        paste_method: get_or_default(&ini, "paste_method", &defaults.paste_method),
-        paste_hotkey: get_or_default(&ini, "paste_hotkey", &defaults.paste_hotkey),
-        clipboard_paste: get_or_default(&ini, "clipboard_paste", &defaults.clipboard_paste),
+    Settings {
+        key: get_or_default(ini, "key", &defaults.key),
+        cancel_key: get_or_default(ini, "cancel_key", &defaults.cancel_key),
+        grab_key: get_bool_or_default(ini, "grab_key", defaults.grab_key),
+        keyboard_device: get_or_default(ini, "keyboard_device", &defaults.keyboard_device),
+        trigger_backend: get_or_default(ini, "trigger_backend", &defaults.trigger_backend),
+        model: get_or_default(ini, "model", &defaults.model),
+        fallback_model: get_or_default(ini, "fallback_model", &defaults.fallback_model),
+        download_retries: get_u32_or_default(ini, "download_retries", defaults.download_retries),
+        download_retry_delay_ms: get_u32_or_default(
+            ini,
+            "download_retry_delay_ms",
+            defaults.download_retry_delay_ms,
+        ),
+        confirm_download: get_bool_or_default(ini, "confirm_download", defaults.confirm_download),
+        offline: get_bool_or_default(ini, "offline", defaults.offline),
+        language: get_or_default(ini, "language", &defaults.language),
+        auto_language_sticky: get_bool_or_default(
+            ini,
+            "auto_language_sticky",
+            defaults.auto_language_sticky,
+        ),
+        numbers_as_digits: get_bool_or_default(
+            ini,
+            "numbers_as_digits",
+            defaults.numbers_as_digits,
+        ),
+        use_gpu: get_bool_or_default(ini, "use_gpu", defaults.use_gpu),
+        temperature: get_f32_or_default(ini, "temperature", defaults.temperature),
+        temperature_inc: get_f32_or_default(ini, "temperature_inc", defaults.temperature_inc),
+        suppress_non_speech: get_bool_or_default(
+            ini,
+            "suppress_non_speech",
+            defaults.suppress_non_speech,
+        ),
+        trim_result: get_bool_or_default(ini, "trim_result", defaults.trim_result),
+        max_segment_len: get_u32_or_default(ini, "max_segment_len", defaults.max_segment_len),
+        debug_print_whisper: get_bool_or_default(
+            ini,
+            "debug_print_whisper",
+            defaults.debug_print_whisper,
+        ),
+        denoise: get_or_default(ini, "denoise", &defaults.denoise),
+        normalize_audio: get_bool_or_default(ini, "normalize_audio", defaults.normalize_audio),
+        min_chars: get_u32_or_default(ini, "min_chars", defaults.min_chars),
+        drop_fillers: get_bool_or_default(ini, "drop_fillers", defaults.drop_fillers),
+        post_hook: get_or_default(ini, "post_hook", &defaults.post_hook),
+        post_hook_timeout_ms: get_u32_or_default(
+            ini,
+            "post_hook_timeout_ms",
+            defaults.post_hook_timeout_ms,
+        ),
+        pre_record_hook: get_or_default(ini, "pre_record_hook", &defaults.pre_record_hook),
+        post_record_hook: get_or_default(ini, "post_record_hook", &defaults.post_record_hook),
+        n_threads: get_u32_or_default(ini, "n_threads", defaults.n_threads),
+        paste_method: get_or_default(ini, "paste_method", &defaults.paste_method),
+        auto_paste: get_bool_or_default(ini, "auto_paste", defaults.auto_paste),
+        output_sink: get_or_default(ini, "output_sink", &defaults.output_sink),
+        sink_file: get_or_default(ini, "sink_file", &defaults.sink_file),
+        paste_hotkey: get_or_default(ini, "paste_hotkey", &defaults.paste_hotkey),
+        paste_hotkey_uses_keycode: get_bool_or_default(
+            ini,
+            "paste_hotkey_uses_keycode",
+            defaults.paste_hotkey_uses_keycode,
+        ),
+        terminal_paste_hotkey: get_or_default(
+            ini,
+            "terminal_paste_hotkey",
+            &defaults.terminal_paste_hotkey,
+        ),
+        ydotool_socket: get_or_default(ini, "ydotool_socket", &defaults.ydotool_socket),
+        clipboard_paste: get_or_default(ini, "clipboard_paste", &defaults.clipboard_paste),
         clipboard_paste_delay_ms: get_u32_or_default(
-            &ini,
+            ini,
             "clipboard_paste_delay_ms",
             defaults.clipboard_paste_delay_ms,
         ),
-        log_file: get_or_default(&ini, "log_file", &defaults.log_file),
-        log_level: get_or_default(&ini, "log_level", &defaults.log_level),
-    })
+        trailing_space: get_bool_or_default(ini, "trailing_space", defaults.trailing_space),
+        paste_suffix: get_or_default(ini, "paste_suffix", &defaults.paste_suffix),
+        terminal_safe_paste: get_bool_or_default(
+            ini,
+            "terminal_safe_paste",
+            defaults.terminal_safe_paste,
+        ),
+        require_focus: get_bool_or_default(ini, "require_focus", defaults.require_focus),
+        min_hold_ms: get_u32_or_default(ini, "min_hold_ms", defaults.min_hold_ms),
+        auto_stop_silence_ms: get_u32_or_default(
+            ini,
+            "auto_stop_silence_ms",
+            defaults.auto_stop_silence_ms,
+        ),
+        busy_press: get_or_default(ini, "busy_press", &defaults.busy_press),
+        queue_depth: get_u32_or_default(ini, "queue_depth", defaults.queue_depth),
+        release_padding_ms: get_u32_or_default(
+            ini,
+            "release_padding_ms",
+            defaults.release_padding_ms,
+        ),
+        arecord_buffer_us: get_u32_or_default(ini, "arecord_buffer_us", defaults.arecord_buffer_us),
+        arecord_period_us: get_u32_or_default(ini, "arecord_period_us", defaults.arecord_period_us),
+        audio_source: get_or_default(ini, "audio_source", &defaults.audio_source),
+        recording_channels: get_u32_or_default(
+            ini,
+            "recording_channels",
+            defaults.recording_channels,
+        ),
+        recording_rate: get_u32_or_default(ini, "recording_rate", defaults.recording_rate),
+        append_window_ms: get_u32_or_default(ini, "append_window_ms", defaults.append_window_ms),
+        streaming: get_bool_or_default(ini, "streaming", defaults.streaming),
+        incremental_paste: get_bool_or_default(
+            ini,
+            "incremental_paste",
+            defaults.incremental_paste,
+        ),
+        confirm_before_paste: get_bool_or_default(
+            ini,
+            "confirm_before_paste",
+            defaults.confirm_before_paste,
+        ),
+        confirm_window_ms: get_u32_or_default(ini, "confirm_window_ms", defaults.confirm_window_ms),
+        log_transcription_detail: get_bool_or_default(
+            ini,
+            "log_transcription_detail",
+            defaults.log_transcription_detail,
+        ),
+        privacy_mode: get_bool_or_default(ini, "privacy_mode", defaults.privacy_mode),
+        start_minimized: get_bool_or_default(ini, "start_minimized", defaults.start_minimized),
+        retry_preflight: get_bool_or_default(ini, "retry_preflight", defaults.retry_preflight),
+        retry_preflight_timeout_secs: get_u32_or_default(
+            ini,
+            "retry_preflight_timeout_secs",
+            defaults.retry_preflight_timeout_secs,
+        ),
+        log_file: get_or_default(ini, "log_file", &defaults.log_file),
+        recording_dir: get_or_default(ini, "recording_dir", &defaults.recording_dir),
+        keep_recordings: get_bool_or_default(ini, "keep_recordings", defaults.keep_recordings),
+        recordings_keep_dir: get_or_default(
+            ini,
+            "recordings_keep_dir",
+            &defaults.recordings_keep_dir,
+        ),
+        keep_recordings_max: get_u32_or_default(
+            ini,
+            "keep_recordings_max",
+            defaults.keep_recordings_max,
+        ),
+        log_level: get_or_default(ini, "log_level", &defaults.log_level),
+        paste_overrides: section_entries(ini, PASTE_OVERRIDES_SECTION),
+        model_aliases: section_entries(ini, MODEL_ALIASES_SECTION),
+    }
+}
+
+/// Trimmed key-value pairs of a free-form section, in file order.
+fn section_entries(ini: &Ini, section: &str) -> Vec<(String, String)> {
+    ini.section(Some(section))
+        .map(|section| {
+            section
+                .iter()
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 pub fn ensure_default_config() -> Result<PathBuf> {
@@ -105,32 +528,273 @@ pub fn ensure_default_config() -> Result<PathBuf> {
         return Ok(path);
     }
 
+    save_settings_to(&path, &Settings::default())?;
+    Ok(path)
+}
+
+/// Persist the options that differ between `before` and `after` to the
+/// user config file, keeping everything else in it as written.
+pub fn save_changed_settings(before: &Settings, after: &Settings) -> Result<()> {
+    update_config_values(&config_path(), &changed_values(before, after))
+}
+
+/// `[escucha]` keys whose saved value differs between `before` and `after`.
+fn changed_values(before: &Settings, after: &Settings) -> Vec<(String, String)> {
+    let before = settings_to_ini(before);
+    let after = settings_to_ini(after);
+    let Some(section) = after.section(Some(SECTION)) else {
+        return Vec::new();
+    };
+    section
+        .iter()
+        .filter(|(key, value)| before.get_from(Some(SECTION), key) != Some(*value))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Set `changes` in the `[escucha]` section of the config file at `path`,
+/// creating the file if needed. Unlike `save_settings_to`, comments, unknown
+/// keys and other sections are left untouched.
+pub fn update_config_values(path: &Path, changes: &[(String, String)]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read config {}", path.display()));
+        }
+    };
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create config dir {}", parent.display()))?;
     }
+    std::fs::write(path, set_section_values(&text, SECTION, changes))
+        .with_context(|| format!("Failed to write config to {}", path.display()))
+}
 
-    let defaults = Settings::default();
+/// `text` with each key in `changes` set in `[section]`: existing lines are
+/// rewritten in place, new keys are added at the end of the section.
+fn set_section_values(text: &str, section: &str, changes: &[(String, String)]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut written = vec![false; changes.len()];
+    let mut in_section = false;
+    let mut section_end = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_section {
+                section_end = Some(lines.len());
+            }
+            in_section = trimmed
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .is_some_and(|name| name.trim() == section);
+        } else if in_section
+            && let Some(key) = line_key(trimmed)
+            && let Some(i) = changes.iter().position(|(k, _)| k == key)
+        {
+            lines.push(ini_line(&changes[i].0, &changes[i].1));
+            written[i] = true;
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if in_section {
+        section_end = Some(lines.len());
+    }
+
+    let added = changes
+        .iter()
+        .zip(&written)
+        .filter(|(_, written)| !**written)
+        .map(|((key, value), _)| ini_line(key, value));
+    match section_end {
+        Some(mut end) => {
+            // Before the blank lines separating it from the next section.
+            while end > 0 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            lines.splice(end..end, added);
+        }
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{section}]"));
+            lines.extend(added);
+        }
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// The key of a `key=value` (or `key: value`) line; `None` for comments.
+fn line_key(line: &str) -> Option<&str> {
+    if line.starts_with(';') || line.starts_with('#') {
+        return None;
+    }
+    let end = line.find(['=', ':'])?;
+    Some(line[..end].trim())
+}
+
+/// One `key=value` line, escaped the way `Ini::write_to_file` writes it.
+fn ini_line(key: &str, value: &str) -> String {
+    let mut ini = Ini::new();
+    ini.with_general_section().set(key, value);
+    let mut buf = Vec::new();
+    if ini.write_to(&mut buf).is_err() {
+        return format!("{key}={value}");
+    }
+    String::from_utf8_lossy(&buf).trim_end().to_string()
+}
+
+pub fn save_settings_to(path: &Path, settings: &Settings) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create config dir {}", parent.display()))?;
+    }
+
+    settings_to_ini(settings)
+        .write_to_file(path)
+        .with_context(|| format!("Failed to write config to {}", path.display()))
+}
+
+fn settings_to_ini(settings: &Settings) -> Ini {
     let mut ini = Ini::new();
     ini.with_section(Some(SECTION))
-        .set("key", &defaults.key)
-        .set("keyboard_device", &defaults.keyboard_device)
-        .set("model", &defaults.model)
-        .set("language", &defaults.language)
-        .set("paste_method", &defaults.paste_method)
-        .set("paste_hotkey", &defaults.paste_hotkey)
-        .set("clipboard_paste", &defaults.clipboard_paste)
+        .set("key", &settings.key)
+        .set("cancel_key", &settings.cancel_key)
+        .set("grab_key", settings.grab_key.to_string())
+        .set("keyboard_device", &settings.keyboard_device)
+        .set("trigger_backend", &settings.trigger_backend)
+        .set("model", &settings.model)
+        .set("fallback_model", &settings.fallback_model)
+        .set("download_retries", settings.download_retries.to_string())
+        .set(
+            "download_retry_delay_ms",
+            settings.download_retry_delay_ms.to_string(),
+        )
+        .set("confirm_download", settings.confirm_download.to_string())
+        .set("offline", settings.offline.to_string())
+        .set("language", &settings.language)
+        .set(
+            "auto_language_sticky",
+            settings.auto_language_sticky.to_string(),
+        )
+        .set("numbers_as_digits", settings.numbers_as_digits.to_string())
+        .set("use_gpu", settings.use_gpu.to_string())
+        .set("temperature", settings.temperature.to_string())
+        .set("temperature_inc", settings.temperature_inc.to_string())
+        .set(
+            "suppress_non_speech",
+            settings.suppress_non_speech.to_string(),
+        )
+        .set("trim_result", settings.trim_result.to_string())
+        .set("max_segment_len", settings.max_segment_len.to_string())
+        .set(
+            "debug_print_whisper",
+            settings.debug_print_whisper.to_string(),
+        )
+        .set("denoise", &settings.denoise)
+        .set("normalize_audio", settings.normalize_audio.to_string())
+        .set("min_chars", settings.min_chars.to_string())
+        .set("drop_fillers", settings.drop_fillers.to_string())
+        .set("post_hook", &settings.post_hook)
+        .set(
+            "post_hook_timeout_ms",
+            settings.post_hook_timeout_ms.to_string(),
+        )
+        .set("pre_record_hook", &settings.pre_record_hook)
+        .set("post_record_hook", &settings.post_record_hook)
+        .set("n_threads", settings.n_threads.to_string())
+        .set("paste_method", &settings.paste_method)
+        .set("auto_paste", settings.auto_paste.to_string())
+        .set("output_sink", &settings.output_sink)
+        .set("sink_file", &settings.sink_file)
+        .set("paste_hotkey", &settings.paste_hotkey)
+        .set(
+            "paste_hotkey_uses_keycode",
+            settings.paste_hotkey_uses_keycode.to_string(),
+        )
+        .set("terminal_paste_hotkey", &settings.terminal_paste_hotkey)
+        .set("ydotool_socket", &settings.ydotool_socket)
+        .set("clipboard_paste", &settings.clipboard_paste)
         .set(
             "clipboard_paste_delay_ms",
-            defaults.clipboard_paste_delay_ms.to_string(),
+            settings.clipboard_paste_delay_ms.to_string(),
+        )
+        .set("trailing_space", settings.trailing_space.to_string())
+        .set("paste_suffix", &settings.paste_suffix)
+        .set(
+            "terminal_safe_paste",
+            settings.terminal_safe_paste.to_string(),
+        )
+        .set("require_focus", settings.require_focus.to_string())
+        .set("min_hold_ms", settings.min_hold_ms.to_string())
+        .set(
+            "auto_stop_silence_ms",
+            settings.auto_stop_silence_ms.to_string(),
+        )
+        .set("busy_press", &settings.busy_press)
+        .set("queue_depth", settings.queue_depth.to_string())
+        .set(
+            "release_padding_ms",
+            settings.release_padding_ms.to_string(),
+        )
+        .set("arecord_buffer_us", settings.arecord_buffer_us.to_string())
+        .set("arecord_period_us", settings.arecord_period_us.to_string())
+        .set("audio_source", &settings.audio_source)
+        .set(
+            "recording_channels",
+            settings.recording_channels.to_string(),
         )
-        .set("log_file", &defaults.log_file)
-        .set("log_level", &defaults.log_level);
+        .set("recording_rate", settings.recording_rate.to_string())
+        .set("append_window_ms", settings.append_window_ms.to_string())
+        .set("streaming", settings.streaming.to_string())
+        .set("incremental_paste", settings.incremental_paste.to_string())
+        .set(
+            "confirm_before_paste",
+            settings.confirm_before_paste.to_string(),
+        )
+        .set("confirm_window_ms", settings.confirm_window_ms.to_string())
+        .set(
+            "log_transcription_detail",
+            settings.log_transcription_detail.to_string(),
+        )
+        .set("privacy_mode", settings.privacy_mode.to_string())
+        .set("start_minimized", settings.start_minimized.to_string())
+        .set("retry_preflight", settings.retry_preflight.to_string())
+        .set(
+            "retry_preflight_timeout_secs",
+            settings.retry_preflight_timeout_secs.to_string(),
+        )
+        .set("log_file", &settings.log_file)
+        .set("recording_dir", &settings.recording_dir)
+        .set("keep_recordings", settings.keep_recordings.to_string())
+        .set("recordings_keep_dir", &settings.recordings_keep_dir)
+        .set(
+            "keep_recordings_max",
+            settings.keep_recordings_max.to_string(),
+        )
+        .set("log_level", &settings.log_level);
 
-    ini.write_to_file(&path)
-        .with_context(|| format!("Failed to write config to {}", path.display()))?;
+    if !settings.paste_overrides.is_empty() {
+        let mut section = ini.with_section(Some(PASTE_OVERRIDES_SECTION));
+        for (class, method) in &settings.paste_overrides {
+            section.set(class, method);
+        }
+    }
+    if !settings.model_aliases.is_empty() {
+        let mut section = ini.with_section(Some(MODEL_ALIASES_SECTION));
+        for (alias, target) in &settings.model_aliases {
+            section.set(alias, target);
+        }
+    }
 
-    Ok(path)
+    ini
 }
 
 #[cfg(test)]
@@ -143,13 +807,164 @@ mod tests {
         let s = Settings::default();
         assert_eq!(s.key, "KEY_RIGHTCTRL");
         assert_eq!(s.keyboard_device, "auto");
+        assert_eq!(s.output_sink, "paste");
+        assert!(!s.auto_language_sticky);
+        assert_eq!(s.ydotool_socket, "");
+        assert_eq!(s.post_hook, "");
+        assert_eq!(s.post_hook_timeout_ms, 5000);
+        assert_eq!(s.pre_record_hook, "");
+        assert_eq!(s.post_record_hook, "");
+        assert!(s.trim_result);
+        assert_eq!(s.max_segment_len, 0);
+        assert!(!s.debug_print_whisper);
+        assert_eq!(s.denoise, "off");
+        assert!(!s.normalize_audio);
+        assert_eq!(s.busy_press, "queue");
+        assert_eq!(s.terminal_paste_hotkey, "");
+        assert_eq!(s.audio_source, "");
+        assert_eq!(s.auto_stop_silence_ms, 0);
+        assert_eq!(s.queue_depth, 3);
+        assert!(!s.retry_preflight);
+        assert_eq!(s.retry_preflight_timeout_secs, 300);
+        assert_eq!(s.sink_file, "auto");
+        assert_eq!(s.trigger_backend, "evdev");
         assert_eq!(s.model, "base.en");
         assert_eq!(s.language, "en");
+        assert!(!s.numbers_as_digits);
         assert_eq!(s.paste_method, "auto");
         assert_eq!(s.paste_hotkey, "ctrl+v");
+        assert!(!s.paste_hotkey_uses_keycode);
         assert_eq!(s.clipboard_paste, "auto");
         assert_eq!(s.clipboard_paste_delay_ms, 75);
+        assert!(s.trailing_space);
+        assert_eq!(s.paste_suffix, "");
+        assert!(s.auto_paste);
+        assert!(!s.terminal_safe_paste);
+        assert!(!s.require_focus);
+        assert_eq!(s.min_hold_ms, 0);
+        assert!(s.cancel_key.is_empty());
+        assert!(!s.grab_key);
+        assert_eq!(s.release_padding_ms, 0);
+        assert_eq!(s.arecord_buffer_us, 0);
+        assert_eq!(s.arecord_period_us, 0);
+        assert_eq!(s.recording_channels, 1);
+        assert_eq!(s.recording_rate, 16000);
+        assert_eq!(s.append_window_ms, 0);
+        assert!(!s.streaming);
+        assert!(!s.incremental_paste);
+        assert!(!s.confirm_before_paste);
+        assert_eq!(s.confirm_window_ms, 5000);
+        assert!(!s.log_transcription_detail);
+        assert!(!s.privacy_mode);
+        assert!(s.fallback_model.is_empty());
+        assert_eq!(s.download_retries, 3);
+        assert_eq!(s.download_retry_delay_ms, 2000);
+        assert!(!s.confirm_download);
+        assert!(!s.offline);
+        assert!(s.start_minimized);
+        assert_eq!(s.recording_dir, "auto");
+        assert!(!s.keep_recordings);
+        assert_eq!(s.recordings_keep_dir, "auto");
+        assert_eq!(s.keep_recordings_max, 20);
+        assert!(!s.use_gpu);
+        assert_eq!(s.temperature, 0.0);
+        assert_eq!(s.temperature_inc, 0.2);
+        assert!(!s.suppress_non_speech);
+        assert_eq!(s.min_chars, 0);
+        assert!(!s.drop_fillers);
+        assert_eq!(s.n_threads, 0);
         assert_eq!(s.log_level, "info");
+        assert!(s.paste_overrides.is_empty());
+        assert!(s.model_aliases.is_empty());
+    }
+
+    #[test]
+    fn test_config_path_env_override() {
+        assert_eq!(
+            config_path_with_override(Some("/etc/escucha/work.ini".into())),
+            PathBuf::from("/etc/escucha/work.ini")
+        );
+        let default = config_dir().join("config.ini");
+        assert_eq!(config_path_with_override(None), default);
+        // Relative paths are ambiguous for a daemon; they are ignored.
+        assert_eq!(config_path_with_override(Some("work.ini".into())), default);
+        assert_eq!(config_path_with_override(Some("".into())), default);
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        std::fs::write(&path, "[escucha]\nmodel = tiny.en\nlanguage = es\n").unwrap();
+
+        let mut ini = read_ini(&path).unwrap();
+        apply_env_overrides(&mut ini, |name| match name {
+            "ESCUCHA_MODEL" => Some("small.en".into()),
+            "ESCUCHA_N_THREADS" => Some("4".into()),
+            "ESCUCHA_STREAMING" => Some("yes".into()),
+            _ => None,
+        });
+        let settings = settings_from_ini(&ini);
+        assert_eq!(settings.model, "small.en");
+        assert_eq!(settings.n_threads, 4);
+        assert!(settings.streaming);
+        // Not overridden: the file value, then the default.
+        assert_eq!(settings.language, "es");
+        assert_eq!(settings.paste_method, "auto");
+    }
+
+    #[test]
+    fn test_env_overrides_without_config_file() {
+        let mut ini = read_ini(Path::new("/nonexistent/config.ini")).unwrap();
+        apply_env_overrides(&mut ini, |name| {
+            (name == "ESCUCHA_KEY").then(|| "KEY_F13".to_string())
+        });
+        let settings = settings_from_ini(&ini);
+        assert_eq!(settings.key, "KEY_F13");
+        assert_eq!(settings.model, "base.en");
+    }
+
+    #[test]
+    fn test_effective_config_includes_env_overrides() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        std::fs::write(&path, "[escucha]\nmodel = tiny.en\nlanguage = es\n").unwrap();
+
+        let settings = load_settings_with(&path, |name| {
+            (name == "ESCUCHA_MODEL").then(|| "small.en".to_string())
+        })
+        .unwrap();
+        let printed = settings_to_string(&settings);
+        let lines: Vec<&str> = printed.lines().collect();
+        assert_eq!(lines[0], "[escucha]");
+        assert!(lines.contains(&"model=small.en"));
+        assert!(lines.contains(&"language=es"));
+        // Unset keys show their defaults.
+        assert!(lines.contains(&"paste_method=auto"));
+    }
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(env_var_name("model"), "ESCUCHA_MODEL");
+        assert_eq!(
+            env_var_name("clipboard_paste_delay_ms"),
+            "ESCUCHA_CLIPBOARD_PASTE_DELAY_MS"
+        );
+    }
+
+    #[test]
+    fn test_keys_match_saved_settings() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        save_settings_to(&path, &Settings::default()).unwrap();
+        let ini = Ini::load_from_file(&path).unwrap();
+        let saved: Vec<&str> = ini
+            .section(Some(SECTION))
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(saved, KEYS);
     }
 
     #[test]
@@ -188,12 +1003,72 @@ mod tests {
         ini.with_section(Some(SECTION))
             .set("key", "KEY_RIGHTCTRL")
             .set("keyboard_device", "/dev/input/event5")
+            .set("trigger_backend", "portal")
+            .set("output_sink", "file")
+            .set("auto_language_sticky", "true")
+            .set("ydotool_socket", "/run/user/1000/.ydotool_socket")
+            .set("post_hook", "tr a-z A-Z")
+            .set("post_hook_timeout_ms", "800")
+            .set("pre_record_hook", "playerctl pause")
+            .set("post_record_hook", "playerctl play")
+            .set("trim_result", "false")
+            .set("max_segment_len", "80")
+            .set("debug_print_whisper", "true")
+            .set("denoise", "highpass")
+            .set("normalize_audio", "true")
+            .set("busy_press", "ignore")
+            .set("terminal_paste_hotkey", "ctrl+shift+v")
+            .set("audio_source", "alsa_input.usb-Yeti-00.analog-stereo")
+            .set("auto_stop_silence_ms", "1200")
+            .set("queue_depth", "5")
+            .set("retry_preflight", "true")
+            .set("retry_preflight_timeout_secs", "60")
+            .set("sink_file", "~/notes.md")
             .set("model", "small.en")
             .set("language", "es")
+            .set("numbers_as_digits", "on")
             .set("paste_method", "xdotool")
             .set("paste_hotkey", "ctrl+shift+v")
+            .set("paste_hotkey_uses_keycode", "true")
             .set("clipboard_paste", "off")
             .set("clipboard_paste_delay_ms", "100")
+            .set("trailing_space", "off")
+            .set("paste_suffix", r"\n")
+            .set("auto_paste", "false")
+            .set("terminal_safe_paste", "on")
+            .set("require_focus", "true")
+            .set("min_hold_ms", "250")
+            .set("cancel_key", "KEY_ESC")
+            .set("grab_key", "on")
+            .set("release_padding_ms", "300")
+            .set("arecord_buffer_us", "40000")
+            .set("arecord_period_us", "10000")
+            .set("recording_channels", "2")
+            .set("recording_rate", "48000")
+            .set("append_window_ms", "1500")
+            .set("streaming", "on")
+            .set("incremental_paste", "on")
+            .set("confirm_before_paste", "true")
+            .set("confirm_window_ms", "8000")
+            .set("log_transcription_detail", "yes")
+            .set("privacy_mode", "true")
+            .set("fallback_model", "small.en")
+            .set("download_retries", "5")
+            .set("download_retry_delay_ms", "500")
+            .set("confirm_download", "yes")
+            .set("offline", "true")
+            .set("start_minimized", "no")
+            .set("recording_dir", "/mnt/ram")
+            .set("keep_recordings", "true")
+            .set("recordings_keep_dir", "/tmp/kept")
+            .set("keep_recordings_max", "5")
+            .set("use_gpu", "true")
+            .set("temperature", "0.3")
+            .set("temperature_inc", "0")
+            .set("suppress_non_speech", "on")
+            .set("min_chars", "3")
+            .set("drop_fillers", "true")
+            .set("n_threads", "6")
             .set("log_file", "/tmp/test.log")
             .set("log_level", "debug");
         ini.write_to_file(&path).unwrap();
@@ -201,12 +1076,75 @@ mod tests {
         let settings = load_settings_from(path).unwrap();
         assert_eq!(settings.key, "KEY_RIGHTCTRL");
         assert_eq!(settings.keyboard_device, "/dev/input/event5");
+        assert_eq!(settings.trigger_backend, "portal");
+        assert_eq!(settings.output_sink, "file");
+        assert!(settings.auto_language_sticky);
+        assert_eq!(settings.ydotool_socket, "/run/user/1000/.ydotool_socket");
+        assert_eq!(settings.post_hook, "tr a-z A-Z");
+        assert_eq!(settings.post_hook_timeout_ms, 800);
+        assert_eq!(settings.pre_record_hook, "playerctl pause");
+        assert_eq!(settings.post_record_hook, "playerctl play");
+        assert!(!settings.trim_result);
+        assert_eq!(settings.max_segment_len, 80);
+        assert!(settings.debug_print_whisper);
+        assert_eq!(settings.denoise, "highpass");
+        assert!(settings.normalize_audio);
+        assert_eq!(settings.busy_press, "ignore");
+        assert_eq!(settings.terminal_paste_hotkey, "ctrl+shift+v");
+        assert_eq!(
+            settings.audio_source,
+            "alsa_input.usb-Yeti-00.analog-stereo"
+        );
+        assert_eq!(settings.auto_stop_silence_ms, 1200);
+        assert_eq!(settings.queue_depth, 5);
+        assert!(settings.retry_preflight);
+        assert_eq!(settings.retry_preflight_timeout_secs, 60);
+        assert_eq!(settings.sink_file, "~/notes.md");
         assert_eq!(settings.model, "small.en");
         assert_eq!(settings.language, "es");
+        assert!(settings.numbers_as_digits);
         assert_eq!(settings.paste_method, "xdotool");
         assert_eq!(settings.paste_hotkey, "ctrl+shift+v");
+        assert!(settings.paste_hotkey_uses_keycode);
         assert_eq!(settings.clipboard_paste, "off");
         assert_eq!(settings.clipboard_paste_delay_ms, 100);
+        assert!(!settings.trailing_space);
+        assert_eq!(settings.paste_suffix, r"\n");
+        assert!(!settings.auto_paste);
+        assert!(settings.terminal_safe_paste);
+        assert!(settings.require_focus);
+        assert_eq!(settings.min_hold_ms, 250);
+        assert_eq!(settings.cancel_key, "KEY_ESC");
+        assert!(settings.grab_key);
+        assert_eq!(settings.release_padding_ms, 300);
+        assert_eq!(settings.arecord_buffer_us, 40000);
+        assert_eq!(settings.arecord_period_us, 10000);
+        assert_eq!(settings.recording_channels, 2);
+        assert_eq!(settings.recording_rate, 48000);
+        assert_eq!(settings.append_window_ms, 1500);
+        assert!(settings.streaming);
+        assert!(settings.incremental_paste);
+        assert!(settings.confirm_before_paste);
+        assert_eq!(settings.confirm_window_ms, 8000);
+        assert!(settings.log_transcription_detail);
+        assert!(settings.privacy_mode);
+        assert_eq!(settings.fallback_model, "small.en");
+        assert_eq!(settings.download_retries, 5);
+        assert_eq!(settings.download_retry_delay_ms, 500);
+        assert!(settings.confirm_download);
+        assert!(settings.offline);
+        assert!(!settings.start_minimized);
+        assert_eq!(settings.recording_dir, "/mnt/ram");
+        assert!(settings.keep_recordings);
+        assert_eq!(settings.recordings_keep_dir, "/tmp/kept");
+        assert_eq!(settings.keep_recordings_max, 5);
+        assert!(settings.use_gpu);
+        assert_eq!(settings.temperature, 0.3);
+        assert_eq!(settings.temperature_inc, 0.0);
+        assert!(settings.suppress_non_speech);
+        assert_eq!(settings.min_chars, 3);
+        assert!(settings.drop_fillers);
+        assert_eq!(settings.n_threads, 6);
         assert_eq!(settings.log_file, "/tmp/test.log");
         assert_eq!(settings.log_level, "debug");
     }
@@ -246,4 +1184,148 @@ mod tests {
         assert_eq!(settings.key, "KEY_RIGHTCTRL");
         assert_eq!(settings.model, "base.en");
     }
+
+    #[test]
+    fn test_load_paste_overrides() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        std::fs::write(
+            &path,
+            "[escucha]\nmodel = tiny.en\n\n[paste_overrides]\nfirefox = wl-copy\norg.kde.konsole = ydotool\n",
+        )
+        .unwrap();
+
+        let settings = load_settings_from(path).unwrap();
+        assert_eq!(settings.model, "tiny.en");
+        assert_eq!(
+            settings.paste_overrides,
+            vec![
+                ("firefox".to_string(), "wl-copy".to_string()),
+                ("org.kde.konsole".to_string(), "ydotool".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_model_aliases() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        std::fs::write(
+            &path,
+            "[escucha]\nmodel = mywork\n\n[model_aliases]\n\
+             mywork = https://example.com/models/ggml-mywork-q5_0.bin\n\
+             nas = ~/models/ggml-large-v3.bin\n",
+        )
+        .unwrap();
+
+        let settings = load_settings_from(path).unwrap();
+        assert_eq!(settings.model, "mywork");
+        assert_eq!(
+            settings.model_aliases,
+            vec![
+                (
+                    "mywork".to_string(),
+                    "https://example.com/models/ggml-mywork-q5_0.bin".to_string()
+                ),
+                ("nas".to_string(), "~/models/ggml-large-v3.bin".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_settings_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("subdir").join("config.ini");
+
+        let settings = Settings {
+            key: "KEY_CAPSLOCK".into(),
+            model: "small.en".into(),
+            paste_method: "ydotool".into(),
+            clipboard_paste_delay_ms: 120,
+            paste_overrides: vec![
+                ("firefox".into(), "wl-copy".into()),
+                ("kitty".into(), "ydotool".into()),
+            ],
+            model_aliases: vec![(
+                "mywork".into(),
+                "ggml-mywork.bin https://example.com/ggml-mywork.bin".into(),
+            )],
+            ..Settings::default()
+        };
+        save_settings_to(&path, &settings).unwrap();
+
+        let loaded = load_settings_from(path).unwrap();
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_update_config_values_keeps_rest_of_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.ini");
+        let original = "\
+# My escucha setup
+[escucha]
+; the key I like
+key = KEY_RIGHTCTRL
+model = base.en
+future_option = kept
+
+[paste_overrides]
+kitty = ydotool
+";
+        std::fs::write(&path, original).unwrap();
+
+        let before = load_settings_from(path.clone()).unwrap();
+        let after = Settings {
+            model: "small".into(),
+            language: "es".into(),
+            ..before.clone()
+        };
+        let changes = changed_values(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                ("model".to_string(), "small".to_string()),
+                ("language".to_string(), "es".to_string())
+            ]
+        );
+        update_config_values(&path, &changes).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "\
+# My escucha setup
+[escucha]
+; the key I like
+key = KEY_RIGHTCTRL
+model=small
+future_option = kept
+language=es
+
+[paste_overrides]
+kitty = ydotool
+"
+        );
+        assert_eq!(load_settings_from(path).unwrap(), after);
+    }
+
+    #[test]
+    fn test_update_config_values_creates_section() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("new").join("config.ini");
+        let changes = [("model".to_string(), "tiny.en".to_string())];
+        update_config_values(&path, &changes).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[escucha]\nmodel=tiny.en\n"
+        );
+
+        std::fs::write(&path, "# only a comment\n").unwrap();
+        update_config_values(&path, &changes).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# only a comment\n\n[escucha]\nmodel=tiny.en\n"
+        );
+        assert_eq!(load_settings_from(path).unwrap().model, "tiny.en");
+    }
 }
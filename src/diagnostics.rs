@@ -1,18 +1,27 @@
 use crate::{audio, config, input, paste, preflight, transcribe};
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-#[derive(Serialize)]
+/// Version of the `DiagnoseReport` JSON shape. Bump it when fields are added,
+/// removed or change type, not when their values do.
+const SCHEMA_VERSION: u32 = 4;
+
+/// Structured report printed by `--diagnose` and `--smoke-test`.
+#[derive(Serialize, JsonSchema)]
 pub struct DiagnoseReport {
     schema_version: u32,
     app_version: String,
     command: String,
     unix_timestamp_ms: u128,
     ok: bool,
+    /// Set by `--redact`: user, home paths and device names are replaced by
+    /// placeholders and log lines are omitted.
+    redacted: bool,
     environment: EnvironmentInfo,
     permissions: PermissionInfo,
     preflight: PreflightInfo,
@@ -20,7 +29,7 @@ pub struct DiagnoseReport {
     smoke_test: Option<SmokeTestInfo>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct EnvironmentInfo {
     wayland_display: Option<String>,
     x11_display: Option<String>,
@@ -28,11 +37,16 @@ struct EnvironmentInfo {
     xdg_current_desktop: Option<String>,
     gui_autostart_enabled: bool,
     gui_autostart_path: String,
+    config_file: String,
     command_available: BTreeMap<String, bool>,
     user_service_state: BTreeMap<String, String>,
+    gpu_build_supported: bool,
+    compute_backend: String,
+    /// Other dictation tools or ydotool daemons that may compete for input.
+    conflicting_tools: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct PermissionInfo {
     user: String,
     input_group_configured: bool,
@@ -45,14 +59,14 @@ struct PermissionInfo {
     ydotool_socket_available: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct PreflightInfo {
     critical_failures: usize,
     warnings: usize,
     checks: Vec<PreflightCheckInfo>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct PreflightCheckInfo {
     name: String,
     passed: bool,
@@ -61,40 +75,267 @@ struct PreflightCheckInfo {
     hint: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct LogInfo {
     configured_log_file: Option<String>,
     log_file_exists: bool,
     tail_lines: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct SmokeTestInfo {
     duration_ms: u128,
     passed: bool,
     steps: Vec<SmokeStepInfo>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct SmokeStepInfo {
     name: String,
     required: bool,
     status: String,
     detail: String,
     duration_ms: u128,
+    /// Resource use while the step ran; only sampled for `transcription_probe`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<ResourceUsage>,
 }
 
-pub fn run_and_print(command: &str, with_smoke_test: bool) -> Result<bool> {
+/// Best-effort process resource figures from `/proc/self`; a figure that
+/// can't be read is left out.
+#[derive(Serialize, JsonSchema)]
+struct ResourceUsage {
+    /// Peak resident set size of the process so far (`VmHWM`), in KiB.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak_rss_kb: Option<u64>,
+    /// User plus system CPU time used during the step, in milliseconds.
+    /// Above `duration_ms` means more than one core was busy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_ms: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// Usage since `cpu_ticks_before` (from `process_cpu_ticks`), or `None`
+    /// if nothing could be read.
+    fn since(cpu_ticks_before: Option<u64>) -> Option<Self> {
+        let cpu_ms = cpu_ticks_before
+            .zip(process_cpu_ticks())
+            .and_then(|(before, after)| ticks_to_ms(after.saturating_sub(before)));
+        let peak_rss_kb = std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| peak_rss_kb(&status));
+        (cpu_ms.is_some() || peak_rss_kb.is_some()).then_some(Self {
+            peak_rss_kb,
+            cpu_ms,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct BuildInfo {
+    app_version: String,
+    whisper_rs_version: String,
+    gui: GuiBuildInfo,
+    features: Vec<String>,
+    profile: String,
+    target_os: String,
+    target_arch: String,
+}
+
+#[derive(Serialize)]
+struct GuiBuildInfo {
+    qt: bool,
+    gtk: bool,
+}
+
+/// Build provenance for bug reports: versions, GUI toolkits and cargo features.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        whisper_rs_version: option_env!("ESCUCHA_WHISPER_RS_VERSION")
+            .unwrap_or("unknown")
+            .to_string(),
+        // The tray frontend is cxx-qt/QML; there is no GTK frontend compiled in.
+        gui: GuiBuildInfo {
+            qt: true,
+            gtk: false,
+        },
+        features: parse_feature_list(option_env!("ESCUCHA_FEATURES").unwrap_or("")),
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+        .to_string(),
+        target_os: std::env::consts::OS.to_string(),
+        target_arch: std::env::consts::ARCH.to_string(),
+    }
+}
+
+pub fn print_build_info() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&build_info())?);
+    Ok(())
+}
+
+fn parse_feature_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Process exit code for a report, see `exit_code`.
+pub fn run_and_print(command: &str, with_smoke_test: bool, redact: bool) -> Result<i32> {
     let report = run(command, with_smoke_test);
-    println!("{}", serde_json::to_string_pretty(&report)?);
-    Ok(report.ok)
+    let code = report.exit_code();
+    let json = if redact {
+        redacted_json(report, &Redactor::from_system())?
+    } else {
+        serde_json::to_value(&report)?
+    };
+    println!("{}", serde_json::to_string_pretty(&json)?);
+    Ok(code)
+}
+
+/// Exit code when the report fails for any reason without a more specific code.
+const EXIT_FAILURE: i32 = 1;
+
+/// Stable `--smoke-test` exit code for a failing required step, by the
+/// subsystem it checks, so scripts can branch on it. Keep in sync with the
+/// `--smoke-test` help.
+fn step_exit_code(step: &str) -> i32 {
+    match step {
+        "load_settings" => 10,
+        "resolve_trigger_key" | "select_input_device" => 11,
+        "select_paste_method" | "paste_injection" => 12,
+        "audio_capture_roundtrip" => 13,
+        "transcription_probe" => 14,
+        _ => EXIT_FAILURE,
+    }
+}
+
+impl DiagnoseReport {
+    fn exit_code(&self) -> i32 {
+        exit_code(self.ok, self.smoke_test.as_ref())
+    }
+}
+
+/// 0 when everything passed; else the code of the first failing required
+/// smoke-test step (`step_exit_code`), or 1 for preflight failures.
+fn exit_code(ok: bool, smoke_test: Option<&SmokeTestInfo>) -> i32 {
+    if ok {
+        return 0;
+    }
+    smoke_test
+        .iter()
+        .flat_map(|smoke| &smoke.steps)
+        .find(|step| step.required && step.status == "fail")
+        .map_or(EXIT_FAILURE, |step| step_exit_code(&step.name))
+}
+
+/// Replaces machine-identifying strings in a report with placeholders.
+struct Redactor {
+    home: Option<String>,
+    user: String,
+    device_names: Vec<String>,
+}
+
+impl Redactor {
+    fn from_system() -> Self {
+        Self::new(
+            std::env::var("USER").unwrap_or_default(),
+            dirs::home_dir().map(|home| home.display().to_string()),
+            input::list_input_devices()
+                .map(|devices| devices.into_iter().map(|d| d.name).collect())
+                .unwrap_or_default(),
+        )
+    }
+
+    fn new(user: String, home: Option<String>, mut device_names: Vec<String>) -> Self {
+        // Longest first, so a name containing another is replaced whole.
+        device_names.retain(|name| !name.is_empty());
+        device_names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        Self {
+            home: home.filter(|home| home.len() > 1),
+            user,
+            device_names,
+        }
+    }
+
+    fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        if let Some(home) = &self.home {
+            text = text.replace(home.as_str(), "~");
+        }
+        for name in &self.device_names {
+            text = text.replace(name.as_str(), "<device>");
+        }
+        replace_word(&text, &self.user, "<user>")
+    }
+
+    /// Redact every string in a JSON value, in place.
+    fn redact_value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => *s = self.redact(s),
+            serde_json::Value::Array(items) => {
+                items.iter_mut().for_each(|item| self.redact_value(item))
+            }
+            serde_json::Value::Object(fields) => {
+                fields.values_mut().for_each(|v| self.redact_value(v))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace `word` where it isn't part of a longer word, so a short username
+/// like "al" leaves "alsa" alone.
+fn replace_word(text: &str, word: &str, placeholder: &str) -> String {
+    if word.is_empty() {
+        return text.to_string();
+    }
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(word) {
+        let end = pos + word.len();
+        let before_ok = !rest[..pos].chars().next_back().is_some_and(is_word_char);
+        let after_ok = !rest[end..].chars().next().is_some_and(is_word_char);
+        out.push_str(&rest[..pos]);
+        out.push_str(if before_ok && after_ok {
+            placeholder
+        } else {
+            word
+        });
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The report as JSON with identifying strings replaced and log lines
+/// dropped; counts, booleans and structure are kept.
+fn redacted_json(mut report: DiagnoseReport, redactor: &Redactor) -> Result<serde_json::Value> {
+    report.redacted = true;
+    report.logs.tail_lines.clear();
+    let mut json = serde_json::to_value(&report)?;
+    redactor.redact_value(&mut json);
+    Ok(json)
+}
+
+/// Print the JSON Schema for `DiagnoseReport`, for validating `--diagnose` output.
+pub fn print_schema() -> Result<()> {
+    let schema = schemars::schema_for!(DiagnoseReport);
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
 }
 
 pub fn run(command: &str, with_smoke_test: bool) -> DiagnoseReport {
     let settings = config::load_settings();
     let preflight_report = preflight::check_environment();
 
-    let env = collect_environment();
+    let env = collect_environment(settings.as_ref().ok());
     let perms = collect_permissions();
     let preflight = collect_preflight(&preflight_report);
     let logs = collect_logs(settings.as_ref().ok());
@@ -109,11 +350,12 @@ pub fn run(command: &str, with_smoke_test: bool) -> DiagnoseReport {
     let ok = !preflight_report.has_critical_failures() && smoke_ok;
 
     DiagnoseReport {
-        schema_version: 1,
+        schema_version: SCHEMA_VERSION,
         app_version: env!("CARGO_PKG_VERSION").to_string(),
         command: command.to_string(),
         unix_timestamp_ms: now_unix_ms(),
         ok,
+        redacted: false,
         environment: env,
         permissions: perms,
         preflight,
@@ -129,7 +371,7 @@ fn now_unix_ms() -> u128 {
         .as_millis()
 }
 
-fn collect_environment() -> EnvironmentInfo {
+fn collect_environment(settings: Option<&config::Settings>) -> EnvironmentInfo {
     let mut command_available = BTreeMap::new();
     for cmd in [
         "arecord",
@@ -158,8 +400,13 @@ fn collect_environment() -> EnvironmentInfo {
         xdg_current_desktop: std::env::var("XDG_CURRENT_DESKTOP").ok(),
         gui_autostart_enabled: gui_autostart_path().exists(),
         gui_autostart_path: gui_autostart_path().display().to_string(),
+        config_file: config::config_path().display().to_string(),
         command_available,
         user_service_state,
+        gpu_build_supported: transcribe::GPU_SUPPORTED,
+        compute_backend: transcribe::preferred_backend(settings.is_some_and(|s| s.use_gpu))
+            .to_string(),
+        conflicting_tools: preflight::conflicting_tools(),
     }
 }
 
@@ -223,13 +470,22 @@ fn collect_preflight(report: &preflight::PreflightReport) -> PreflightInfo {
 
 fn collect_logs(settings: Option<&config::Settings>) -> LogInfo {
     let configured_log_file = settings.map(|s| s.log_file.clone());
+    let privacy = settings.is_some_and(crate::logging::privacy_mode);
     let (log_file_exists, tail_lines) = configured_log_file
         .as_ref()
         .map(|s| {
-            let path = PathBuf::from(s);
+            let path = crate::logging::expand_home(s.trim());
             let exists = path.exists();
             let lines = if exists {
-                read_tail_lines(&path, 80)
+                let lines = read_tail_lines(&path, 80);
+                if privacy {
+                    lines
+                        .iter()
+                        .map(|l| crate::logging::scrub_line(l))
+                        .collect()
+                } else {
+                    lines
+                }
             } else {
                 Vec::new()
             };
@@ -275,15 +531,15 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
 
     let key = {
         let start = Instant::now();
-        match input::resolve_key(&settings.key) {
-            Ok(key) => {
+        match input::resolve_key_chord(&settings.key) {
+            Ok(keys) => {
                 steps.push(step_pass(
                     "resolve_trigger_key",
                     true,
-                    format!("Resolved {} to {:?}", settings.key, key),
+                    format!("Resolved {} to {:?}", settings.key, keys),
                     start.elapsed(),
                 ));
-                key
+                keys[keys.len() - 1]
             }
             Err(e) => {
                 steps.push(step_fail(
@@ -304,12 +560,20 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
     {
         let start = Instant::now();
         match input::pick_keyboard_device(&settings.keyboard_device, key) {
-            Ok(path) => steps.push(step_pass(
-                "select_input_device",
-                true,
-                format!("Using {}", path.display()),
-                start.elapsed(),
-            )),
+            Ok(path) => match input::check_key_support(&path, key) {
+                Some(warning) => steps.push(step_fail(
+                    "select_input_device",
+                    true,
+                    warning,
+                    start.elapsed(),
+                )),
+                None => steps.push(step_pass(
+                    "select_input_device",
+                    true,
+                    format!("Using {}", path.display()),
+                    start.elapsed(),
+                )),
+            },
             Err(e) => steps.push(step_fail(
                 "select_input_device",
                 true,
@@ -348,44 +612,35 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
                 start.elapsed(),
             ));
         } else {
-            match audio::temp_wav_path() {
-                Ok(path) => match audio::Recording::start(&path) {
+            let dir = audio::recording_dir(&settings.recording_dir);
+            let format = audio::RecordingFormat::from_settings(&settings);
+            match audio::CaptureTiming::from_settings(&settings)
+                .and_then(|timing| Ok((audio::temp_wav_path(&dir)?, timing, format?)))
+            {
+                Ok((path, timing, fmt)) => match audio::Recording::start(&path, &timing, &fmt) {
                     Ok(rec) => {
                         std::thread::sleep(Duration::from_millis(350));
-                        match rec.stop() {
+                        match rec.stop_checked() {
                             Ok(recorded) => {
                                 let size = std::fs::metadata(&recorded)
                                     .map(|m| m.len())
                                     .unwrap_or_default();
-                                if size > 44 {
-                                    steps.push(step_pass(
-                                        "audio_capture_roundtrip",
-                                        true,
-                                        format!(
-                                            "Captured WAV at {} ({} bytes)",
-                                            recorded.display(),
-                                            size
-                                        ),
-                                        start.elapsed(),
-                                    ));
-                                } else {
-                                    steps.push(step_fail(
-                                        "audio_capture_roundtrip",
-                                        true,
-                                        format!(
-                                            "Recorded WAV too small at {} ({} bytes)",
-                                            recorded.display(),
-                                            size
-                                        ),
-                                        start.elapsed(),
-                                    ));
-                                }
+                                steps.push(step_pass(
+                                    "audio_capture_roundtrip",
+                                    true,
+                                    format!(
+                                        "Captured WAV at {} ({} bytes)",
+                                        recorded.display(),
+                                        size
+                                    ),
+                                    start.elapsed(),
+                                ));
                                 wav_path = Some(recorded);
                             }
                             Err(e) => steps.push(step_fail(
                                 "audio_capture_roundtrip",
                                 true,
-                                format!("Failed to stop recording: {e}"),
+                                format!("Recording failed: {e}"),
                                 start.elapsed(),
                             )),
                         }
@@ -400,7 +655,7 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
                 Err(e) => steps.push(step_fail(
                     "audio_capture_roundtrip",
                     true,
-                    format!("Could not create temp WAV path: {e}"),
+                    format!("Could not prepare recording: {e}"),
                     start.elapsed(),
                 )),
             }
@@ -412,12 +667,23 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
         let model_path = transcribe::model_path(&settings.model);
         match (&wav_path, model_path.exists()) {
             (Some(wav), true) => {
-                match transcribe::Transcriber::new(&model_path, &settings.language) {
+                let cpu_before = process_cpu_ticks();
+                match transcribe::Transcriber::new(
+                    &model_path,
+                    &settings.language,
+                    settings.use_gpu,
+                )
+                .map(|t| t.with_threads(settings.n_threads))
+                {
                     Ok(transcriber) => match transcriber.transcribe(wav) {
                         Ok(text) => steps.push(step_pass(
                             "transcription_probe",
                             true,
-                            format!("Transcription completed ({} chars)", text.len()),
+                            format!(
+                                "Transcription completed on {} ({} chars)",
+                                transcriber.backend(),
+                                text.len()
+                            ),
                             start.elapsed(),
                         )),
                         Err(e) => steps.push(step_fail(
@@ -434,6 +700,9 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
                         start.elapsed(),
                     )),
                 }
+                if let Some(step) = steps.last_mut() {
+                    step.resources = ResourceUsage::since(cpu_before);
+                }
             }
             (Some(_), false) => steps.push(step_skip(
                 "transcription_probe",
@@ -458,6 +727,8 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
         audio::cleanup_recording(&path);
     }
 
+    steps.push(paste_probe(&settings));
+
     let passed = steps
         .iter()
         .filter(|s| s.required)
@@ -470,6 +741,89 @@ fn run_smoke_test(settings: Option<&config::Settings>) -> SmokeTestInfo {
     }
 }
 
+/// Text the paste probe sends through the paste pipeline.
+const PASTE_PROBE_TEXT: &str = "escucha smoke test";
+
+/// Run `paste_text` up to injection, check the injection tool can run, then
+/// round-trip the payload through the clipboard with the method's tools.
+/// Nothing is typed into the focused window. The clipboard is only written
+/// when its previous contents could be read, and they are restored after.
+fn paste_probe(settings: &config::Settings) -> SmokeStepInfo {
+    const NAME: &str = "paste_injection";
+    let start = Instant::now();
+    let config = match paste::PasteConfig::from_settings(settings) {
+        Ok(config) => config,
+        Err(e) => {
+            return step_fail(
+                NAME,
+                false,
+                format!("Paste setup failed: {e}"),
+                start.elapsed(),
+            );
+        }
+    };
+
+    let plan = paste::plan_paste(PASTE_PROBE_TEXT, &config);
+    if !plan.payload.starts_with(PASTE_PROBE_TEXT) {
+        return step_fail(
+            NAME,
+            false,
+            format!("Paste pipeline changed the text to {:?}", plan.payload),
+            start.elapsed(),
+        );
+    }
+
+    if let Err(e) = paste::check_injection(plan.method) {
+        return step_fail(
+            NAME,
+            false,
+            format!("{} can't inject keys: {e:#}", plan.method),
+            start.elapsed(),
+        );
+    }
+
+    let previous = match paste::read_clipboard(plan.method) {
+        Ok(previous) => previous,
+        Err(e) => {
+            return step_skip(
+                NAME,
+                false,
+                format!(
+                    "Injection check passed; clipboard not tested, its contents can't be read: {e}"
+                ),
+                start.elapsed(),
+            );
+        }
+    };
+    let roundtrip = paste::copy_to_clipboard(&plan.payload, plan.method)
+        .and_then(|()| paste::read_clipboard(plan.method));
+    let _ = paste::copy_to_clipboard(&previous, plan.method);
+
+    match roundtrip {
+        Ok(text) if text == plan.payload => step_pass(
+            NAME,
+            false,
+            format!("{} clipboard round-trip matched", plan.method),
+            start.elapsed(),
+        ),
+        Ok(text) => step_fail(
+            NAME,
+            false,
+            format!(
+                "{} clipboard returned {text:?}, expected {:?}",
+                plan.method, plan.payload
+            ),
+            start.elapsed(),
+        ),
+        Err(e) => step_skip(
+            NAME,
+            false,
+            format!("Dry run passed; clipboard check unavailable: {e}"),
+            start.elapsed(),
+        ),
+    }
+}
+
 fn read_tail_lines(path: &Path, line_count: usize) -> Vec<String> {
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
@@ -583,6 +937,38 @@ fn input_device_readability() -> (usize, usize) {
     (readable, total)
 }
 
+/// CPU time (user + system) this process has used, in clock ticks.
+fn process_cpu_ticks() -> Option<u64> {
+    cpu_ticks(&std::fs::read_to_string("/proc/self/stat").ok()?)
+}
+
+/// utime + stime from a `/proc/<pid>/stat` line. The command name may contain
+/// spaces, so fields are counted from its closing parenthesis.
+fn cpu_ticks(stat: &str) -> Option<u64> {
+    let mut fields = stat.get(stat.rfind(')')? + 1..)?.split_whitespace();
+    // After the name: state is field 3; utime and stime are fields 14 and 15.
+    let utime: u64 = fields.nth(11)?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+    Some(utime + stime)
+}
+
+fn ticks_to_ms(ticks: u64) -> Option<u64> {
+    // SAFETY: sysconf only reads a system constant.
+    let per_sec = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).ok()?;
+    (per_sec > 0).then(|| ticks * 1000 / per_sec)
+}
+
+/// `VmHWM` (peak resident memory, KiB) from `/proc/<pid>/status`.
+fn peak_rss_kb(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
 fn uinput_mode_string() -> Option<String> {
     use std::os::unix::fs::MetadataExt;
     let meta = std::fs::metadata("/dev/uinput").ok()?;
@@ -613,6 +999,7 @@ fn step_pass(
         status: "pass".to_string(),
         detail: detail.into(),
         duration_ms: elapsed.as_millis(),
+        resources: None,
     }
 }
 
@@ -628,6 +1015,7 @@ fn step_fail(
         status: "fail".to_string(),
         detail: detail.into(),
         duration_ms: elapsed.as_millis(),
+        resources: None,
     }
 }
 
@@ -643,5 +1031,107 @@ fn step_skip(
         status: "skip".to_string(),
         detail: detail.into(),
         duration_ms: elapsed.as_millis(),
+        resources: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_exit_code() {
+        assert_eq!(step_exit_code("load_settings"), 10);
+        assert_eq!(step_exit_code("resolve_trigger_key"), 11);
+        assert_eq!(step_exit_code("select_input_device"), 11);
+        assert_eq!(step_exit_code("select_paste_method"), 12);
+        assert_eq!(step_exit_code("audio_capture_roundtrip"), 13);
+        assert_eq!(step_exit_code("transcription_probe"), 14);
+        assert_eq!(step_exit_code("something_new"), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_exit_code_uses_first_required_failure() {
+        let elapsed = Duration::ZERO;
+        let smoke = SmokeTestInfo {
+            duration_ms: 0,
+            passed: false,
+            steps: vec![
+                step_pass("load_settings", true, "", elapsed),
+                // Optional steps never decide the code.
+                step_fail("paste_injection", false, "", elapsed),
+                step_fail("audio_capture_roundtrip", true, "", elapsed),
+                step_fail("transcription_probe", true, "", elapsed),
+            ],
+        };
+        assert_eq!(exit_code(false, Some(&smoke)), 13);
+        assert_eq!(exit_code(true, Some(&smoke)), 0);
+        // Smoke test passed (or wasn't run) but preflight failed.
+        assert_eq!(exit_code(false, None), EXIT_FAILURE);
+        let passed = SmokeTestInfo {
+            duration_ms: 0,
+            passed: true,
+            steps: vec![step_pass("load_settings", true, "", elapsed)],
+        };
+        assert_eq!(exit_code(false, Some(&passed)), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_redact_removes_username() {
+        let redactor = Redactor::new(
+            "alice".into(),
+            Some("/home/alice".into()),
+            vec!["Keychron K2 SN 00A1".into()],
+        );
+        let mut json = serde_json::json!({
+            "permissions": { "user": "alice", "input_group_configured": true },
+            "environment": { "config_file": "/home/alice/.config/escucha/config.ini" },
+            "checks": [
+                { "message": "Keychron K2 SN 00A1 (/dev/input/event3) does not report KEY_FN" },
+                { "message": "alice is not in the input group" },
+            ],
+        });
+        redactor.redact_value(&mut json);
+
+        let text = json.to_string();
+        assert!(!text.contains("alice"));
+        assert!(!text.contains("Keychron"));
+        assert_eq!(json["permissions"]["user"], "<user>");
+        assert_eq!(json["permissions"]["input_group_configured"], true);
+        assert_eq!(
+            json["environment"]["config_file"],
+            "~/.config/escucha/config.ini"
+        );
+        assert_eq!(
+            json["checks"][0]["message"],
+            "<device> (/dev/input/event3) does not report KEY_FN"
+        );
+    }
+
+    #[test]
+    fn test_replace_word() {
+        assert_eq!(
+            replace_word("al uses alsa", "al", "<user>"),
+            "<user> uses alsa"
+        );
+        assert_eq!(replace_word("user=al,", "al", "<user>"), "user=<user>,");
+        assert_eq!(replace_word("nothing", "", "<user>"), "nothing");
+    }
+
+    #[test]
+    fn test_cpu_ticks() {
+        let stat = "4242 (escucha (gui)) S 1 4242 4242 0 -1 4194304 5000 0 12 0 \
+                    150 30 0 0 20 0 8 0 100 1000000 2000";
+        assert_eq!(cpu_ticks(stat), Some(180));
+        assert_eq!(cpu_ticks("4242 (escucha) S 1"), None);
+        assert_eq!(cpu_ticks(""), None);
+    }
+
+    #[test]
+    fn test_peak_rss_kb() {
+        let status =
+            "Name:\tescucha\nVmPeak:\t  900000 kB\nVmHWM:\t  512340 kB\nVmRSS:\t  400000 kB\n";
+        assert_eq!(peak_rss_kb(status), Some(512340));
+        assert_eq!(peak_rss_kb("Name:\tescucha\n"), None);
     }
 }
@@ -0,0 +1,167 @@
+//! `--json-events`: newline-delimited JSON on stdout as the daemon runs,
+//! for editor and IDE plugins that show dictation state. Logs still go to
+//! stderr.
+
+use serde::Serialize;
+use std::io::Write;
+
+use crate::service::{ServiceCallbacks, ServiceStatus};
+
+/// One line of the event stream, e.g. `{"type":"status","value":"recording"}`.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Status(String),
+    /// A transient hint, e.g. "Too short - recording discarded".
+    Message(&'a str),
+    Text(&'a str),
+    PartialText(&'a str),
+    Error(&'a str),
+    /// Input level from 0.0 to 1.0, about every 100ms while recording.
+    AudioLevel(f32),
+    RecordingProgress(u64),
+    KeyUnsupported(&'a str),
+}
+
+impl Event<'_> {
+    pub fn to_json(&self) -> String {
+        // Every variant is a string or a number, which can't fail to serialize.
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Writes each callback as an `Event` line to `out`, then passes it on to
+/// `inner` (the usual logging callbacks).
+pub struct JsonEvents<W, C> {
+    out: W,
+    inner: C,
+    /// Set once a write failed, so a closed pipe is only reported once.
+    failed: bool,
+}
+
+impl<W: Write + Send, C: ServiceCallbacks> JsonEvents<W, C> {
+    pub fn new(out: W, inner: C) -> Self {
+        Self {
+            out,
+            inner,
+            failed: false,
+        }
+    }
+
+    fn emit(&mut self, event: Event<'_>) {
+        let result = writeln!(self.out, "{}", event.to_json()).and_then(|()| self.out.flush());
+        if let Err(e) = result
+            && !std::mem::replace(&mut self.failed, true)
+        {
+            log::warn!("Failed to write JSON event: {e}");
+        }
+    }
+}
+
+impl<W: Write + Send, C: ServiceCallbacks> ServiceCallbacks for JsonEvents<W, C> {
+    fn on_status(&mut self, status: ServiceStatus) {
+        self.emit(Event::Status(status.to_string()));
+        self.inner.on_status(status);
+    }
+    fn on_status_msg(&mut self, msg: &str) {
+        self.emit(Event::Message(msg));
+        self.inner.on_status_msg(msg);
+    }
+    fn on_text(&mut self, text: &str) {
+        self.emit(Event::Text(text));
+        self.inner.on_text(text);
+    }
+    fn on_partial_text(&mut self, text: &str) {
+        self.emit(Event::PartialText(text));
+        self.inner.on_partial_text(text);
+    }
+    fn on_error(&mut self, error: &str) {
+        self.emit(Event::Error(error));
+        self.inner.on_error(error);
+    }
+    fn on_audio_level(&mut self, level: f32) {
+        self.emit(Event::AudioLevel(level));
+        self.inner.on_audio_level(level);
+    }
+    fn on_recording_progress(&mut self, elapsed_ms: u64) {
+        self.emit(Event::RecordingProgress(elapsed_ms));
+        self.inner.on_recording_progress(elapsed_ms);
+    }
+    fn on_key_unsupported(&mut self, message: &str) {
+        self.emit(Event::KeyUnsupported(message));
+        self.inner.on_key_unsupported(message);
+    }
+    fn on_tick(&mut self) {
+        self.inner.on_tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        assert_eq!(
+            Event::Status(ServiceStatus::Recording.to_string()).to_json(),
+            r#"{"type":"status","value":"recording"}"#
+        );
+        assert_eq!(
+            Event::Message("Cancelled").to_json(),
+            r#"{"type":"message","value":"Cancelled"}"#
+        );
+        assert_eq!(
+            Event::Text("say \"hi\"\n").to_json(),
+            r#"{"type":"text","value":"say \"hi\"\n"}"#
+        );
+        assert_eq!(
+            Event::PartialText("hel").to_json(),
+            r#"{"type":"partial_text","value":"hel"}"#
+        );
+        assert_eq!(
+            Event::Error("Paste failed").to_json(),
+            r#"{"type":"error","value":"Paste failed"}"#
+        );
+        assert_eq!(
+            Event::AudioLevel(0.5).to_json(),
+            r#"{"type":"audio_level","value":0.5}"#
+        );
+        assert_eq!(
+            Event::RecordingProgress(2000).to_json(),
+            r#"{"type":"recording_progress","value":2000}"#
+        );
+        assert_eq!(
+            Event::KeyUnsupported("no KEY_FN").to_json(),
+            r#"{"type":"key_unsupported","value":"no KEY_FN"}"#
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorded {
+        texts: Vec<String>,
+    }
+
+    impl ServiceCallbacks for Recorded {
+        fn on_status(&mut self, _status: ServiceStatus) {}
+        fn on_status_msg(&mut self, _msg: &str) {}
+        fn on_text(&mut self, text: &str) {
+            self.texts.push(text.to_string());
+        }
+        fn on_error(&mut self, _error: &str) {}
+    }
+
+    #[test]
+    fn test_json_events_writes_lines_and_forwards() {
+        let mut events = JsonEvents::new(Vec::new(), Recorded::default());
+        events.on_status(ServiceStatus::Transcribing);
+        events.on_text("hola");
+        events.on_tick();
+        let out = String::from_utf8(events.out).unwrap();
+        assert_eq!(
+            out,
+            "{\"type\":\"status\",\"value\":\"transcribing\"}\n\
+             {\"type\":\"text\",\"value\":\"hola\"}\n"
+        );
+        assert_eq!(events.inner.texts, vec!["hola"]);
+    }
+}
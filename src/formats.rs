@@ -0,0 +1,152 @@
+//! Output formats for `--transcribe --format`: plain text, SRT and WebVTT
+//! subtitles built from segment timings, or the full result as JSON.
+
+use anyhow::Result;
+
+use crate::transcribe::{Segment, Transcription};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Txt,
+    Srt,
+    Vtt,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "txt" => Some(OutputFormat::Txt),
+            "srt" => Some(OutputFormat::Srt),
+            "vtt" => Some(OutputFormat::Vtt),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Render a transcription; every format ends with a newline.
+pub fn render(format: OutputFormat, transcription: &Transcription) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Txt => format!("{}\n", transcription.text),
+        OutputFormat::Srt => srt(&transcription.segments),
+        OutputFormat::Vtt => vtt(&transcription.segments),
+        OutputFormat::Json => format!("{}\n", serde_json::to_string_pretty(transcription)?),
+    })
+}
+
+/// Numbered SubRip cues. Segments with no text are skipped and don't use
+/// up a number.
+pub fn srt(segments: &[Segment]) -> String {
+    cues(segments)
+        .enumerate()
+        .map(|(i, segment)| {
+            format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                timestamp(segment.start_ms, ','),
+                timestamp(segment.end_ms, ','),
+                segment.text.trim()
+            )
+        })
+        .collect()
+}
+
+/// A WebVTT file: the header, then one cue per segment.
+pub fn vtt(segments: &[Segment]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for segment in cues(segments) {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            timestamp(segment.start_ms, '.'),
+            timestamp(segment.end_ms, '.'),
+            segment.text.trim()
+        ));
+    }
+    out
+}
+
+fn cues(segments: &[Segment]) -> impl Iterator<Item = &Segment> {
+    segments.iter().filter(|s| !s.text.trim().is_empty())
+}
+
+/// "HH:MM:SS,mmm" (SRT) or "HH:MM:SS.mmm" (WebVTT).
+fn timestamp(ms: u64, separator: char) -> String {
+    let secs = ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.into(),
+            confidence: 0.9,
+        }
+    }
+
+    fn two_segments() -> Vec<Segment> {
+        vec![
+            segment(0, 2500, " Hello there."),
+            segment(2500, 3_661_042, " General Kenobi."),
+        ]
+    }
+
+    #[test]
+    fn test_srt() {
+        assert_eq!(
+            srt(&two_segments()),
+            "1\n00:00:00,000 --> 00:00:02,500\nHello there.\n\n\
+             2\n00:00:02,500 --> 01:01:01,042\nGeneral Kenobi.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_srt_skips_empty_segments() {
+        let segments = vec![segment(0, 1000, " "), segment(1000, 2000, "Hi")];
+        assert_eq!(srt(&segments), "1\n00:00:01,000 --> 00:00:02,000\nHi\n\n");
+    }
+
+    #[test]
+    fn test_vtt() {
+        assert_eq!(
+            vtt(&two_segments()),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:02.500\nHello there.\n\n\
+             00:00:02.500 --> 01:01:01.042\nGeneral Kenobi.\n\n"
+        );
+        assert_eq!(vtt(&[]), "WEBVTT\n\n");
+    }
+
+    #[test]
+    fn test_render_txt_and_json() {
+        let transcription = Transcription {
+            text: "Hello there. General Kenobi.".into(),
+            confidence: 0.9,
+            segments: two_segments(),
+        };
+        assert_eq!(
+            render(OutputFormat::Txt, &transcription).unwrap(),
+            "Hello there. General Kenobi.\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&render(OutputFormat::Json, &transcription).unwrap()).unwrap();
+        assert_eq!(json["segments"][1]["start_ms"], 2500);
+        assert_eq!(json["text"], "Hello there. General Kenobi.");
+    }
+
+    #[test]
+    fn test_output_format_from_name() {
+        assert_eq!(OutputFormat::from_name("srt"), Some(OutputFormat::Srt));
+        assert_eq!(OutputFormat::from_name("SRT"), None);
+    }
+}
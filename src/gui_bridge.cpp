@@ -13,6 +13,8 @@ std::int32_t run_qml_app()
   std::array<char*, 2> argv = { app_name, nullptr };
 
   QApplication app(argc, argv.data());
+  // The tray icon keeps running when the settings window is closed.
+  app.setQuitOnLastWindowClosed(false);
   QQmlApplicationEngine engine;
   engine.load(QUrl(QStringLiteral("qrc:/qt/qml/io/github/escucha/src/qml/Main.qml")));
   return app.exec();
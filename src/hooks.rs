@@ -0,0 +1,237 @@
+//! User shell commands run on the transcription (`post_hook`) and around
+//! recording (`pre_record_hook`, `post_record_hook`).
+
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::config::Settings;
+
+/// How often a running hook is checked for exit.
+const HOOK_POLL: Duration = Duration::from_millis(10);
+
+/// Command that rewrites the transcription: the text goes to its stdin and
+/// its stdout is pasted instead (`post_hook`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostHook {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+impl PostHook {
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let command = settings.post_hook.trim();
+        (!command.is_empty()).then(|| Self {
+            command: command.to_string(),
+            timeout: Duration::from_millis(u64::from(settings.post_hook_timeout_ms)),
+        })
+    }
+
+    /// Run the hook on `text`. Fails if it exits non-zero or outlives the
+    /// timeout; the caller keeps the original text then.
+    pub fn apply(&self, text: &str) -> Result<String> {
+        let output = run_with_timeout(&self.command, Some(text), self.timeout)?;
+        Ok(output.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// How long a recording hook may run before it is killed.
+const RECORD_HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Commands fired when recording starts and stops, e.g. `playerctl pause` /
+/// `playerctl play`. They run in order on a worker thread, so the loop never
+/// waits for them and a stop hook can't overtake its start hook.
+pub struct RecordHooks {
+    pre: Option<String>,
+    post: Option<String>,
+    worker: mpsc::Sender<String>,
+}
+
+impl RecordHooks {
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let command = |setting: &str| {
+            let setting = setting.trim();
+            (!setting.is_empty()).then(|| setting.to_string())
+        };
+        let pre = command(&settings.pre_record_hook);
+        let post = command(&settings.post_record_hook);
+        if pre.is_none() && post.is_none() {
+            return None;
+        }
+        let (worker, commands) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for command in commands {
+                if let Err(e) = run_with_timeout(&command, None, RECORD_HOOK_TIMEOUT) {
+                    log::warn!("Recording hook failed: {e:#}");
+                }
+            }
+        });
+        Some(Self { pre, post, worker })
+    }
+
+    /// Fire `pre_record_hook`, just before arecord is started.
+    pub fn before_recording(&self) {
+        self.fire(self.pre.as_ref());
+    }
+
+    /// Fire `post_record_hook`, once arecord has stopped.
+    pub fn after_recording(&self) {
+        self.fire(self.post.as_ref());
+    }
+
+    fn fire(&self, command: Option<&String>) {
+        if let Some(command) = command {
+            let _ = self.worker.send(command.clone());
+        }
+    }
+}
+
+/// Run `command` with `sh -c`, feeding it `input` and returning its stdout.
+/// The command is killed once `timeout` passes.
+fn run_with_timeout(command: &str, input: Option<&str>, timeout: Duration) -> Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        // Own process group, so a timeout also kills what the shell started.
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("Failed to run hook: {command}"))?;
+
+    // Feed stdin and drain stdout on their own threads, so a hook that
+    // writes before reading all its input can't deadlock against us.
+    let writer = child.stdin.take().zip(input).map(|(mut stdin, input)| {
+        let input = input.to_string();
+        std::thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+    let reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut out = Vec::new();
+            let _ = stdout.read_to_end(&mut out);
+            out
+        })
+    });
+
+    let status = wait_with_timeout(&mut child, timeout);
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    let status = status.with_context(|| format!("Hook failed: {command}"))?;
+    let stdout = reader
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+    if !status.success() {
+        bail!("Hook exited with {status}: {command}");
+    }
+    String::from_utf8(stdout).context("Hook output is not UTF-8")
+}
+
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<ExitStatus> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            if let Ok(pid) = i32::try_from(child.id()) {
+                // SAFETY: signals only the hook's own process group.
+                unsafe { libc::kill(-pid, libc::SIGKILL) };
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("timed out after {}ms", timeout.as_millis());
+        }
+        std::thread::sleep(HOOK_POLL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hook(command: &str, timeout_ms: u64) -> PostHook {
+        PostHook {
+            command: command.into(),
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+
+    #[test]
+    fn test_post_hook_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(PostHook::from_settings(&settings), None);
+        settings.post_hook = " tr a-z A-Z ".into();
+        settings.post_hook_timeout_ms = 1500;
+        assert_eq!(
+            PostHook::from_settings(&settings),
+            Some(hook("tr a-z A-Z", 1500))
+        );
+    }
+
+    #[test]
+    fn test_post_hook_replaces_text() {
+        let upper = hook("tr a-z A-Z", 5000);
+        assert_eq!(upper.apply("hello world").unwrap(), "HELLO WORLD");
+        // A trailing newline from the hook isn't pasted.
+        let echo = hook("cat; echo", 5000);
+        assert_eq!(echo.apply("hola").unwrap(), "hola");
+    }
+
+    #[test]
+    fn test_post_hook_failure() {
+        let err = hook("cat >/dev/null; exit 3", 5000)
+            .apply("hola")
+            .unwrap_err();
+        assert!(err.to_string().contains("exited"));
+    }
+
+    #[test]
+    fn test_record_hooks_run_in_order() {
+        assert!(RecordHooks::from_settings(&Settings::default()).is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("hooks.log");
+        let settings = Settings {
+            // A slow start hook must still finish before the stop hook runs.
+            pre_record_hook: format!("sleep 0.2; echo pre >> {}", log.display()),
+            post_record_hook: format!("echo post >> {}", log.display()),
+            ..Settings::default()
+        };
+        let hooks = RecordHooks::from_settings(&settings).unwrap();
+        let started = Instant::now();
+        hooks.before_recording();
+        hooks.after_recording();
+        // Firing doesn't wait for the commands.
+        assert!(started.elapsed() < Duration::from_millis(200));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while std::fs::read_to_string(&log)
+            .unwrap_or_default()
+            .lines()
+            .count()
+            < 2
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "pre\npost\n");
+    }
+
+    #[test]
+    fn test_post_hook_timeout() {
+        let started = Instant::now();
+        let err = hook("sleep 5", 100).apply("hola").unwrap_err();
+        assert!(format!("{err:#}").contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
@@ -1,14 +1,68 @@
 use anyhow::{Context, Result, bail};
-use evdev::Key;
-use std::path::PathBuf;
+use evdev::{InputEventKind, Key};
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct InputDevice {
     pub path: PathBuf,
     pub name: String,
+    /// Keys and buttons the device advertises (`supported_keys`).
+    pub keys: Vec<Key>,
 }
 
-/// List all /dev/input/event* devices with their names.
+impl InputDevice {
+    /// Display label in the form "/dev/input/eventN - Device Name".
+    pub fn label(&self) -> String {
+        format!("{} - {}", self.path.display(), self.name)
+    }
+
+    /// Whether the device advertises `key` in its capabilities.
+    pub fn supports(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Whether the device can type letters, i.e. is a real keyboard rather
+    /// than a button panel, power switch or bare mouse.
+    fn has_letter_keys(&self) -> bool {
+        LETTER_KEYS.iter().all(|&key| self.supports(key))
+    }
+}
+
+const LETTER_KEYS: [Key; 26] = [
+    Key::KEY_A,
+    Key::KEY_B,
+    Key::KEY_C,
+    Key::KEY_D,
+    Key::KEY_E,
+    Key::KEY_F,
+    Key::KEY_G,
+    Key::KEY_H,
+    Key::KEY_I,
+    Key::KEY_J,
+    Key::KEY_K,
+    Key::KEY_L,
+    Key::KEY_M,
+    Key::KEY_N,
+    Key::KEY_O,
+    Key::KEY_P,
+    Key::KEY_Q,
+    Key::KEY_R,
+    Key::KEY_S,
+    Key::KEY_T,
+    Key::KEY_U,
+    Key::KEY_V,
+    Key::KEY_W,
+    Key::KEY_X,
+    Key::KEY_Y,
+    Key::KEY_Z,
+];
+
+/// List all /dev/input/event* devices with their names and capabilities.
 pub fn list_input_devices() -> Result<Vec<InputDevice>> {
     let mut devices = Vec::new();
 
@@ -23,18 +77,9 @@ pub fn list_input_devices() -> Result<Vec<InputDevice>> {
             continue;
         }
 
-        match evdev::Device::open(&path) {
-            Ok(device) => {
-                let name = device.name().unwrap_or("Unknown").to_string();
-                devices.push(InputDevice {
-                    path: path.clone(),
-                    name,
-                });
-            }
-            Err(_) => {
-                // Skip devices we can't open (permission issues)
-                continue;
-            }
+        // Skip devices we can't open (permission issues)
+        if let Some(device) = open_device(&path) {
+            devices.push(device);
         }
     }
 
@@ -42,26 +87,115 @@ pub fn list_input_devices() -> Result<Vec<InputDevice>> {
     Ok(devices)
 }
 
-/// Filter out mice, touchpads, and virtual devices from device list.
-pub fn filter_keyboards(devices: &[InputDevice]) -> Vec<&InputDevice> {
+/// Read a device's name and capabilities, or `None` if it can't be opened.
+fn open_device(path: &Path) -> Option<InputDevice> {
+    let device = evdev::Device::open(path).ok()?;
+    let name = device.name().unwrap_or("Unknown").to_string();
+    let keys = device
+        .supported_keys()
+        .map(|keys| keys.iter().collect())
+        .unwrap_or_default();
+    Some(InputDevice {
+        path: path.to_path_buf(),
+        name,
+        keys,
+    })
+}
+
+/// Whether the device may be grabbed (EVIOCGRAB) while `keys` are held.
+/// A grab blocks every key on the device, so a trigger that includes a
+/// typing key (letters, digits, space, enter, ...) is never grabbed.
+pub fn grab_is_safe(keys: &[Key]) -> bool {
+    const TYPING_KEYS: &[Key] = &[
+        Key::KEY_1,
+        Key::KEY_2,
+        Key::KEY_3,
+        Key::KEY_4,
+        Key::KEY_5,
+        Key::KEY_6,
+        Key::KEY_7,
+        Key::KEY_8,
+        Key::KEY_9,
+        Key::KEY_0,
+        Key::KEY_SPACE,
+        Key::KEY_ENTER,
+        Key::KEY_BACKSPACE,
+        Key::KEY_TAB,
+    ];
+    !keys.is_empty()
+        && !keys
+            .iter()
+            .any(|key| LETTER_KEYS.contains(key) || TYPING_KEYS.contains(key))
+}
+
+/// Keys that make good dictation triggers, offered when the configured key
+/// isn't on the selected device.
+const SUGGESTED_TRIGGERS: &[Key] = &[
+    Key::KEY_RIGHTCTRL,
+    Key::KEY_RIGHTALT,
+    Key::KEY_RIGHTMETA,
+    Key::KEY_CAPSLOCK,
+    Key::KEY_SCROLLLOCK,
+    Key::KEY_PAUSE,
+    Key::KEY_INSERT,
+    Key::KEY_COMPOSE,
+    Key::KEY_F13,
+    Key::KEY_F14,
+    Key::KEY_F15,
+];
+
+/// Warning for a device that doesn't advertise the trigger key, so holding
+/// it would never start dictation. Suggests keys the device does have.
+fn missing_key_message(device: &InputDevice, key: Key) -> Option<String> {
+    if device.supports(key) {
+        return None;
+    }
+    let suggestions: Vec<String> = SUGGESTED_TRIGGERS
+        .iter()
+        .filter(|&&k| device.supports(k))
+        .map(|&k| key_to_name(k))
+        .collect();
+    let mut message = format!(
+        "{} ({}) does not report {}, so it will never trigger dictation. \
+         Choose a different key or keyboard_device",
+        device.name,
+        device.path.display(),
+        key_to_name(key)
+    );
+    if !suggestions.is_empty() {
+        message.push_str(&format!("; this device has {}", suggestions.join(", ")));
+    }
+    Some(message)
+}
+
+/// Check that the device at `path` can deliver `key`. Returns a warning
+/// naming alternatives if not; `None` if it can, or can't be opened.
+pub fn check_key_support(path: &Path, key: Key) -> Option<String> {
+    missing_key_message(&open_device(path)?, key)
+}
+
+/// Keep the devices that can trigger dictation: any device advertising the
+/// `trigger` key, plus keyboards with letter keys whose name doesn't mark them
+/// as a mouse, touchpad or virtual device (gaming mice and ydotool's virtual
+/// device advertise a full key set too).
+pub fn filter_keyboards(devices: &[InputDevice], trigger: Option<Key>) -> Vec<&InputDevice> {
     let exclude_patterns = ["mouse", "touchpad", "trackpoint", "trackball", "virtual"];
     devices
         .iter()
         .filter(|d| {
+            if trigger.is_some_and(|key| d.supports(key)) {
+                return true;
+            }
             let lower = d.name.to_lowercase();
-            !exclude_patterns.iter().any(|pat| lower.contains(pat))
+            d.has_letter_keys() && !exclude_patterns.iter().any(|pat| lower.contains(pat))
         })
         .collect()
 }
 
-/// Check if a device supports a specific key in its capabilities.
-fn device_supports_key(path: &std::path::Path, key: Key) -> bool {
-    let Ok(device) = evdev::Device::open(path) else {
-        return false;
-    };
-    device
-        .supported_keys()
-        .is_some_and(|keys| keys.contains(key))
+/// The key whose device is used for a trigger setting: the last (non-modifier)
+/// component of a chord. `None` if the setting doesn't parse.
+pub fn trigger_key(spec: &str) -> Option<Key> {
+    resolve_key_chord(spec).ok()?.last().copied()
 }
 
 /// Pick the keyboard device to use based on settings.
@@ -77,11 +211,11 @@ pub fn pick_keyboard_device(device_setting: &str, key: Key) -> Result<PathBuf> {
     }
 
     let devices = list_input_devices()?;
-    let keyboards = filter_keyboards(&devices);
+    let keyboards = filter_keyboards(&devices, Some(key));
 
     // First pass: find a keyboard that supports the key
     for dev in &keyboards {
-        if device_supports_key(&dev.path, key) {
+        if dev.supports(key) {
             log::info!(
                 "Auto-selected device {} ({}) - supports {:?}",
                 dev.path.display(),
@@ -92,7 +226,8 @@ pub fn pick_keyboard_device(device_setting: &str, key: Key) -> Result<PathBuf> {
         }
     }
 
-    // Fallback: first keyboard device
+    // Fallback: first keyboard device. Callers should `check_key_support`
+    // the result, since the key can't fire here.
     if let Some(dev) = keyboards.first() {
         log::warn!(
             "No device explicitly supports {:?}, falling back to {} ({})",
@@ -111,49 +246,126 @@ pub fn resolve_key(key_name: &str) -> Result<Key> {
     parse_key_name(key_name).with_context(|| format!("Unknown key name: {key_name}"))
 }
 
+/// Resolve a trigger key setting that may name a chord, e.g. "KEY_LEFTCTRL+KEY_LEFTALT".
+/// Every component must be a known key; duplicates are rejected.
+pub fn resolve_key_chord(spec: &str) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    for part in spec.split('+') {
+        let part = part.trim();
+        if part.is_empty() {
+            bail!("Empty key name in trigger chord: {spec}");
+        }
+        let key = resolve_key(part)
+            .with_context(|| format!("Invalid trigger chord component {part:?} in {spec:?}"))?;
+        if keys.contains(&key) {
+            bail!("Key {part} is listed twice in trigger chord: {spec}");
+        }
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
 /// Parse a key name string to an evdev Key.
+/// Accepts any evdev key or button name, case-insensitive, with or without
+/// the `KEY_` prefix (e.g. "KEY_F13", "menu", "BTN_SIDE").
 fn parse_key_name(name: &str) -> Option<Key> {
-    let name_upper = name.to_uppercase();
-    let name_upper = name_upper.strip_prefix("KEY_").unwrap_or(&name_upper);
-
-    match name_upper {
-        "FN" => Some(Key::KEY_FN),
-        "CAPSLOCK" => Some(Key::KEY_CAPSLOCK),
-        "RIGHTCTRL" => Some(Key::KEY_RIGHTCTRL),
-        "LEFTCTRL" => Some(Key::KEY_LEFTCTRL),
-        "RIGHTALT" => Some(Key::KEY_RIGHTALT),
-        "LEFTALT" => Some(Key::KEY_LEFTALT),
-        "RIGHTMETA" => Some(Key::KEY_RIGHTMETA),
-        "LEFTMETA" => Some(Key::KEY_LEFTMETA),
-        "RIGHTSHIFT" => Some(Key::KEY_RIGHTSHIFT),
-        "LEFTSHIFT" => Some(Key::KEY_LEFTSHIFT),
-        "SCROLLLOCK" => Some(Key::KEY_SCROLLLOCK),
-        "PAUSE" => Some(Key::KEY_PAUSE),
-        "INSERT" => Some(Key::KEY_INSERT),
-        "F1" => Some(Key::KEY_F1),
-        "F2" => Some(Key::KEY_F2),
-        "F3" => Some(Key::KEY_F3),
-        "F4" => Some(Key::KEY_F4),
-        "F5" => Some(Key::KEY_F5),
-        "F6" => Some(Key::KEY_F6),
-        "F7" => Some(Key::KEY_F7),
-        "F8" => Some(Key::KEY_F8),
-        "F9" => Some(Key::KEY_F9),
-        "F10" => Some(Key::KEY_F10),
-        "F11" => Some(Key::KEY_F11),
-        "F12" => Some(Key::KEY_F12),
-        "SPACE" => Some(Key::KEY_SPACE),
-        _ => None,
+    let name_upper = name.trim().to_uppercase();
+    if name_upper.starts_with("BTN_") {
+        return Key::from_str(&name_upper).ok();
+    }
+    let bare = name_upper.strip_prefix("KEY_").unwrap_or(&name_upper);
+    Key::from_str(&format!("KEY_{bare}")).ok()
+}
+
+/// Reverse of `parse_key_name`: the config name for a key, e.g. "KEY_RIGHTCTRL".
+pub fn key_to_name(key: Key) -> String {
+    format!("{key:?}")
+}
+
+/// Human-readable label for a trigger key setting, e.g. "KEY_RIGHTCTRL" ->
+/// "Right Ctrl", "KEY_LEFTCTRL+KEY_LEFTALT" -> "Left Ctrl+Left Alt".
+pub fn key_label(spec: &str) -> String {
+    spec.split('+')
+        .map(|part| key_component_label(part.trim()))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+fn key_component_label(name: &str) -> String {
+    let upper = name.to_ascii_uppercase();
+    let bare = upper
+        .strip_prefix("KEY_")
+        .or_else(|| upper.strip_prefix("BTN_"))
+        .unwrap_or(&upper);
+    for side in ["LEFT", "RIGHT"] {
+        if let Some(modifier) = bare.strip_prefix(side)
+            && ["CTRL", "ALT", "SHIFT", "META"].contains(&modifier)
+        {
+            return format!("{} {}", capitalize(side), capitalize(modifier));
+        }
+    }
+    capitalize(bare)
+}
+
+fn capitalize(word: &str) -> String {
+    let lower = word.to_ascii_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Wait up to `timeout_ms` for `device` to have events to read, so readers
+/// can check for shutdown between waits instead of blocking in `fetch_events`.
+pub fn wait_for_events(device: &evdev::Device, timeout_ms: u16) -> Result<bool> {
+    // SAFETY: the fd is owned by `device`, which outlives this borrow.
+    let fd = unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) };
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+    let ready =
+        poll(&mut fds, PollTimeout::from(timeout_ms)).context("Failed to poll input device")?;
+    Ok(ready > 0)
+}
+
+/// Wait for the next key press on a device, for the GUI "press a key to bind" flow.
+/// Returns `Ok(None)` if `timeout` elapses or `cancel` is set first.
+pub fn capture_key_press(
+    path: &Path,
+    timeout: Duration,
+    cancel: &AtomicBool,
+) -> Result<Option<Key>> {
+    let mut device =
+        evdev::Device::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let deadline = Instant::now() + timeout;
+
+    while Instant::now() < deadline && !cancel.load(Ordering::Relaxed) {
+        // Poll in short slices so cancel and the deadline are noticed promptly.
+        if !wait_for_events(&device, 200)? {
+            continue;
+        }
+
+        for event in device
+            .fetch_events()
+            .context("Failed to read input events")?
+        {
+            if let InputEventKind::Key(key) = event.kind()
+                && event.value() == 1
+            {
+                return Ok(Some(key));
+            }
+        }
     }
+
+    Ok(None)
 }
 
-pub fn list_devices_cli() -> Result<()> {
+pub fn list_devices_cli(trigger: Option<Key>) -> Result<()> {
     let devices = list_input_devices()?;
-    let keyboards = filter_keyboards(&devices);
+    let keyboards = filter_keyboards(&devices, trigger);
 
     println!("Input devices (keyboards):");
     for dev in &keyboards {
-        println!("  {} - {}", dev.path.display(), dev.name);
+        println!("  {}", dev.label());
     }
 
     if keyboards.is_empty() {
@@ -211,41 +423,169 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resolve_previously_unsupported_keys() {
+        assert_eq!(resolve_key("KEY_F13").unwrap(), Key::KEY_F13);
+        assert_eq!(resolve_key("KEY_F24").unwrap(), Key::KEY_F24);
+        assert_eq!(resolve_key("KEY_MENU").unwrap(), Key::KEY_MENU);
+        assert_eq!(resolve_key("playpause").unwrap(), Key::KEY_PLAYPAUSE);
+        assert_eq!(resolve_key("btn_side").unwrap(), Key::BTN_SIDE);
+    }
+
+    #[test]
+    fn test_resolve_key_chord() {
+        assert_eq!(
+            resolve_key_chord("KEY_RIGHTCTRL").unwrap(),
+            vec![Key::KEY_RIGHTCTRL]
+        );
+        assert_eq!(
+            resolve_key_chord("KEY_LEFTCTRL+KEY_LEFTALT").unwrap(),
+            vec![Key::KEY_LEFTCTRL, Key::KEY_LEFTALT]
+        );
+        assert_eq!(
+            resolve_key_chord(" leftctrl + leftalt ").unwrap(),
+            vec![Key::KEY_LEFTCTRL, Key::KEY_LEFTALT]
+        );
+    }
+
+    #[test]
+    fn test_resolve_key_chord_rejects_bad_components() {
+        let err = resolve_key_chord("KEY_LEFTCTRL+KEY_BOGUS").unwrap_err();
+        assert!(format!("{err:#}").contains("KEY_BOGUS"));
+        assert!(resolve_key_chord("KEY_LEFTCTRL+").is_err());
+        assert!(resolve_key_chord("KEY_LEFTCTRL+leftctrl").is_err());
+    }
+
+    #[test]
+    fn test_key_label() {
+        assert_eq!(key_label("KEY_RIGHTCTRL"), "Right Ctrl");
+        assert_eq!(key_label("KEY_CAPSLOCK"), "Capslock");
+        assert_eq!(key_label("KEY_F13"), "F13");
+        assert_eq!(key_label("BTN_SIDE"), "Side");
+        // Arrow keys are not split into side + modifier
+        assert_eq!(key_label("KEY_LEFT"), "Left");
+        assert_eq!(key_label("KEY_LEFTCTRL+KEY_LEFTALT"), "Left Ctrl+Left Alt");
+    }
+
+    #[test]
+    fn test_key_to_name_roundtrip() {
+        assert_eq!(key_to_name(Key::KEY_RIGHTCTRL), "KEY_RIGHTCTRL");
+        assert_eq!(key_to_name(Key::BTN_SIDE), "BTN_SIDE");
+        for key in [
+            Key::KEY_FN,
+            Key::KEY_F5,
+            Key::KEY_F13,
+            Key::KEY_MENU,
+            Key::BTN_EXTRA,
+        ] {
+            assert_eq!(resolve_key(&key_to_name(key)).unwrap(), key);
+        }
+    }
+
+    fn device(n: u32, name: &str, keys: &[Key]) -> InputDevice {
+        InputDevice {
+            path: PathBuf::from(format!("/dev/input/event{n}")),
+            name: name.into(),
+            keys: keys.to_vec(),
+        }
+    }
+
+    fn full_keyboard() -> Vec<Key> {
+        let mut keys = LETTER_KEYS.to_vec();
+        keys.extend([Key::KEY_ENTER, Key::KEY_SPACE, Key::KEY_RIGHTCTRL]);
+        keys
+    }
+
     #[test]
     fn test_filter_keyboards() {
         let devices = vec![
-            InputDevice {
-                path: PathBuf::from("/dev/input/event0"),
-                name: "AT Translated Set 2 keyboard".into(),
-            },
-            InputDevice {
-                path: PathBuf::from("/dev/input/event1"),
-                name: "SynPS/2 Synaptics TouchPad".into(),
-            },
-            InputDevice {
-                path: PathBuf::from("/dev/input/event2"),
-                name: "TPPS/2 Elan TrackPoint".into(),
-            },
-            InputDevice {
-                path: PathBuf::from("/dev/input/event3"),
-                name: "USB Mouse".into(),
-            },
-            InputDevice {
-                path: PathBuf::from("/dev/input/event4"),
-                name: "ThinkPad Extra Buttons".into(),
-            },
+            device(0, "AT Translated Set 2 keyboard", &full_keyboard()),
+            device(1, "SynPS/2 Synaptics TouchPad", &[Key::BTN_LEFT]),
+            device(
+                2,
+                "TPPS/2 Elan TrackPoint",
+                &[Key::BTN_LEFT, Key::BTN_RIGHT],
+            ),
+            device(3, "USB Mouse", &[Key::BTN_LEFT, Key::BTN_SIDE]),
+            device(4, "ThinkPad Extra Buttons", &[Key::KEY_MICMUTE]),
         ];
 
-        let keyboards = filter_keyboards(&devices);
-        assert_eq!(keyboards.len(), 2);
+        let keyboards = filter_keyboards(&devices, Some(Key::KEY_RIGHTCTRL));
+        assert_eq!(keyboards.len(), 1);
         assert_eq!(keyboards[0].name, "AT Translated Set 2 keyboard");
-        assert_eq!(keyboards[1].name, "ThinkPad Extra Buttons");
+    }
+
+    #[test]
+    fn test_filter_keyboards_by_capability() {
+        let devices = vec![
+            // Oddly named keyboard: kept for its letter keys.
+            device(0, "SONiX USB DEVICE", &full_keyboard()),
+            // Button panel and power switch: no letters.
+            device(1, "Power Button", &[Key::KEY_POWER]),
+            // Gaming mouse advertising a full key set: the name rules it out.
+            device(2, "Logitech G502 Gaming Mouse", &full_keyboard()),
+            device(3, "ydotoold virtual device", &full_keyboard()),
+        ];
+
+        let names = |trigger| {
+            filter_keyboards(&devices, trigger)
+                .iter()
+                .map(|d| d.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None), ["SONiX USB DEVICE"]);
+        // A device advertising the trigger key is kept whatever its name.
+        assert_eq!(
+            names(Some(Key::KEY_POWER)),
+            ["SONiX USB DEVICE", "Power Button"]
+        );
+        assert_eq!(names(Some(Key::BTN_SIDE)), ["SONiX USB DEVICE"]);
+    }
+
+    #[test]
+    fn test_missing_key_message() {
+        let keyboard = device(0, "SONiX USB DEVICE", &full_keyboard());
+        assert!(missing_key_message(&keyboard, Key::KEY_RIGHTCTRL).is_none());
+
+        let message = missing_key_message(&keyboard, Key::KEY_FN).unwrap();
+        assert!(message.starts_with("SONiX USB DEVICE (/dev/input/event0) does not report KEY_FN"));
+        assert!(message.ends_with("; this device has KEY_RIGHTCTRL"));
+
+        let buttons = device(1, "Power Button", &[Key::KEY_POWER]);
+        let message = missing_key_message(&buttons, Key::KEY_FN).unwrap();
+        assert!(message.ends_with("Choose a different key or keyboard_device"));
+    }
+
+    #[test]
+    fn test_grab_is_safe() {
+        assert!(grab_is_safe(&[Key::KEY_RIGHTCTRL]));
+        assert!(grab_is_safe(&[Key::KEY_LEFTCTRL, Key::KEY_F13]));
+        assert!(!grab_is_safe(&[Key::KEY_A]));
+        assert!(!grab_is_safe(&[Key::KEY_LEFTCTRL, Key::KEY_SPACE]));
+        assert!(!grab_is_safe(&[Key::KEY_LEFTALT, Key::KEY_5]));
+        assert!(!grab_is_safe(&[]));
+    }
+
+    #[test]
+    fn test_trigger_key() {
+        assert_eq!(trigger_key("KEY_RIGHTCTRL"), Some(Key::KEY_RIGHTCTRL));
+        assert_eq!(trigger_key("KEY_LEFTCTRL+KEY_SPACE"), Some(Key::KEY_SPACE));
+        assert_eq!(trigger_key("KEY_NOPE"), None);
+    }
+
+    #[test]
+    fn test_input_device_label() {
+        let dev = device(0, "AT Translated Set 2 keyboard", &[]);
+        assert_eq!(
+            dev.label(),
+            "/dev/input/event0 - AT Translated Set 2 keyboard"
+        );
     }
 
     #[test]
     fn test_filter_keyboards_empty() {
         let devices: Vec<InputDevice> = vec![];
-        let keyboards = filter_keyboards(&devices);
+        let keyboards = filter_keyboards(&devices, Some(Key::KEY_RIGHTCTRL));
         assert!(keyboards.is_empty());
     }
 
@@ -1,11 +1,24 @@
 pub mod audio;
+pub mod benchmark;
 pub mod bridge;
 pub mod config;
 pub mod diagnostics;
+pub mod events;
+pub mod formats;
 pub mod gui;
 mod gui_bridge;
+pub mod hooks;
 pub mod input;
+pub mod logging;
+pub mod meeting;
 pub mod paste;
+pub mod pipewire;
+pub mod portal;
 pub mod preflight;
+pub mod privileged;
+pub mod selftest;
 pub mod service;
+pub mod sink;
+pub mod systemd;
 pub mod transcribe;
+pub mod watch;
@@ -0,0 +1,188 @@
+use crate::config::Settings;
+use log::LevelFilter;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Parse the `log_level` setting, falling back to `Info` for unknown values.
+pub fn parse_level(level: &str) -> LevelFilter {
+    level.trim().parse().unwrap_or(LevelFilter::Info)
+}
+
+/// Initialize logging from settings: records go to stderr (picked up by
+/// journald under systemd) and are appended to `log_file` when it is set.
+/// `RUST_LOG`, if present, overrides `log_level`.
+pub fn init(settings: &Settings) {
+    let level = parse_level(&settings.log_level);
+    let file = open_log_file(&settings.log_file);
+
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(level.as_str().to_ascii_lowercase()),
+    )
+    .target(env_logger::Target::Pipe(Box::new(Tee { file })))
+    .init();
+}
+
+fn open_log_file(path: &str) -> Option<File> {
+    if path.trim().is_empty() {
+        return None;
+    }
+    let path = expand_home(path.trim());
+    let path = path.as_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match File::options().create(true).append(true).open(path) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("escucha: cannot open log file {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Environment variable that overrides `privacy_mode` for one run, e.g.
+/// `ESCUCHA_PRIVACY_MODE=0` to see transcriptions in the log while debugging.
+pub const PRIVACY_MODE_ENV: &str = "ESCUCHA_PRIVACY_MODE";
+
+/// Whether transcribed text must be kept out of logs and history.
+pub fn privacy_mode(settings: &Settings) -> bool {
+    privacy_mode_with_override(
+        settings.privacy_mode,
+        std::env::var(PRIVACY_MODE_ENV).ok().as_deref(),
+    )
+}
+
+fn privacy_mode_with_override(setting: bool, env: Option<&str>) -> bool {
+    match env.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        Some("true" | "yes" | "on" | "1") => true,
+        Some("false" | "no" | "off" | "0") => false,
+        _ => setting,
+    }
+}
+
+/// Transcribed text as it may appear in a log: the text itself, or only its
+/// length in privacy mode.
+pub fn loggable_text(text: &str, privacy: bool) -> String {
+    if privacy {
+        format!("<{} chars redacted>", text.chars().count())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Remove transcribed text from a log line written by the service, for
+/// `--diagnose` output in privacy mode.
+pub fn scrub_line(line: &str) -> String {
+    if let Some(pos) = line.find("Transcribed: ") {
+        let end = pos + "Transcribed: ".len();
+        return format!("{}{}", &line[..end], loggable_text(&line[end..], true));
+    }
+    // Segment lines look like "Segment [0.00s-2.34s] p=0.87 text".
+    if let Some(pos) = line.find("Segment [")
+        && let Some(p) = line[pos..].find(" p=")
+        && let Some(space) = line[pos + p + 1..].find(' ')
+    {
+        let end = pos + p + 1 + space + 1;
+        return format!("{}{}", &line[..end], loggable_text(&line[end..], true));
+    }
+    line.to_string()
+}
+
+/// Expand a leading `~/`, as written in the documented config example.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Writes every log record to stderr and, if open, the log file.
+struct Tee {
+    file: Option<File>,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = std::io::stderr().write_all(buf);
+        if let Some(file) = &mut self.file {
+            file.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let _ = std::io::stderr().flush();
+        if let Some(file) = &mut self.file {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_level() {
+        assert_eq!(parse_level("debug"), LevelFilter::Debug);
+        assert_eq!(parse_level("WARN"), LevelFilter::Warn);
+        assert_eq!(parse_level(" error "), LevelFilter::Error);
+        assert_eq!(parse_level("off"), LevelFilter::Off);
+    }
+
+    #[test]
+    fn test_parse_level_invalid_falls_back_to_info() {
+        assert_eq!(parse_level("verbose"), LevelFilter::Info);
+        assert_eq!(parse_level(""), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_privacy_mode_override() {
+        assert!(privacy_mode_with_override(true, None));
+        assert!(!privacy_mode_with_override(false, None));
+        assert!(!privacy_mode_with_override(true, Some("0")));
+        assert!(privacy_mode_with_override(false, Some("on")));
+        assert!(privacy_mode_with_override(true, Some("bogus")));
+    }
+
+    #[test]
+    fn test_loggable_text() {
+        assert_eq!(loggable_text("hola mundo", false), "hola mundo");
+        assert_eq!(loggable_text("hola mundo", true), "<10 chars redacted>");
+    }
+
+    #[test]
+    fn test_scrub_line() {
+        assert_eq!(
+            scrub_line("[INFO escucha::service] Transcribed: my password"),
+            "[INFO escucha::service] Transcribed: <11 chars redacted>"
+        );
+        assert_eq!(
+            scrub_line("[INFO escucha::service] Segment [0.00s-2.34s] p=0.87 hi there"),
+            "[INFO escucha::service] Segment [0.00s-2.34s] p=0.87 <8 chars redacted>"
+        );
+        assert_eq!(scrub_line("Status: Ready"), "Status: Ready");
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~/x.log"), home.join("x.log"));
+        assert_eq!(expand_home("/tmp/x.log"), PathBuf::from("/tmp/x.log"));
+    }
+
+    #[test]
+    fn test_tee_appends_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("escucha.log");
+        let mut tee = Tee {
+            file: open_log_file(path.to_str().unwrap()),
+        };
+        tee.write_all(b"first\n").unwrap();
+        tee.write_all(b"second\n").unwrap();
+        tee.flush().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+    }
+}
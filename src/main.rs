@@ -8,10 +8,33 @@ struct Cli {
     #[arg(long)]
     list_devices: bool,
 
+    /// List PipeWire audio sources for the audio_source setting
+    #[arg(long)]
+    list_audio_sources: bool,
+
+    /// Print the config file path ($ESCUCHA_CONFIG or ~/.config/escucha/config.ini)
+    #[arg(long)]
+    config_path: bool,
+
+    /// Print the effective settings as INI: defaults, config file and
+    /// ESCUCHA_* environment overrides
+    #[arg(long)]
+    print_config: bool,
+
     /// Launch the toolbar (system tray) app
     #[arg(long)]
     gui: bool,
 
+    /// Run the daemon and print status, text and errors to stdout as
+    /// newline-delimited JSON
+    #[arg(long)]
+    json_events: bool,
+
+    /// Restart the daemon with the new settings whenever the config file is
+    /// saved (for trying out settings)
+    #[arg(long)]
+    watch_config: bool,
+
     /// Run environment checks and print a diagnostic report
     #[arg(long)]
     check: bool,
@@ -20,24 +43,148 @@ struct Cli {
     #[arg(long)]
     diagnose: bool,
 
+    /// With --diagnose, print the JSON Schema of the report instead
+    #[arg(long, requires = "diagnose")]
+    schema: bool,
+
+    /// With --diagnose or --smoke-test, replace the username, home paths and
+    /// device names with placeholders and omit log lines
+    #[arg(long)]
+    redact: bool,
+
     /// Run headless smoke test flow and print JSON output
+    ///
+    /// Exit codes: 0 passed, 1 environment check failed, 10 config,
+    /// 11 input device/key, 12 paste, 13 audio capture, 14 transcription
+    /// (the first required step that failed).
     #[arg(long)]
     smoke_test: bool,
+
+    /// Print version, whisper backend, GUI toolkit and cargo features as JSON
+    #[arg(long)]
+    build_info: bool,
+
+    /// Measure transcription latency for each Whisper model
+    #[arg(long)]
+    benchmark: bool,
+
+    /// Comma-separated models for --benchmark (default: tiny.en,base.en,small.en)
+    #[arg(long, value_delimiter = ',', requires = "benchmark")]
+    models: Vec<String>,
+
+    /// Print --benchmark results as JSON instead of a table
+    #[arg(long, requires = "benchmark")]
+    json: bool,
+
+    /// Transcribe a 16 kHz WAV file (or `-` for stdin) and print the text
+    #[arg(long, value_name = "FILE")]
+    transcribe: Option<String>,
+
+    /// Output format for --transcribe: txt, srt, vtt (subtitles from segment
+    /// timings) or json
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "txt",
+        requires = "transcribe",
+        value_parser = parse_format
+    )]
+    format: escucha::formats::OutputFormat,
+
+    /// Record until Ctrl+C, transcribing at each pause and appending
+    /// timestamped lines to the --output file (meeting mode)
+    #[arg(long, requires = "output")]
+    transcribe_stream: bool,
+
+    /// Transcript file for --transcribe-stream
+    #[arg(long, value_name = "FILE", requires = "transcribe_stream")]
+    output: Option<std::path::PathBuf>,
+
+    /// Paste TEXT into the focused window after a 3 second countdown, using
+    /// the configured paste settings, and report the method and result
+    #[arg(long, value_name = "TEXT")]
+    paste_test: Option<String>,
+
+    /// List known Whisper models, their sizes and which are downloaded
+    #[arg(long)]
+    list_models: bool,
+
+    /// Record 2 seconds from the microphone, report its level and play it back
+    #[arg(long)]
+    selftest_audio: bool,
+
+    /// Write ~/.config/systemd/user/escucha.service for this executable and
+    /// reload the user systemd manager
+    #[arg(long)]
+    install_service: bool,
+
+    /// With --install-service, also enable and start the service
+    #[arg(long, requires = "install_service")]
+    enable: bool,
+
+    /// With --install-service, also set up ydotoold for pasting: its user
+    /// unit, /dev/uinput access (asks for admin rights) and a running daemon
+    #[arg(long, requires = "install_service")]
+    with_ydotoold: bool,
+
+    /// Stop and disable escucha.service and remove its unit file
+    #[arg(long, conflicts_with = "install_service")]
+    uninstall_service: bool,
+}
+
+fn parse_format(name: &str) -> std::result::Result<escucha::formats::OutputFormat, String> {
+    escucha::formats::OutputFormat::from_name(name)
+        .ok_or_else(|| format!("unknown format {name} (expected txt, srt, vtt or json)"))
 }
 
 fn main() -> Result<()> {
-    env_logger::init();
+    // Loaded once; modes that run on the settings fail on a broken config,
+    // while --diagnose, --gui and the daemon report it themselves.
+    let settings = escucha::config::load_settings();
+    match &settings {
+        Ok(settings) => {
+            escucha::logging::init(settings);
+            if let Err(e) = escucha::transcribe::set_model_aliases(&settings.model_aliases) {
+                log::warn!("{e:#}");
+            }
+        }
+        Err(_) => escucha::logging::init(&escucha::config::Settings::default()),
+    }
     let cli = Cli::parse();
 
-    if cli.diagnose {
-        let ok = escucha::diagnostics::run_and_print("diagnose", false)?;
-        if !ok {
+    if cli.build_info {
+        escucha::diagnostics::print_build_info()?;
+    } else if cli.config_path {
+        println!("{}", escucha::config::config_path().display());
+    } else if cli.print_config {
+        print!("{}", escucha::config::effective_config()?);
+    } else if cli.benchmark {
+        let models = if cli.models.is_empty() {
+            escucha::benchmark::DEFAULT_MODELS
+                .iter()
+                .map(|m| m.to_string())
+                .collect()
+        } else {
+            cli.models
+        };
+        let settings = settings?;
+        escucha::benchmark::run_and_print(&models, &settings, cli.json)?;
+    } else if cli.selftest_audio {
+        let settings = settings?;
+        if !escucha::selftest::run(&settings)? {
             std::process::exit(1);
         }
+    } else if cli.diagnose && cli.schema {
+        escucha::diagnostics::print_schema()?;
+    } else if cli.diagnose {
+        let code = escucha::diagnostics::run_and_print("diagnose", false, cli.redact)?;
+        if code != 0 {
+            std::process::exit(code);
+        }
     } else if cli.smoke_test {
-        let ok = escucha::diagnostics::run_and_print("smoke-test", true)?;
-        if !ok {
-            std::process::exit(1);
+        let code = escucha::diagnostics::run_and_print("smoke-test", true, cli.redact)?;
+        if code != 0 {
+            std::process::exit(code);
         }
     } else if cli.check {
         let report = escucha::preflight::check_environment();
@@ -45,12 +192,38 @@ fn main() -> Result<()> {
         if report.has_critical_failures() {
             std::process::exit(1);
         }
+    } else if let Some(input) = &cli.transcribe {
+        let settings = settings?;
+        escucha::transcribe::transcribe_cli(input, cli.format, &settings)?;
+    } else if cli.transcribe_stream
+        && let Some(output) = &cli.output
+    {
+        let settings = settings?;
+        escucha::meeting::run(output, &settings)?;
+    } else if let Some(text) = &cli.paste_test {
+        let settings = settings?;
+        if !escucha::paste::paste_test_cli(text, &settings)? {
+            std::process::exit(1);
+        }
+    } else if cli.install_service {
+        escucha::systemd::install_service(cli.enable)?;
+        if cli.with_ydotoold && !escucha::systemd::install_ydotoold_service() {
+            std::process::exit(1);
+        }
+    } else if cli.uninstall_service {
+        escucha::systemd::uninstall_service()?;
+    } else if cli.list_models {
+        escucha::transcribe::list_models_cli();
     } else if cli.list_devices {
-        escucha::input::list_devices_cli()?;
+        let settings = settings?;
+        escucha::input::list_devices_cli(escucha::input::trigger_key(&settings.key))?;
+    } else if cli.list_audio_sources {
+        let settings = settings?;
+        escucha::pipewire::list_audio_sources_cli(settings.audio_source.trim())?;
     } else if cli.gui {
         escucha::gui::run_gui()?;
     } else {
-        escucha::service::run_daemon()?;
+        escucha::service::run_daemon(cli.json_events, cli.watch_config)?;
     }
 
     Ok(())
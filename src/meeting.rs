@@ -0,0 +1,511 @@
+//! `--transcribe-stream`: hands-free meeting transcription. Records until
+//! Ctrl+C, cuts the audio into chunks at pauses, and appends each chunk's
+//! text to a transcript file with its offset from the start. The same
+//! energy-based pause detection ends dictations for `auto_stop_silence_ms`.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::audio::{self, Recording, WHISPER_SAMPLE_RATE};
+use crate::config::Settings;
+use crate::transcribe::{self, Transcriber};
+
+/// Samples per VAD frame: 100ms at 16kHz.
+const FRAME_SAMPLES: usize = 1600;
+/// Frames at or above this level count as speech.
+const SPEECH_DBFS: f32 = -45.0;
+/// Silence that ends a chunk: 1s.
+const PAUSE_FRAMES: usize = 10;
+/// Silence kept before speech so its first word isn't clipped: 300ms.
+const PREROLL_FRAMES: usize = 3;
+/// Chunks shorter than this much speech are dropped as clicks and bumps.
+const MIN_SPEECH_FRAMES: usize = 3;
+/// Longest chunk, kept under Whisper's 30s window: 25s.
+const MAX_CHUNK_FRAMES: usize = 250;
+/// How often new audio is read from arecord's file.
+const READ_INTERVAL: Duration = Duration::from_millis(250);
+/// Audio one recorder file may hold before arecord is restarted at the next
+/// pause, so the temporary WAV doesn't grow for the whole meeting.
+const ROTATE_AFTER: Duration = Duration::from_secs(120);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn stop_handler(_sig: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// Audio between two pauses, with its offset in the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub start: Duration,
+    pub samples: Vec<i16>,
+}
+
+/// Energy-based chunking of a 16kHz mono stream: speech runs until a pause
+/// (or the length cap), using the same RMS/dBFS measure as the level meter.
+#[derive(Default)]
+pub struct Chunker {
+    /// Samples not yet making up a whole frame.
+    partial: Vec<i16>,
+    chunk: Vec<i16>,
+    /// Stream offset, in samples, of `chunk[0]`.
+    chunk_start: usize,
+    /// Samples consumed into frames so far.
+    position: usize,
+    speech_frames: usize,
+    silent_frames: usize,
+}
+
+impl Chunker {
+    /// Feed audio; returns the chunks it completed.
+    pub fn push(&mut self, samples: &[i16]) -> Vec<Chunk> {
+        self.partial.extend_from_slice(samples);
+        let mut done = Vec::new();
+        let frames = self.partial.len() / FRAME_SAMPLES;
+        let buffered = std::mem::take(&mut self.partial);
+        for frame in buffered.chunks_exact(FRAME_SAMPLES).take(frames) {
+            if let Some(chunk) = self.push_frame(frame) {
+                done.push(chunk);
+            }
+        }
+        self.partial = buffered[frames * FRAME_SAMPLES..].to_vec();
+        done
+    }
+
+    /// Whether the speaker is pausing between chunks, so a gap in the
+    /// audio here would cut no words.
+    pub fn is_idle(&self) -> bool {
+        self.speech_frames == 0 && !self.chunk.is_empty()
+    }
+
+    /// Account for audio that was never captured, e.g. while the recorder
+    /// restarted, so later chunks keep their offsets.
+    pub fn skip(&mut self, gap: Duration) {
+        let samples = gap.as_millis() as usize * WHISPER_SAMPLE_RATE as usize / 1000;
+        self.position += samples;
+        if self.speech_frames == 0 {
+            // The pre-roll no longer leads up to what comes next.
+            self.chunk.clear();
+            self.chunk_start = self.position;
+        }
+    }
+
+    /// The chunk in progress, once the stream has ended.
+    pub fn finish(mut self) -> Option<Chunk> {
+        let partial = std::mem::take(&mut self.partial);
+        self.chunk.extend_from_slice(&partial);
+        self.take_chunk()
+    }
+
+    fn push_frame(&mut self, frame: &[i16]) -> Option<Chunk> {
+        self.position += frame.len();
+        self.chunk.extend_from_slice(frame);
+        let speech = is_speech(frame);
+        if self.speech_frames == 0 {
+            if speech {
+                self.speech_frames = 1;
+                self.silent_frames = 0;
+            } else {
+                // Nothing said yet: keep only the pre-roll.
+                let keep = PREROLL_FRAMES * FRAME_SAMPLES;
+                if self.chunk.len() > keep {
+                    let drop = self.chunk.len() - keep;
+                    self.chunk.drain(..drop);
+                    self.chunk_start += drop;
+                }
+            }
+            return None;
+        }
+        if speech {
+            self.speech_frames += 1;
+            self.silent_frames = 0;
+        } else {
+            self.silent_frames += 1;
+        }
+        if self.silent_frames >= PAUSE_FRAMES
+            || self.chunk.len() >= MAX_CHUNK_FRAMES * FRAME_SAMPLES
+        {
+            return self.take_chunk();
+        }
+        None
+    }
+
+    /// End the current chunk; None if it held too little speech.
+    fn take_chunk(&mut self) -> Option<Chunk> {
+        let samples = std::mem::take(&mut self.chunk);
+        let start = self.chunk_start;
+        let speech_frames = self.speech_frames;
+        self.chunk_start = self.position;
+        self.speech_frames = 0;
+        self.silent_frames = 0;
+        (speech_frames >= MIN_SPEECH_FRAMES).then(|| Chunk {
+            start: samples_duration(start),
+            samples,
+        })
+    }
+}
+
+/// Spots the end of speech in a growing 16kHz mono recording: speech
+/// followed by a pause of the configured length (`auto_stop_silence_ms`).
+/// Silence before the first word never ends it.
+pub struct EndOfSpeech {
+    pause_frames: usize,
+    /// Samples not yet making up a whole frame.
+    partial: Vec<i16>,
+    speech_frames: usize,
+    silent_frames: usize,
+}
+
+impl EndOfSpeech {
+    pub fn new(pause: Duration) -> Self {
+        let frame_ms = FRAME_SAMPLES as u128 * 1000 / u128::from(WHISPER_SAMPLE_RATE);
+        Self {
+            pause_frames: pause.as_millis().div_ceil(frame_ms).max(1) as usize,
+            partial: Vec::new(),
+            speech_frames: 0,
+            silent_frames: 0,
+        }
+    }
+
+    /// Feed audio; true once the speaker has paused long enough.
+    pub fn push(&mut self, samples: &[i16]) -> bool {
+        self.partial.extend_from_slice(samples);
+        let frames = self.partial.len() / FRAME_SAMPLES;
+        for frame in self.partial.chunks_exact(FRAME_SAMPLES).take(frames) {
+            if is_speech(frame) {
+                self.speech_frames += 1;
+                self.silent_frames = 0;
+            } else {
+                self.silent_frames += 1;
+            }
+        }
+        self.partial.drain(..frames * FRAME_SAMPLES);
+        self.speech_frames >= MIN_SPEECH_FRAMES && self.silent_frames >= self.pause_frames
+    }
+}
+
+fn is_speech(frame: &[i16]) -> bool {
+    audio::dbfs(audio::rms(frame)) >= SPEECH_DBFS
+}
+
+fn samples_duration(samples: usize) -> Duration {
+    Duration::from_millis(samples as u64 * 1000 / u64::from(WHISPER_SAMPLE_RATE))
+}
+
+/// "HH:MM:SS" offset into the meeting.
+fn format_offset(offset: Duration) -> String {
+    let secs = offset.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// The transcript file, appended to as chunks are transcribed.
+struct Transcript {
+    file: std::fs::File,
+    path: PathBuf,
+}
+
+impl Transcript {
+    fn create(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(
+            file,
+            "# escucha transcript, started {}",
+            crate::sink::local_timestamp()
+        )?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    fn append(&mut self, offset: Duration, text: &str) -> Result<()> {
+        let line = format!("[{}] {text}", format_offset(offset));
+        println!("{line}");
+        writeln!(self.file, "{line}")
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Mark the end of the meeting and flush everything to disk.
+    fn finish(mut self) -> Result<()> {
+        writeln!(self.file, "# ended {}\n", crate::sink::local_timestamp())?;
+        self.file
+            .sync_all()
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Record and transcribe into `output` until SIGINT/SIGTERM.
+pub fn run(output: &Path, settings: &Settings) -> Result<()> {
+    let transcriber = transcribe::load_transcriber(settings)?;
+    let timing = audio::CaptureTiming::from_settings(settings)?;
+    let format = audio::RecordingFormat::from_settings(settings)?;
+    let wav_path = audio::temp_wav_path(&audio::recording_dir(&settings.recording_dir))?;
+    let mut transcript = Transcript::create(output)?;
+
+    STOP.store(false, Ordering::Relaxed);
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            stop_handler as *const () as libc::sighandler_t,
+        );
+        libc::signal(
+            libc::SIGTERM,
+            stop_handler as *const () as libc::sighandler_t,
+        );
+    }
+
+    let mut rec = Some(Recording::start(&wav_path, &timing, &format)?);
+    eprintln!(
+        "Transcribing to {}. Press Ctrl+C to stop.",
+        output.display()
+    );
+    let mut chunker = Chunker::default();
+    let mut reader = StreamReader::new(format);
+    let result = (|| -> Result<()> {
+        while !STOP.load(Ordering::Relaxed)
+            && let Some(current) = &rec
+        {
+            let samples = reader.read(current.path())?;
+            for chunk in chunker.push(&samples) {
+                write_chunk(&transcriber, &mut transcript, &chunk)?;
+            }
+            if reader.recorded() >= ROTATE_AFTER
+                && chunker.is_idle()
+                && let Some(old) = rec.take()
+            {
+                // Finish the old file, then record into a fresh one.
+                let restarted = Instant::now();
+                let path = old.stop()?;
+                let samples = reader.read(&path)?;
+                audio::cleanup_recording(&path);
+                for chunk in chunker.push(&samples) {
+                    write_chunk(&transcriber, &mut transcript, &chunk)?;
+                }
+                rec = Some(Recording::start(&wav_path, &timing, &format)?);
+                reader.restart();
+                chunker.skip(restarted.elapsed());
+                log::debug!("Meeting recorder restarted");
+            }
+            std::thread::sleep(READ_INTERVAL);
+        }
+        Ok(())
+    })();
+
+    // Transcribe what was said before the stop, then close the file even
+    // if something failed along the way.
+    let stopped = rec.map(Recording::stop).transpose();
+    let tail = match (&result, &stopped) {
+        (Ok(()), Ok(Some(path))) => reader.read(path).and_then(|samples| {
+            for chunk in chunker.push(&samples).into_iter().chain(chunker.finish()) {
+                write_chunk(&transcriber, &mut transcript, &chunk)?;
+            }
+            Ok(())
+        }),
+        _ => Ok(()),
+    };
+    audio::cleanup_recording(&wav_path);
+    transcript.finish()?;
+    result?;
+    stopped?;
+    tail?;
+    eprintln!("Transcript saved to {}", output.display());
+    Ok(())
+}
+
+/// Follows the WAV file arecord is writing, returning the audio added since
+/// the last read as 16kHz mono. Resampling continues across reads, and
+/// across recorder restarts.
+pub(crate) struct StreamReader {
+    format: audio::RecordingFormat,
+    converter: audio::StreamConverter,
+    /// Samples already read from the current file.
+    consumed: usize,
+}
+
+impl StreamReader {
+    pub(crate) fn new(format: audio::RecordingFormat) -> Self {
+        Self {
+            format,
+            converter: audio::StreamConverter::new(format),
+            consumed: 0,
+        }
+    }
+
+    /// Audio written to `path` since the last read.
+    pub(crate) fn read(&mut self, path: &Path) -> Result<Vec<i16>> {
+        let mut raw = audio::read_samples_from(path, self.consumed)?;
+        // Only whole frames, so channels stay aligned across reads.
+        raw.truncate(raw.len() - raw.len() % usize::from(self.format.channels.max(1)));
+        self.consumed += raw.len();
+        Ok(self.converter.push(&raw))
+    }
+
+    /// Read the next file from its start, after the recorder restarted.
+    fn restart(&mut self) {
+        self.consumed = 0;
+    }
+
+    /// Length of the audio read from the current file.
+    fn recorded(&self) -> Duration {
+        let per_sec = u64::from(self.format.rate) * u64::from(self.format.channels.max(1));
+        Duration::from_millis(self.consumed as u64 * 1000 / per_sec.max(1))
+    }
+}
+
+fn write_chunk(
+    transcriber: &Transcriber,
+    transcript: &mut Transcript,
+    chunk: &Chunk,
+) -> Result<()> {
+    match transcriber.transcribe_samples(&chunk.samples) {
+        Ok(text) if !text.trim().is_empty() => transcript.append(chunk.start, text.trim()),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            log::warn!(
+                "Transcription failed at {}: {e:#}",
+                format_offset(chunk.start)
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn speech(frames: usize) -> Vec<i16> {
+        // A loud square wave, about -6 dBFS.
+        (0..frames * FRAME_SAMPLES)
+            .map(|i| if i % 32 < 16 { 16000 } else { -16000 })
+            .collect()
+    }
+
+    fn silence(frames: usize) -> Vec<i16> {
+        vec![0; frames * FRAME_SAMPLES]
+    }
+
+    #[test]
+    fn test_chunker_ignores_silence() {
+        let mut chunker = Chunker::default();
+        assert!(chunker.push(&silence(50)).is_empty());
+        assert_eq!(chunker.finish(), None);
+    }
+
+    #[test]
+    fn test_chunker_splits_on_pause() {
+        let mut chunker = Chunker::default();
+        // 2s of silence, 1s of speech, then a pause.
+        assert!(chunker.push(&silence(20)).is_empty());
+        assert!(chunker.push(&speech(10)).is_empty());
+        let chunks = chunker.push(&silence(PAUSE_FRAMES));
+        assert_eq!(chunks.len(), 1);
+        // Starts with the pre-roll before the speech.
+        assert_eq!(chunks[0].start, Duration::from_millis(1700));
+        assert_eq!(
+            chunks[0].samples.len(),
+            (PREROLL_FRAMES + 10 + PAUSE_FRAMES) * FRAME_SAMPLES
+        );
+
+        // The next chunk is timed from the stream start too.
+        chunker.push(&speech(5));
+        let last = chunker.finish().unwrap();
+        assert_eq!(last.start, Duration::from_millis(4000));
+    }
+
+    #[test]
+    fn test_chunker_caps_long_speech() {
+        let mut chunker = Chunker::default();
+        let chunks = chunker.push(&speech(MAX_CHUNK_FRAMES + 20));
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].samples.len(), MAX_CHUNK_FRAMES * FRAME_SAMPLES);
+        let rest = chunker.finish().unwrap();
+        assert_eq!(rest.start, Duration::from_secs(25));
+    }
+
+    #[test]
+    fn test_chunker_drops_clicks() {
+        let mut chunker = Chunker::default();
+        chunker.push(&speech(1));
+        assert!(chunker.push(&silence(PAUSE_FRAMES)).is_empty());
+    }
+
+    #[test]
+    fn test_chunker_skip_keeps_offsets() {
+        let mut chunker = Chunker::default();
+        chunker.push(&speech(5));
+        chunker.push(&silence(PAUSE_FRAMES));
+        assert!(!chunker.is_idle());
+        chunker.push(&silence(1));
+        assert!(chunker.is_idle());
+
+        // Two seconds were lost restarting the recorder.
+        chunker.skip(Duration::from_secs(2));
+        chunker.push(&speech(5));
+        let chunk = chunker.finish().unwrap();
+        assert_eq!(chunk.start, Duration::from_millis(3600));
+        assert_eq!(chunk.samples.len(), 5 * FRAME_SAMPLES);
+    }
+
+    #[test]
+    fn test_stream_reader_follows_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("meeting.wav");
+        let mut reader = StreamReader::new(audio::RecordingFormat::default());
+        audio::write_wav_samples(&path, &[1, 2, 3]).unwrap();
+        assert_eq!(reader.read(&path).unwrap(), vec![1, 2, 3]);
+        assert!(reader.read(&path).unwrap().is_empty());
+        assert_eq!(reader.recorded(), Duration::ZERO);
+
+        // A new recorder starts a new file at the same path.
+        audio::write_wav_samples(&path, &[4, 5]).unwrap();
+        reader.restart();
+        assert_eq!(reader.read(&path).unwrap(), vec![4, 5]);
+    }
+
+    #[test]
+    fn test_end_of_speech() {
+        let mut end = EndOfSpeech::new(Duration::from_millis(800));
+        // Waiting for the first word doesn't count as a pause.
+        assert!(!end.push(&silence(30)));
+        assert!(!end.push(&speech(5)));
+        assert!(!end.push(&silence(7)));
+        // Half a frame, then the rest: frames are joined across pushes.
+        assert!(!end.push(&vec![0; FRAME_SAMPLES / 2]));
+        assert!(end.push(&vec![0; FRAME_SAMPLES / 2]));
+
+        // Speaking again resets the pause.
+        let mut end = EndOfSpeech::new(Duration::from_millis(300));
+        end.push(&speech(5));
+        end.push(&silence(2));
+        assert!(!end.push(&speech(1)));
+        assert!(!end.push(&silence(2)));
+        assert!(end.push(&silence(1)));
+    }
+
+    #[test]
+    fn test_end_of_speech_ignores_clicks() {
+        let mut end = EndOfSpeech::new(Duration::from_millis(300));
+        end.push(&speech(1));
+        assert!(!end.push(&silence(20)));
+    }
+
+    #[test]
+    fn test_format_offset() {
+        assert_eq!(format_offset(Duration::from_millis(1700)), "00:00:01");
+        assert_eq!(format_offset(Duration::from_secs(3725)), "01:02:05");
+    }
+}
@@ -1,6 +1,9 @@
 use anyhow::{Context, Result, bail};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::RwLock;
+
+use crate::config::Settings;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PasteMethod {
@@ -11,6 +14,17 @@ pub enum PasteMethod {
 }
 
 impl PasteMethod {
+    /// Parse an explicit method name (not `auto`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xdotool" => Some(PasteMethod::Xdotool),
+            "wtype" => Some(PasteMethod::Wtype),
+            "ydotool" => Some(PasteMethod::Ydotool),
+            "wl-copy" => Some(PasteMethod::WlCopy),
+            _ => None,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             PasteMethod::Xdotool => "xdotool",
@@ -27,22 +41,274 @@ impl std::fmt::Display for PasteMethod {
     }
 }
 
+/// Values accepted by the `paste_method` setting.
+pub const PASTE_METHOD_SETTINGS: &[&str] = &["auto", "ydotool", "wtype", "xdotool", "wl-copy"];
+
+/// A clipboard paste chord.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkey {
+    /// Key names for wtype and xdotool, e.g. `ctrl+shift+v`.
+    pub keys: String,
+    /// Raw evdev codes (`paste_hotkey_uses_keycode`), sent to ydotool as-is
+    /// instead of mapping key names.
+    pub codes: Option<Vec<u16>>,
+}
+
+impl Hotkey {
+    /// Parse the value of `setting` (`paste_hotkey` or
+    /// `terminal_paste_hotkey`).
+    fn from_setting(setting: &str, value: &str, uses_keycode: bool) -> Result<Self> {
+        if !uses_keycode {
+            return Ok(Self {
+                keys: value.to_string(),
+                codes: None,
+            });
+        }
+        let codes = parse_hotkey_codes(setting, value)?;
+        let names: Vec<String> = codes.iter().map(|&code| keycode_name(code)).collect();
+        Ok(Self {
+            keys: names.join("+"),
+            codes: Some(codes),
+        })
+    }
+
+    /// `ydotool key` arguments.
+    fn ydotool_args(&self) -> Vec<String> {
+        match &self.codes {
+            Some(codes) => ydotool_key_args(codes),
+            None => parse_hotkey_to_ydotool(&self.keys),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PasteConfig {
     pub method: PasteMethod,
-    pub hotkey: String,
+    /// Inject the text into the focused window; when off, only copy it
+    /// (`auto_paste = false`).
+    pub auto_paste: bool,
+    pub hotkey: Hotkey,
+    /// Used instead of `hotkey` when the focused window is a terminal
+    /// (`terminal_paste_hotkey`).
+    pub terminal_hotkey: Option<Hotkey>,
     pub clipboard_paste: String,
     pub clipboard_paste_delay_ms: u32,
+    /// Append a space so consecutive dictations don't run together.
+    pub trailing_space: bool,
+    /// Appended instead of the `trailing_space` logic (`paste_suffix`).
+    pub suffix: Option<String>,
+    /// Turn newlines into spaces when pasting into a terminal.
+    pub terminal_safe: bool,
+    /// Copy instead of pasting when no window has focus (X11 only).
+    pub require_focus: bool,
+    /// Per-application methods from `[paste_overrides]`, keyed on the
+    /// focused window's class / app id.
+    pub overrides: Vec<(String, PasteMethod)>,
+}
+
+impl PasteConfig {
+    /// Build a paste config from settings, resolving `paste_method = auto`.
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let overrides = settings
+            .paste_overrides
+            .iter()
+            .map(|(class, method)| match PasteMethod::from_name(method) {
+                Some(m) => Ok((class.clone(), m)),
+                None => bail!(
+                    "Invalid paste override for {class}: {method} \
+                     (expected ydotool, wtype, xdotool or wl-copy)"
+                ),
+            })
+            .collect::<Result<_>>()?;
+        set_ydotool_socket(&settings.ydotool_socket);
+        let uses_keycode = settings.paste_hotkey_uses_keycode;
+        let hotkey = Hotkey::from_setting("paste_hotkey", &settings.paste_hotkey, uses_keycode)?;
+        let terminal_hotkey = match settings.terminal_paste_hotkey.trim() {
+            "" => None,
+            value => Some(Hotkey::from_setting(
+                "terminal_paste_hotkey",
+                value,
+                uses_keycode,
+            )?),
+        };
+        Ok(Self {
+            method: if settings.auto_paste {
+                pick_paste_method(&settings.paste_method)?
+            } else {
+                pick_clipboard_method(&settings.paste_method)
+            },
+            auto_paste: settings.auto_paste,
+            hotkey,
+            terminal_hotkey,
+            clipboard_paste: settings.clipboard_paste.clone(),
+            clipboard_paste_delay_ms: settings.clipboard_paste_delay_ms,
+            trailing_space: settings.trailing_space,
+            suffix: parse_suffix(&settings.paste_suffix),
+            terminal_safe: settings.terminal_safe_paste,
+            require_focus: settings.require_focus,
+            overrides,
+        })
+    }
+
+    /// Whether pasting needs to know the focused window.
+    fn needs_active_window(&self) -> bool {
+        !self.overrides.is_empty() || self.terminal_safe || self.terminal_hotkey.is_some()
+    }
+
+    /// The paste chord for the window with class `class`: the terminal one
+    /// for a known terminal, else `hotkey`. An undetected window gets
+    /// `hotkey`, since Ctrl+Shift+V does something else in many apps.
+    fn hotkey_for_window(&self, class: Option<&str>) -> &Hotkey {
+        match &self.terminal_hotkey {
+            Some(terminal) if class.is_some_and(is_terminal_class) => terminal,
+            _ => &self.hotkey,
+        }
+    }
+
+    /// The method to paste with into the window with class `class`: its
+    /// override if one matches, else the configured method.
+    fn method_for_window(&self, class: Option<&str>) -> PasteMethod {
+        if self.overrides.is_empty() {
+            return self.method;
+        }
+        match class {
+            Some(class) => {
+                let method = override_for(&self.overrides, class).unwrap_or(self.method);
+                log::debug!("Active window {class}: pasting with {method}");
+                method
+            }
+            None => {
+                log::debug!(
+                    "Could not detect the active window; pasting with {}",
+                    self.method
+                );
+                self.method
+            }
+        }
+    }
+}
+
+/// Window classes / app ids of common terminal emulators, for `terminal_safe_paste`.
+const TERMINAL_CLASSES: &[&str] = &[
+    "alacritty",
+    "com.gexperts.tilix",
+    "com.mitchellh.ghostty",
+    "foot",
+    "footclient",
+    "gnome-terminal-server",
+    "kgx",
+    "kitty",
+    "konsole",
+    "org.gnome.console",
+    "org.gnome.ptyxis",
+    "org.gnome.terminal",
+    "org.kde.konsole",
+    "org.wezfurlong.wezterm",
+    "st",
+    "st-256color",
+    "terminator",
+    "tilix",
+    "urxvt",
+    "wezterm",
+    "xfce4-terminal",
+    "xterm",
+];
+
+fn is_terminal_class(class: &str) -> bool {
+    TERMINAL_CLASSES
+        .iter()
+        .any(|t| t.eq_ignore_ascii_case(class))
+}
+
+/// Join lines with spaces so a terminal doesn't run each line as a command.
+fn flatten_newlines(text: &str) -> String {
+    text.split(['\n', '\r'])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Case-insensitive lookup of a window class in the override list.
+fn override_for(overrides: &[(String, PasteMethod)], class: &str) -> Option<PasteMethod> {
+    overrides
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(class))
+        .map(|(_, m)| *m)
+}
+
+/// Class / app id of the focused window. Hyprland and Sway are queried over
+/// their IPC; X11 via xdotool. Other Wayland compositors (GNOME, KDE) expose
+/// no such query to clients, so this returns `None` there.
+pub fn active_window_class() -> Option<String> {
+    let class = if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+        command_stdout("hyprctl", &["activewindow", "-j"]).and_then(|o| hyprland_class(&o))
+    } else if std::env::var("SWAYSOCK").is_ok() {
+        command_stdout("swaymsg", &["-t", "get_tree"]).and_then(|o| sway_focused_class(&o))
+    } else if std::env::var("WAYLAND_DISPLAY").is_err() && std::env::var("DISPLAY").is_ok() {
+        command_stdout("xdotool", &["getactivewindow", "getwindowclassname"])
+    } else {
+        None
+    };
+    class
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+fn command_stdout(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(cmd)
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// `class` of `hyprctl activewindow -j` output.
+fn hyprland_class(json: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    value.get("class")?.as_str().map(str::to_string)
+}
+
+/// `app_id` (or X11 class for XWayland windows) of the focused node in
+/// `swaymsg -t get_tree` output.
+fn sway_focused_class(json: &str) -> Option<String> {
+    fn find(node: &serde_json::Value) -> Option<String> {
+        if node.get("focused").and_then(|f| f.as_bool()) == Some(true) {
+            return node
+                .get("app_id")
+                .and_then(|a| a.as_str())
+                .or_else(|| node.pointer("/window_properties/class")?.as_str())
+                .map(str::to_string);
+        }
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|key| node.get(key)?.as_array())
+            .flatten()
+            .find_map(find)
+    }
+    find(&serde_json::from_str(json).ok()?)
+}
+
+/// Method whose clipboard tool copy-only pasting uses: `wl-copy` on Wayland,
+/// `xclip` on X11 (via the xdotool method). No injection tool is needed.
+pub fn pick_clipboard_method(setting: &str) -> PasteMethod {
+    PasteMethod::from_name(setting).unwrap_or_else(|| {
+        if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            PasteMethod::WlCopy
+        } else {
+            PasteMethod::Xdotool
+        }
+    })
 }
 
 /// Auto-detect the best paste method for the current environment.
 pub fn pick_paste_method(setting: &str) -> Result<PasteMethod> {
-    match setting {
-        "xdotool" => return Ok(PasteMethod::Xdotool),
-        "wtype" => return Ok(PasteMethod::Wtype),
-        "ydotool" => return Ok(PasteMethod::Ydotool),
-        "wl-copy" => return Ok(PasteMethod::WlCopy),
-        _ => {}
+    if let Some(method) = PasteMethod::from_name(setting) {
+        return Ok(method);
     }
 
     let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
@@ -77,15 +343,78 @@ fn is_available(cmd: &str) -> bool {
     which::which(cmd).is_ok()
 }
 
+/// The `ydotool_socket` setting, once settings are loaded.
+static YDOTOOL_SOCKET_SETTING: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Apply the `ydotool_socket` setting: checked first when looking for the
+/// socket, and passed to ydotool. Empty means search the usual places.
+pub fn set_ydotool_socket(setting: &str) {
+    let setting = setting.trim();
+    let path = (!setting.is_empty()).then(|| crate::logging::expand_home(setting));
+    if let Ok(mut configured) = YDOTOOL_SOCKET_SETTING.write() {
+        *configured = path;
+    }
+}
+
 fn ydotool_socket_path_candidates() -> Vec<PathBuf> {
-    let mut paths = Vec::new();
-    if let Ok(path) = std::env::var("YDOTOOL_SOCKET") {
-        paths.push(PathBuf::from(path));
+    let configured = YDOTOOL_SOCKET_SETTING
+        .read()
+        .ok()
+        .and_then(|configured| configured.clone());
+    socket_candidates(
+        configured,
+        std::env::var_os("YDOTOOL_SOCKET").map(PathBuf::from),
+        std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from),
+    )
+}
+
+/// Where to look for the ydotool socket, in order: the `ydotool_socket`
+/// setting, `$YDOTOOL_SOCKET`, `$XDG_RUNTIME_DIR/.ydotool_socket`, then
+/// ydotool's old default in /tmp.
+fn socket_candidates(
+    configured: Option<PathBuf>,
+    env: Option<PathBuf>,
+    runtime_dir: Option<PathBuf>,
+) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let runtime = runtime_dir.map(|dir| dir.join(".ydotool_socket"));
+    for path in [
+        configured,
+        env,
+        runtime,
+        Some(PathBuf::from("/tmp/.ydotool_socket")),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
     }
-    paths.push(PathBuf::from("/tmp/.ydotool_socket"));
     paths
 }
 
+/// A ydotool command pointed at the socket found, which may not be where
+/// this ydotool build looks by default.
+fn ydotool_command() -> Command {
+    let mut cmd = Command::new("ydotool");
+    if let Some((socket, _)) = ydotool_socket_owner() {
+        cmd.env("YDOTOOL_SOCKET", socket);
+    }
+    cmd
+}
+
+/// The ydotool socket in use and the uid that owns it, if one exists.
+pub fn ydotool_socket_owner() -> Option<(PathBuf, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    ydotool_socket_path_candidates()
+        .into_iter()
+        .find_map(|path| {
+            let uid = std::fs::metadata(&path).ok()?.uid();
+            Some((path, uid))
+        })
+}
+
 pub fn ydotool_socket_available() -> bool {
     ydotool_socket_path_candidates().iter().any(|p| p.exists())
 }
@@ -138,24 +467,10 @@ fn run_systemctl_user<const N: usize>(args: [&str; N]) -> bool {
         .is_ok_and(|s| s.success())
 }
 
-fn validated_current_user() -> Result<String> {
-    let user = std::env::var("USER").unwrap_or_default();
-    if user.is_empty() {
-        bail!("Could not determine current username");
-    }
-    if !user
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
-    {
-        bail!("Refusing to use unsafe username in privileged setup");
-    }
-    Ok(user)
-}
-
 /// Privileged best-effort repair for systems where /dev/uinput is not user-accessible.
 /// This installs an udev rule, ensures input-group membership, and reloads udev.
 pub fn repair_uinput_permissions() -> Result<()> {
-    let user = validated_current_user()?;
+    let user = crate::privileged::shell_quote(&crate::privileged::current_user()?);
     let script = format!(
         "set -e; \
          install -d /etc/udev/rules.d; \
@@ -200,21 +515,224 @@ pub fn repair_paste_setup() -> Result<()> {
     }
 }
 
-/// Paste text using the configured method.
-/// Appends a trailing space so consecutive dictations don't run together.
-pub fn paste_text(text: &str, config: &PasteConfig) -> Result<()> {
-    let text = format!("{text} ");
-    match config.method {
-        PasteMethod::Xdotool => paste_xdotool(&text, config),
-        PasteMethod::Wtype => paste_wtype(&text, config),
-        PasteMethod::Ydotool => paste_ydotool(&text, config),
-        PasteMethod::WlCopy => paste_wl_copy_only(&text),
+/// Paste text using the configured method. Returns what was done, so callers
+/// can tell the user when text was only copied (`PastePlan::unfocused`) or
+/// ydotoold had to be restarted (`PastePlan::ydotoold`).
+pub fn paste_text(text: &str, config: &PasteConfig) -> Result<PastePlan> {
+    let mut plan = plan_paste(text, config);
+    if !plan.copy_only && plan.method == PasteMethod::Ydotool {
+        plan.ydotoold = ydotoold_recovery(ydotool_ready(), ensure_ydotoold_running);
+        if plan.ydotoold == Some(YdotooldRecovery::CopiedInstead) {
+            plan.copy_only = true;
+        }
+    }
+    if plan.copy_only {
+        copy_only(&plan.payload, plan.method)?;
+        return Ok(plan);
+    }
+    match plan.method {
+        PasteMethod::Xdotool => paste_xdotool(&plan.payload, &plan.hotkey, config),
+        PasteMethod::Wtype => paste_wtype(&plan.payload, &plan.hotkey, config),
+        PasteMethod::Ydotool => paste_ydotool(&plan.payload, &plan.hotkey, config),
+        PasteMethod::WlCopy => copy_only(&plan.payload, plan.method),
+    }?;
+    Ok(plan)
+}
+
+/// Seconds `--paste-test` waits so the user can focus the target window.
+const PASTE_TEST_COUNTDOWN_SECS: u64 = 3;
+
+/// Paste `text` with the configured settings after a countdown and report
+/// the outcome, for `--paste-test`. Returns whether the paste succeeded.
+pub fn paste_test_cli(text: &str, settings: &Settings) -> Result<bool> {
+    let config = PasteConfig::from_settings(settings)?;
+    println!("Focus the window to paste into...");
+    for remaining in (1..=PASTE_TEST_COUNTDOWN_SECS).rev() {
+        println!("{remaining}...");
+        std::thread::sleep(std::time::Duration::from_secs(1));
     }
+    let result = paste_text(text, &config);
+    println!("{}", paste_test_summary(config.method, &result));
+    Ok(result.is_ok())
 }
 
-fn paste_xdotool(text: &str, config: &PasteConfig) -> Result<()> {
+/// One-line `--paste-test` result: the method used and whether it worked.
+fn paste_test_summary(method: PasteMethod, result: &Result<PastePlan>) -> String {
+    match result {
+        Ok(plan) => {
+            let chars = plan.payload.chars().count();
+            if plan.unfocused {
+                format!(
+                    "No focused window; copied {chars} chars to the clipboard with {}",
+                    plan.method
+                )
+            } else if plan.ydotoold == Some(YdotooldRecovery::CopiedInstead) {
+                format!("ydotoold not running; copied {chars} chars to the clipboard")
+            } else if plan.ydotoold == Some(YdotooldRecovery::Restarted) {
+                format!(
+                    "Restarted ydotoold; pasted {chars} chars with {}",
+                    plan.method
+                )
+            } else if plan.copy_only {
+                format!("Copied {chars} chars to the clipboard with {}", plan.method)
+            } else {
+                format!("Pasted {chars} chars with {}", plan.method)
+            }
+        }
+        Err(e) => format!("Paste with {method} failed: {e:#}"),
+    }
+}
+
+/// What `paste_text` will do for the focused window: the method and the
+/// exact text it types or copies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PastePlan {
+    pub method: PasteMethod,
+    pub payload: String,
+    /// Chord for a clipboard paste into the focused window.
+    pub hotkey: Hotkey,
+    /// Only copy to the clipboard; nothing is typed or pasted.
+    pub copy_only: bool,
+    /// Copying only because no window has focus (`require_focus`).
+    pub unfocused: bool,
+    /// Set when ydotoold had stopped since startup.
+    pub ydotoold: Option<YdotooldRecovery>,
+}
+
+/// What a paste did about ydotoold having died mid-session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YdotooldRecovery {
+    /// It was started again and the paste went ahead.
+    Restarted,
+    /// It couldn't be started; the text was only copied to the clipboard.
+    CopiedInstead,
+}
+
+/// Decide how to handle ydotoold before a ydotool paste: nothing when it is
+/// `ready`, else one `restart` attempt.
+fn ydotoold_recovery(ready: bool, restart: impl FnOnce() -> bool) -> Option<YdotooldRecovery> {
+    if ready {
+        return None;
+    }
+    if restart() {
+        log::warn!("ydotoold had stopped; restarted it");
+        Some(YdotooldRecovery::Restarted)
+    } else {
+        log::warn!("ydotoold is not running; copying to the clipboard instead of pasting");
+        Some(YdotooldRecovery::CopiedInstead)
+    }
+}
+
+/// Dry run of `paste_text`: everything except injecting the text.
+pub fn plan_paste(text: &str, config: &PasteConfig) -> PastePlan {
+    let class = if config.needs_active_window() {
+        active_window_class()
+    } else {
+        None
+    };
+    // When the focused window can't be detected, assume it may be a terminal.
+    let text = if config.terminal_safe && class.as_deref().is_none_or(is_terminal_class) {
+        flatten_newlines(text)
+    } else {
+        text.to_string()
+    };
+    let method = config.method_for_window(class.as_deref());
+    let copy_only = !config.auto_paste || method == PasteMethod::WlCopy;
+    let unfocused = !copy_only && config.require_focus && !window_focused();
+    if unfocused {
+        log::warn!("No window has focus; copying to the clipboard instead of pasting");
+    }
+    PastePlan {
+        method,
+        payload: paste_payload(&text, config),
+        hotkey: config.hotkey_for_window(class.as_deref()).clone(),
+        copy_only: copy_only || unfocused,
+        unfocused,
+        ydotoold: None,
+    }
+}
+
+/// Whether some window has keyboard focus, for `require_focus`. Only X11 can
+/// be asked (`xdotool getwindowfocus`); on Wayland, or without xdotool, this
+/// assumes a window is focused.
+fn window_focused() -> bool {
+    if std::env::var("WAYLAND_DISPLAY").is_ok() || std::env::var("DISPLAY").is_err() {
+        return true;
+    }
+    match Command::new("xdotool")
+        .arg("getwindowfocus")
+        .stderr(Stdio::null())
+        .output()
+    {
+        Ok(output) => {
+            output.status.success() && is_focus_window(&String::from_utf8_lossy(&output.stdout))
+        }
+        Err(_) => true,
+    }
+}
+
+/// Whether `xdotool getwindowfocus` output names a real window. With nothing
+/// focused X reports `None` (0) or `PointerRoot` (1).
+fn is_focus_window(output: &str) -> bool {
+    output.trim().parse::<u64>().is_ok_and(|id| id > 1)
+}
+
+/// Unescape `paste_suffix`: `\n`, `\t`, `\s` (space, which an ini value
+/// can't end with) and `\\`. None when empty.
+fn parse_suffix(setting: &str) -> Option<String> {
+    if setting.is_empty() {
+        return None;
+    }
+    let mut suffix = String::new();
+    let mut chars = setting.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            suffix.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => suffix.push('\n'),
+            Some('t') => suffix.push('\t'),
+            Some('s') => suffix.push(' '),
+            Some(other) => suffix.push(other),
+            None => suffix.push('\\'),
+        }
+    }
+    Some(suffix)
+}
+
+/// The text actually typed or copied: the transcription plus `paste_suffix`,
+/// or with `trailing_space` a separating space unless there is nothing to
+/// separate or the text already ends in whitespace.
+fn paste_payload(text: &str, config: &PasteConfig) -> String {
+    match &config.suffix {
+        Some(suffix) => format!("{text}{suffix}"),
+        None if config.trailing_space && text.ends_with(|c: char| !c.is_whitespace()) => {
+            format!("{text} ")
+        }
+        None => text.to_string(),
+    }
+}
+
+/// Last character `paste_text` will type for `text`, suffix included.
+pub fn payload_end(text: &str, config: &PasteConfig) -> Option<char> {
+    paste_payload(text, config).chars().next_back()
+}
+
+/// Separator to type before `next` when it continues a paste that ended
+/// with `previous`. Nothing when nothing was pasted yet (the cursor may be at
+/// the start of an empty field), after whitespace, or before text that
+/// starts with its own.
+pub fn leading_separator(previous: Option<char>, next: &str) -> &'static str {
+    match previous {
+        Some(c) if !c.is_whitespace() && next.starts_with(|c: char| !c.is_whitespace()) => " ",
+        _ => "",
+    }
+}
+
+fn paste_xdotool(text: &str, hotkey: &Hotkey, config: &PasteConfig) -> Result<()> {
     if should_use_clipboard(&config.clipboard_paste) {
-        clipboard_paste_x11(text, &config.hotkey, config.clipboard_paste_delay_ms)
+        clipboard_paste_x11(text, &hotkey.keys, config.clipboard_paste_delay_ms)
     } else {
         // Direct typing with xdotool
         let status = Command::new("xdotool")
@@ -229,9 +747,9 @@ fn paste_xdotool(text: &str, config: &PasteConfig) -> Result<()> {
     }
 }
 
-fn paste_wtype(text: &str, config: &PasteConfig) -> Result<()> {
+fn paste_wtype(text: &str, hotkey: &Hotkey, config: &PasteConfig) -> Result<()> {
     if should_use_clipboard(&config.clipboard_paste) {
-        clipboard_paste_wayland(text, &config.hotkey, config.clipboard_paste_delay_ms)
+        clipboard_paste_wayland(text, &hotkey.keys, config.clipboard_paste_delay_ms)
     } else {
         let status = Command::new("wtype")
             .arg(text)
@@ -241,19 +759,23 @@ fn paste_wtype(text: &str, config: &PasteConfig) -> Result<()> {
         if !status.success() {
             // Fallback to clipboard paste
             log::warn!("wtype direct typing failed, falling back to clipboard paste");
-            clipboard_paste_wayland(text, &config.hotkey, config.clipboard_paste_delay_ms)
+            clipboard_paste_wayland(text, &hotkey.keys, config.clipboard_paste_delay_ms)
         } else {
             Ok(())
         }
     }
 }
 
-fn paste_ydotool(text: &str, config: &PasteConfig) -> Result<()> {
+fn paste_ydotool(text: &str, hotkey: &Hotkey, config: &PasteConfig) -> Result<()> {
     if should_use_clipboard(&config.clipboard_paste) {
-        clipboard_paste_ydotool(text, &config.hotkey, config.clipboard_paste_delay_ms)
+        clipboard_paste_ydotool(
+            text,
+            &hotkey.ydotool_args(),
+            config.clipboard_paste_delay_ms,
+        )
     } else {
         // Direct typing with ydotool
-        let status = Command::new("ydotool")
+        let status = ydotool_command()
             .args(["type", text])
             .status()
             .context("Failed to run ydotool")?;
@@ -261,15 +783,90 @@ fn paste_ydotool(text: &str, config: &PasteConfig) -> Result<()> {
         if !status.success() {
             // Fallback to clipboard paste
             log::warn!("ydotool direct typing failed, falling back to clipboard paste");
-            clipboard_paste_ydotool(text, &config.hotkey, config.clipboard_paste_delay_ms)
+            clipboard_paste_ydotool(
+                text,
+                &hotkey.ydotool_args(),
+                config.clipboard_paste_delay_ms,
+            )
         } else {
             Ok(())
         }
     }
 }
 
-/// Clipboard-only paste: copies text to clipboard via wl-copy and logs a notice.
-fn paste_wl_copy_only(text: &str) -> Result<()> {
+/// Clipboard-only paste: copies text to the clipboard and logs a notice.
+fn copy_only(text: &str, method: PasteMethod) -> Result<()> {
+    copy_to_clipboard(text, method)?;
+
+    log::info!("Text copied to clipboard (paste with Ctrl+V)");
+    Ok(())
+}
+
+fn should_use_clipboard(setting: &str) -> bool {
+    setting == "auto" || setting == "on"
+}
+
+/// Copy text to the clipboard without pasting it, using the clipboard tool
+/// that matches the paste method (xclip on X11, wl-copy on Wayland).
+pub fn copy_to_clipboard(text: &str, method: PasteMethod) -> Result<()> {
+    match method {
+        PasteMethod::Xdotool => copy_x11(text),
+        PasteMethod::Wtype | PasteMethod::Ydotool | PasteMethod::WlCopy => copy_wayland(text),
+    }
+}
+
+/// Read the clipboard with the tool matching the paste method (`xclip -o` on
+/// X11, `wl-paste` on Wayland).
+pub fn read_clipboard(method: PasteMethod) -> Result<String> {
+    let (cmd, args): (&str, &[&str]) = match method {
+        PasteMethod::Xdotool => ("xclip", &["-selection", "clipboard", "-o"]),
+        PasteMethod::Wtype | PasteMethod::Ydotool | PasteMethod::WlCopy => {
+            ("wl-paste", &["--no-newline"])
+        }
+    };
+    let output = Command::new(cmd)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to read the clipboard with {cmd}"))?;
+    if !output.status.success() {
+        bail!("{cmd} failed with status {}", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Check that the method's key injection can run without sending any keys:
+/// connect to the ydotoold socket, or run wtype/xdotool with `--help`.
+pub fn check_injection(method: PasteMethod) -> Result<()> {
+    match method {
+        PasteMethod::Ydotool => {
+            let Some((socket, _)) = ydotool_socket_owner() else {
+                bail!("No ydotoold socket found - is ydotoold running?");
+            };
+            connect_socket(&socket)
+        }
+        PasteMethod::Wtype | PasteMethod::Xdotool => {
+            let cmd = method.as_str();
+            Command::new(cmd)
+                .arg("--help")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .with_context(|| format!("Failed to run {cmd}"))?;
+            Ok(())
+        }
+        PasteMethod::WlCopy => Ok(()),
+    }
+}
+
+/// Connect to a ydotoold socket without sending anything on it.
+fn connect_socket(path: &Path) -> Result<()> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket
+        .connect(path)
+        .with_context(|| format!("ydotoold socket {} refused the connection", path.display()))
+}
+
+fn copy_wayland(text: &str) -> Result<()> {
     let status = Command::new("wl-copy")
         .arg(text)
         .status()
@@ -278,17 +875,11 @@ fn paste_wl_copy_only(text: &str) -> Result<()> {
     if !status.success() {
         bail!("wl-copy failed");
     }
-
-    log::info!("Text copied to clipboard (paste with Ctrl+V)");
     Ok(())
 }
 
-fn should_use_clipboard(setting: &str) -> bool {
-    setting == "auto" || setting == "on"
-}
-
-fn clipboard_paste_x11(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
-    // Copy to clipboard using xclip or xsel
+fn copy_x11(text: &str) -> Result<()> {
+    // Copy to clipboard using xclip
     let status = Command::new("xclip")
         .args(["-selection", "clipboard"])
         .stdin(std::process::Stdio::piped())
@@ -305,6 +896,11 @@ fn clipboard_paste_x11(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
     if !status.success() {
         bail!("xclip failed");
     }
+    Ok(())
+}
+
+fn clipboard_paste_x11(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
+    copy_x11(text)?;
 
     std::thread::sleep(std::time::Duration::from_millis(delay_ms as u64));
 
@@ -321,15 +917,7 @@ fn clipboard_paste_x11(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
 }
 
 fn clipboard_paste_wayland(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
-    // Copy to clipboard using wl-copy
-    let status = Command::new("wl-copy")
-        .arg(text)
-        .status()
-        .context("Failed to copy to clipboard with wl-copy")?;
-
-    if !status.success() {
-        bail!("wl-copy failed");
-    }
+    copy_wayland(text)?;
 
     std::thread::sleep(std::time::Duration::from_millis(delay_ms as u64));
 
@@ -349,26 +937,17 @@ fn clipboard_paste_wayland(text: &str, hotkey: &str, delay_ms: u32) -> Result<()
     Ok(())
 }
 
-fn clipboard_paste_ydotool(text: &str, hotkey: &str, delay_ms: u32) -> Result<()> {
-    // Copy to clipboard using wl-copy
-    let status = Command::new("wl-copy")
-        .arg(text)
-        .status()
-        .context("Failed to copy to clipboard with wl-copy")?;
-
-    if !status.success() {
-        bail!("wl-copy failed");
-    }
+fn clipboard_paste_ydotool(text: &str, key_args: &[String], delay_ms: u32) -> Result<()> {
+    copy_wayland(text)?;
 
     std::thread::sleep(std::time::Duration::from_millis(delay_ms as u64));
 
     // Simulate paste hotkey with ydotool
     // Format: ydotool key KEYCODE:1 KEYCODE:1 KEYCODE:0 KEYCODE:0
     // where :1 = press, :0 = release
-    let args = parse_hotkey_to_ydotool(hotkey);
-    let status = Command::new("ydotool")
+    let status = ydotool_command()
         .arg("key")
-        .args(&args)
+        .args(key_args)
         .status()
         .context("Failed to simulate paste with ydotool")?;
 
@@ -420,26 +999,60 @@ fn parse_hotkey_to_wtype(hotkey: &str) -> Vec<String> {
 }
 
 /// Map a key name to a Linux evdev key code for ydotool.
-fn key_name_to_code(name: &str) -> Option<&'static str> {
+fn key_name_to_code(name: &str) -> Option<u16> {
     match name.to_lowercase().as_str() {
-        "ctrl" => Some("29"),            // KEY_LEFTCTRL
-        "shift" => Some("42"),           // KEY_LEFTSHIFT
-        "alt" => Some("56"),             // KEY_LEFTALT
-        "super" | "meta" => Some("125"), // KEY_LEFTMETA
-        "v" => Some("47"),               // KEY_V
-        "c" => Some("46"),               // KEY_C
-        "a" => Some("30"),               // KEY_A
-        "z" => Some("44"),               // KEY_Z
+        "ctrl" => Some(29),            // KEY_LEFTCTRL
+        "shift" => Some(42),           // KEY_LEFTSHIFT
+        "alt" => Some(56),             // KEY_LEFTALT
+        "super" | "meta" => Some(125), // KEY_LEFTMETA
+        "v" => Some(47),               // KEY_V
+        "c" => Some(46),               // KEY_C
+        "a" => Some(30),               // KEY_A
+        "z" => Some(44),               // KEY_Z
         _ => None,
     }
 }
 
+/// Highest evdev key code (`KEY_MAX`).
+const MAX_KEY_CODE: u16 = 0x2ff;
+
+/// Parse a paste hotkey given as raw evdev key codes, e.g. `29+47` for
+/// Left Ctrl + the key in the V position on a US layout.
+fn parse_hotkey_codes(setting: &str, spec: &str) -> Result<Vec<u16>> {
+    spec.split('+')
+        .map(|part| {
+            let part = part.trim();
+            match part.parse::<u16>() {
+                Ok(code) if (1..=MAX_KEY_CODE).contains(&code) => Ok(code),
+                _ => bail!(
+                    "Invalid key code in {setting}: {part:?} \
+                     (expected evdev codes joined with +, e.g. 29+47)"
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Key name wtype and xdotool understand for an evdev code (`29` -> `ctrl`,
+/// `47` -> `v`).
+fn keycode_name(code: u16) -> String {
+    let name = format!("{:?}", evdev::Key::new(code));
+    let name = name.strip_prefix("KEY_").unwrap_or(&name).to_lowercase();
+    match name.as_str() {
+        "leftctrl" | "rightctrl" => "ctrl".into(),
+        "leftshift" | "rightshift" => "shift".into(),
+        "leftalt" | "rightalt" => "alt".into(),
+        "leftmeta" | "rightmeta" => "super".into(),
+        _ => name,
+    }
+}
+
 /// Parse a hotkey like "ctrl+v" to ydotool key arguments.
 /// ydotool format: each arg is KEYCODE:STATE where 1=press, 0=release.
 /// For ctrl+v: "29:1" "47:1" "47:0" "29:0"
 fn parse_hotkey_to_ydotool(hotkey: &str) -> Vec<String> {
     let parts: Vec<&str> = hotkey.split('+').collect();
-    let mut codes: Vec<&str> = Vec::new();
+    let mut codes: Vec<u16> = Vec::new();
 
     for part in &parts {
         if let Some(code) = key_name_to_code(part) {
@@ -449,10 +1062,15 @@ fn parse_hotkey_to_ydotool(hotkey: &str) -> Vec<String> {
         }
     }
 
+    ydotool_key_args(&codes)
+}
+
+/// Press `codes` in order, then release them in reverse.
+fn ydotool_key_args(codes: &[u16]) -> Vec<String> {
     let mut args = Vec::new();
 
     // Press all keys in order
-    for code in &codes {
+    for code in codes {
         args.push(format!("{code}:1"));
     }
 
@@ -497,6 +1115,46 @@ mod tests {
         assert_eq!(args, vec!["29:1", "42:1", "47:1", "47:0", "42:0", "29:0"]);
     }
 
+    #[test]
+    fn test_parse_hotkey_codes() {
+        assert_eq!(
+            parse_hotkey_codes("paste_hotkey", "29+47").unwrap(),
+            vec![29, 47]
+        );
+        assert_eq!(
+            parse_hotkey_codes("paste_hotkey", " 29 + 42 + 47 ").unwrap(),
+            vec![29, 42, 47]
+        );
+        assert!(parse_hotkey_codes("paste_hotkey", "ctrl+v").is_err());
+        assert!(parse_hotkey_codes("paste_hotkey", "29+").is_err());
+        assert!(parse_hotkey_codes("paste_hotkey", "0").is_err());
+        assert!(parse_hotkey_codes("paste_hotkey", "70000").is_err());
+        assert!(parse_hotkey_codes("paste_hotkey", "768").is_err());
+    }
+
+    #[test]
+    fn test_paste_config_hotkey_keycodes() {
+        let settings = Settings {
+            paste_method: "ydotool".into(),
+            paste_hotkey: "29+47".into(),
+            paste_hotkey_uses_keycode: true,
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(config.hotkey.codes, Some(vec![29, 47]));
+        assert_eq!(config.hotkey.keys, "ctrl+v");
+        assert_eq!(
+            config.hotkey.ydotool_args(),
+            vec!["29:1", "47:1", "47:0", "29:0"]
+        );
+
+        let invalid = Settings {
+            paste_hotkey: "ctrl+v".into(),
+            ..settings
+        };
+        assert!(PasteConfig::from_settings(&invalid).is_err());
+    }
+
     #[test]
     fn test_parse_hotkey_ctrl_v_wtype() {
         let args = parse_hotkey_to_wtype("ctrl+v");
@@ -521,16 +1179,389 @@ mod tests {
         assert!(!should_use_clipboard("off"));
     }
 
+    #[test]
+    fn test_paste_config_from_settings() {
+        let settings = Settings {
+            paste_method: "xdotool".into(),
+            paste_hotkey: "ctrl+shift+v".into(),
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(config.method, PasteMethod::Xdotool);
+        assert_eq!(config.hotkey.keys, "ctrl+shift+v");
+        assert_eq!(config.clipboard_paste_delay_ms, 75);
+    }
+
+    #[test]
+    fn test_paste_config_overrides() {
+        let settings = Settings {
+            paste_method: "ydotool".into(),
+            paste_overrides: vec![("firefox".into(), "wl-copy".into())],
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(
+            config.overrides,
+            vec![("firefox".to_string(), PasteMethod::WlCopy)]
+        );
+
+        let bad = Settings {
+            paste_method: "ydotool".into(),
+            paste_overrides: vec![("firefox".into(), "auto".into())],
+            ..Settings::default()
+        };
+        assert!(PasteConfig::from_settings(&bad).is_err());
+    }
+
+    #[test]
+    fn test_override_for() {
+        let overrides = vec![
+            ("firefox".to_string(), PasteMethod::WlCopy),
+            ("kitty".to_string(), PasteMethod::Ydotool),
+        ];
+        assert_eq!(
+            override_for(&overrides, "Firefox"),
+            Some(PasteMethod::WlCopy)
+        );
+        assert_eq!(
+            override_for(&overrides, "kitty"),
+            Some(PasteMethod::Ydotool)
+        );
+        assert_eq!(override_for(&overrides, "code"), None);
+    }
+
+    #[test]
+    fn test_hyprland_class() {
+        let json = r#"{"address": "0x1", "class": "firefox", "title": "Mozilla Firefox"}"#;
+        assert_eq!(hyprland_class(json), Some("firefox".into()));
+        assert_eq!(hyprland_class("{}"), None);
+        assert_eq!(hyprland_class("not json"), None);
+    }
+
+    #[test]
+    fn test_sway_focused_class() {
+        let json = r#"{"focused": false, "nodes": [
+            {"focused": false, "nodes": [
+                {"focused": false, "app_id": "kitty", "nodes": []},
+                {"focused": true, "app_id": null,
+                 "window_properties": {"class": "Code"}, "nodes": []}
+            ]}
+        ], "floating_nodes": []}"#;
+        assert_eq!(sway_focused_class(json), Some("Code".into()));
+
+        let floating = r#"{"focused": false, "nodes": [], "floating_nodes": [
+            {"focused": true, "app_id": "pavucontrol", "nodes": []}
+        ]}"#;
+        assert_eq!(sway_focused_class(floating), Some("pavucontrol".into()));
+    }
+
+    #[test]
+    fn test_paste_payload_trailing_space() {
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            ..Settings::default()
+        };
+        let mut config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(paste_payload("hola mundo", &config), "hola mundo ");
+
+        // Clipboard-only users can get exactly the transcription.
+        config.trailing_space = false;
+        assert_eq!(paste_payload("hola mundo", &config), "hola mundo");
+    }
+
+    #[test]
+    fn test_paste_payload_already_ends_in_space() {
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        // A newline from post_hook or a replacement already separates.
+        assert_eq!(paste_payload("item one\n", &config), "item one\n");
+        assert_eq!(paste_payload("hola ", &config), "hola ");
+        // Nothing to separate: no lone space in an empty field.
+        assert_eq!(paste_payload("", &config), "");
+        assert_eq!(payload_end("hola", &config), Some(' '));
+        assert_eq!(payload_end("", &config), None);
+    }
+
+    #[test]
+    fn test_paste_suffix_overrides_trailing_space() {
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            paste_suffix: r",\s".into(),
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(paste_payload("eggs", &config), "eggs, ");
+        assert_eq!(paste_payload("eggs ", &config), "eggs , ");
+
+        assert_eq!(parse_suffix(""), None);
+        assert_eq!(parse_suffix(r"\n").as_deref(), Some("\n"));
+        assert_eq!(parse_suffix(r"\t;\\").as_deref(), Some("\t;\\"));
+        assert_eq!(parse_suffix(r" -\").as_deref(), Some(" -\\"));
+    }
+
+    #[test]
+    fn test_leading_separator() {
+        // Start of a dictation, e.g. the cursor in an empty field.
+        assert_eq!(leading_separator(None, "hello"), "");
+        assert_eq!(leading_separator(Some('o'), "world"), " ");
+        // The previous part already ended in a space or newline.
+        assert_eq!(leading_separator(Some(' '), "world"), "");
+        assert_eq!(leading_separator(Some('\n'), "world"), "");
+        assert_eq!(leading_separator(Some('o'), " world"), "");
+        assert_eq!(leading_separator(Some('o'), ""), "");
+    }
+
+    #[test]
+    fn test_plan_paste() {
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            ..Settings::default()
+        };
+        let mut config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(
+            plan_paste("line one\nline two", &config),
+            PastePlan {
+                method: PasteMethod::WlCopy,
+                payload: "line one\nline two ".into(),
+                hotkey: config.hotkey.clone(),
+                copy_only: true,
+                unfocused: false,
+                ydotoold: None,
+            }
+        );
+
+        // Overrides and terminal detection aren't used when unset, so the
+        // plan doesn't depend on the focused window.
+        config.trailing_space = false;
+        assert_eq!(plan_paste("exact", &config).payload, "exact");
+    }
+
+    #[test]
+    fn test_plan_paste_auto_paste_off_only_copies() {
+        let settings = Settings {
+            paste_method: "xdotool".into(),
+            auto_paste: false,
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        let plan = plan_paste("hola", &config);
+        // paste_text returns after the clipboard copy, before xdotool runs.
+        assert!(plan.copy_only);
+        assert_eq!(plan.method, PasteMethod::Xdotool);
+
+        let config = PasteConfig {
+            auto_paste: true,
+            ..config
+        };
+        assert!(!plan_paste("hola", &config).copy_only);
+    }
+
+    #[test]
+    fn test_paste_test_summary() {
+        let plan = PastePlan {
+            method: PasteMethod::Ydotool,
+            payload: "hola ".into(),
+            hotkey: Hotkey::from_setting("paste_hotkey", "ctrl+v", false).unwrap(),
+            copy_only: false,
+            unfocused: false,
+            ydotoold: None,
+        };
+        assert_eq!(
+            paste_test_summary(PasteMethod::Ydotool, &Ok(plan.clone())),
+            "Pasted 5 chars with ydotool"
+        );
+        assert_eq!(
+            paste_test_summary(
+                PasteMethod::Ydotool,
+                &Err(anyhow::anyhow!("ydotool key failed"))
+            ),
+            "Paste with ydotool failed: ydotool key failed"
+        );
+        let copy = PastePlan {
+            method: PasteMethod::WlCopy,
+            copy_only: true,
+            ..plan.clone()
+        };
+        assert_eq!(
+            paste_test_summary(PasteMethod::WlCopy, &Ok(copy)),
+            "Copied 5 chars to the clipboard with wl-copy"
+        );
+        let unfocused = PastePlan {
+            method: PasteMethod::Xdotool,
+            copy_only: true,
+            unfocused: true,
+            ..plan.clone()
+        };
+        assert_eq!(
+            paste_test_summary(PasteMethod::Xdotool, &Ok(unfocused)),
+            "No focused window; copied 5 chars to the clipboard with xdotool"
+        );
+        let restarted = PastePlan {
+            ydotoold: Some(YdotooldRecovery::Restarted),
+            ..plan.clone()
+        };
+        assert_eq!(
+            paste_test_summary(PasteMethod::Ydotool, &Ok(restarted)),
+            "Restarted ydotoold; pasted 5 chars with ydotool"
+        );
+        let copied = PastePlan {
+            copy_only: true,
+            ydotoold: Some(YdotooldRecovery::CopiedInstead),
+            ..plan
+        };
+        assert_eq!(
+            paste_test_summary(PasteMethod::Ydotool, &Ok(copied)),
+            "ydotoold not running; copied 5 chars to the clipboard"
+        );
+    }
+
+    #[test]
+    fn test_connect_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ydotool_socket");
+        assert!(connect_socket(&path).is_err());
+        let _listener = std::os::unix::net::UnixDatagram::bind(&path).unwrap();
+        assert!(connect_socket(&path).is_ok());
+    }
+
+    #[test]
+    fn test_socket_candidates_order() {
+        let tmp = PathBuf::from("/tmp/.ydotool_socket");
+        assert_eq!(socket_candidates(None, None, None), vec![tmp.clone()]);
+        assert_eq!(
+            socket_candidates(
+                Some("/run/ydotool.sock".into()),
+                Some("/env/ydotool.sock".into()),
+                Some("/run/user/1000".into()),
+            ),
+            vec![
+                PathBuf::from("/run/ydotool.sock"),
+                PathBuf::from("/env/ydotool.sock"),
+                PathBuf::from("/run/user/1000/.ydotool_socket"),
+                tmp.clone(),
+            ]
+        );
+        // A setting matching the environment isn't listed twice.
+        assert_eq!(
+            socket_candidates(Some(tmp.clone()), Some(tmp.clone()), None),
+            vec![tmp]
+        );
+    }
+
+    #[test]
+    fn test_ydotoold_recovery() {
+        assert_eq!(
+            ydotoold_recovery(true, || panic!("no restart when ready")),
+            None
+        );
+        assert_eq!(
+            ydotoold_recovery(false, || true),
+            Some(YdotooldRecovery::Restarted)
+        );
+        assert_eq!(
+            ydotoold_recovery(false, || false),
+            Some(YdotooldRecovery::CopiedInstead)
+        );
+    }
+
+    #[test]
+    fn test_is_focus_window() {
+        assert!(is_focus_window("69206023\n"));
+        assert!(!is_focus_window("1\n"));
+        assert!(!is_focus_window("0"));
+        assert!(!is_focus_window(""));
+    }
+
+    #[test]
+    fn test_pick_clipboard_method_explicit() {
+        assert_eq!(pick_clipboard_method("xdotool"), PasteMethod::Xdotool);
+        assert_eq!(pick_clipboard_method("ydotool"), PasteMethod::Ydotool);
+    }
+
+    #[test]
+    fn test_flatten_newlines() {
+        assert_eq!(
+            flatten_newlines("ls -la\nrm -rf build"),
+            "ls -la rm -rf build"
+        );
+        assert_eq!(flatten_newlines("one\r\n\r\ntwo\n"), "one two");
+        assert_eq!(flatten_newlines("no newlines"), "no newlines");
+    }
+
+    #[test]
+    fn test_is_terminal_class() {
+        assert!(is_terminal_class("kitty"));
+        assert!(is_terminal_class("org.kde.konsole"));
+        assert!(is_terminal_class("Alacritty"));
+        assert!(!is_terminal_class("firefox"));
+    }
+
     #[test]
     fn test_paste_config_clone() {
         let config = PasteConfig {
             method: PasteMethod::Xdotool,
-            hotkey: "ctrl+v".into(),
+            auto_paste: true,
+            hotkey: Hotkey::from_setting("paste_hotkey", "ctrl+v", false).unwrap(),
+            terminal_hotkey: None,
             clipboard_paste: "auto".into(),
             clipboard_paste_delay_ms: 75,
+            trailing_space: true,
+            suffix: None,
+            terminal_safe: false,
+            require_focus: false,
+            overrides: Vec::new(),
         };
         let cloned = config.clone();
         assert_eq!(cloned.method, PasteMethod::Xdotool);
-        assert_eq!(cloned.hotkey, "ctrl+v");
+        assert_eq!(cloned.hotkey.keys, "ctrl+v");
+    }
+
+    #[test]
+    fn test_hotkey_for_window() {
+        let settings = Settings {
+            paste_method: "wtype".into(),
+            ..Settings::default()
+        };
+        let config = PasteConfig::from_settings(&settings).unwrap();
+        assert_eq!(config.terminal_hotkey, None);
+        assert_eq!(config.hotkey_for_window(Some("kitty")).keys, "ctrl+v");
+
+        let config = PasteConfig::from_settings(&Settings {
+            terminal_paste_hotkey: "ctrl+shift+v".into(),
+            ..settings.clone()
+        })
+        .unwrap();
+        assert!(config.needs_active_window());
+        assert_eq!(
+            config.hotkey_for_window(Some("Alacritty")).keys,
+            "ctrl+shift+v"
+        );
+        assert_eq!(config.hotkey_for_window(Some("firefox")).keys, "ctrl+v");
+        // Unknown focus keeps the default chord.
+        assert_eq!(config.hotkey_for_window(None).keys, "ctrl+v");
+
+        let config = PasteConfig::from_settings(&Settings {
+            paste_hotkey: "29+47".into(),
+            terminal_paste_hotkey: "29+42+47".into(),
+            paste_hotkey_uses_keycode: true,
+            ..settings.clone()
+        })
+        .unwrap();
+        assert_eq!(
+            config.hotkey_for_window(Some("foot")).ydotool_args(),
+            vec!["29:1", "42:1", "47:1", "47:0", "42:0", "29:0"]
+        );
+
+        let err = PasteConfig::from_settings(&Settings {
+            terminal_paste_hotkey: "ctrl+shift+v".into(),
+            paste_hotkey_uses_keycode: true,
+            paste_hotkey: "29+47".into(),
+            ..settings
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("terminal_paste_hotkey"));
     }
 }
@@ -0,0 +1,217 @@
+//! PipeWire capture sources, listed from `pw-dump` for `--list-audio-sources`
+//! and picked with `audio_source`. Node names stay the same across reboots
+//! and replugging, unlike ALSA card numbers.
+
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::process::Command;
+
+/// A PipeWire node that captures audio (`media.class` Audio/Source).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSource {
+    /// `node.name`, the value to put in `audio_source`.
+    pub name: String,
+    /// `node.description`, e.g. "Blue Yeti Analog Stereo".
+    pub description: String,
+    /// `object.serial`; `pw-record --target` accepts it too, but it changes
+    /// whenever the device reappears.
+    pub serial: Option<u64>,
+    /// The session's default source.
+    pub is_default: bool,
+}
+
+/// List capture sources by running `pw-dump`.
+pub fn list_audio_sources() -> Result<Vec<AudioSource>> {
+    let output = Command::new("pw-dump")
+        .output()
+        .context("Failed to run pw-dump. Is PipeWire (pipewire-utils) installed?")?;
+    if !output.status.success() {
+        bail!("pw-dump exited with {}", output.status);
+    }
+    parse_pw_dump(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Capture sources from `pw-dump` JSON, in the order PipeWire lists them.
+fn parse_pw_dump(json: &str) -> Result<Vec<AudioSource>> {
+    let objects: Vec<Value> = serde_json::from_str(json).context("Invalid pw-dump output")?;
+    let default = default_source(&objects);
+    Ok(objects
+        .iter()
+        .filter(|object| object["type"] == "PipeWire:Interface:Node")
+        .filter_map(|object| {
+            let props = &object["info"]["props"];
+            if !props["media.class"]
+                .as_str()
+                .is_some_and(|class| class.starts_with("Audio/Source"))
+            {
+                return None;
+            }
+            let name = props["node.name"].as_str()?.to_string();
+            Some(AudioSource {
+                description: props["node.description"]
+                    .as_str()
+                    .unwrap_or(&name)
+                    .to_string(),
+                serial: props["object.serial"].as_u64(),
+                is_default: default.as_deref() == Some(name.as_str()),
+                name,
+            })
+        })
+        .collect())
+}
+
+/// `default.audio.source` from the "default" metadata object.
+fn default_source(objects: &[Value]) -> Option<String> {
+    objects
+        .iter()
+        .filter(|object| object["props"]["metadata.name"] == "default")
+        .filter_map(|object| object["metadata"].as_array())
+        .flatten()
+        .find(|entry| entry["key"] == "default.audio.source")
+        .and_then(|entry| entry["value"]["name"].as_str())
+        .map(str::to_string)
+}
+
+/// Print capture sources for `--list-audio-sources`.
+pub fn list_audio_sources_cli(configured: &str) -> Result<()> {
+    let sources = list_audio_sources()?;
+    println!("PipeWire audio sources (* = default, set audio_source to the name):");
+    for source in &sources {
+        let marker = if source.is_default { '*' } else { ' ' };
+        let configured = if source.name == configured {
+            "  [configured]"
+        } else {
+            ""
+        };
+        println!(
+            "  {marker} {}\n      {}{configured}",
+            source.name, source.description
+        );
+    }
+    if sources.is_empty() {
+        println!("  (none found)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed `pw-dump` output from a laptop with a USB microphone plugged in.
+    const PW_DUMP: &str = r#"[
+      {
+        "id": 0,
+        "type": "PipeWire:Interface:Core",
+        "info": { "name": "pipewire-0" }
+      },
+      {
+        "id": 32,
+        "type": "PipeWire:Interface:Metadata",
+        "props": { "metadata.name": "settings" },
+        "metadata": [ { "subject": 0, "key": "clock.rate", "value": 48000 } ]
+      },
+      {
+        "id": 41,
+        "type": "PipeWire:Interface:Metadata",
+        "props": { "metadata.name": "default" },
+        "metadata": [
+          { "subject": 0, "key": "default.audio.sink", "type": "Spa:String:JSON",
+            "value": { "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" } },
+          { "subject": 0, "key": "default.audio.source", "type": "Spa:String:JSON",
+            "value": { "name": "alsa_input.usb-Blue_Microphones_Yeti-00.analog-stereo" } }
+        ]
+      },
+      {
+        "id": 56,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+          "state": "suspended",
+          "props": {
+            "media.class": "Audio/Sink",
+            "node.name": "alsa_output.pci-0000_00_1f.3.analog-stereo",
+            "node.description": "Built-in Audio Analog Stereo",
+            "object.serial": 57
+          }
+        }
+      },
+      {
+        "id": 58,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+          "state": "suspended",
+          "props": {
+            "media.class": "Audio/Source",
+            "node.name": "alsa_input.pci-0000_00_1f.3.analog-stereo",
+            "node.description": "Built-in Audio Analog Stereo",
+            "object.serial": 59
+          }
+        }
+      },
+      {
+        "id": 73,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+          "state": "running",
+          "props": {
+            "media.class": "Audio/Source",
+            "node.name": "alsa_input.usb-Blue_Microphones_Yeti-00.analog-stereo",
+            "node.description": "Yeti Stereo Microphone Analog Stereo",
+            "object.serial": 412
+          }
+        }
+      },
+      {
+        "id": 80,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+          "props": {
+            "media.class": "Audio/Source/Virtual",
+            "node.name": "noise-cancel-source"
+          }
+        }
+      },
+      {
+        "id": 91,
+        "type": "PipeWire:Interface:Node",
+        "info": {
+          "props": { "media.class": "Stream/Input/Audio", "node.name": "Firefox" }
+        }
+      }
+    ]"#;
+
+    #[test]
+    fn test_parse_pw_dump() {
+        let sources = parse_pw_dump(PW_DUMP).unwrap();
+        assert_eq!(
+            sources,
+            vec![
+                AudioSource {
+                    name: "alsa_input.pci-0000_00_1f.3.analog-stereo".into(),
+                    description: "Built-in Audio Analog Stereo".into(),
+                    serial: Some(59),
+                    is_default: false,
+                },
+                AudioSource {
+                    name: "alsa_input.usb-Blue_Microphones_Yeti-00.analog-stereo".into(),
+                    description: "Yeti Stereo Microphone Analog Stereo".into(),
+                    serial: Some(412),
+                    is_default: true,
+                },
+                // A virtual source without a description falls back to its name.
+                AudioSource {
+                    name: "noise-cancel-source".into(),
+                    description: "noise-cancel-source".into(),
+                    serial: None,
+                    is_default: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pw_dump_invalid() {
+        assert!(parse_pw_dump("").is_err());
+        assert!(parse_pw_dump("[]").unwrap().is_empty());
+    }
+}
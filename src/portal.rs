@@ -0,0 +1,211 @@
+//! Trigger via the XDG desktop portal's GlobalShortcuts interface
+//! (`trigger_backend = portal`). The desktop owns the key binding, so no
+//! access to /dev/input is needed.
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SHORTCUTS_IFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_IFACE: &str = "org.freedesktop.portal.Request";
+
+/// Shortcut id for hold-to-talk.
+pub const DICTATE_ID: &str = "dictate";
+/// Shortcut id for discarding the recording, bound only with a cancel key.
+pub const CANCEL_ID: &str = "cancel";
+
+/// A bound shortcut being pressed or released.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShortcutEvent {
+    pub id: String,
+    pub pressed: bool,
+}
+
+/// A GlobalShortcuts session. It lives as long as the D-Bus connection.
+#[derive(Clone)]
+pub struct Shortcuts {
+    conn: Connection,
+    session: OwnedObjectPath,
+    /// The keys the desktop assigned to the dictate shortcut, if it said.
+    pub trigger_description: Option<String>,
+}
+
+impl Shortcuts {
+    /// Open a session and bind the dictate shortcut (and cancel, if wanted).
+    /// The desktop asks the user to pick or confirm the keys.
+    pub fn bind(with_cancel: bool) -> Result<Self> {
+        let conn = Connection::session().context("Failed to connect to the session bus")?;
+        let proxy = Proxy::new(&conn, PORTAL_DEST, PORTAL_PATH, SHORTCUTS_IFACE)
+            .context("GlobalShortcuts portal not available")?;
+        let token = format!("escucha{}", std::process::id());
+
+        let options = HashMap::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("session_handle_token", Value::from(token.as_str())),
+        ]);
+        let results = call_request(&conn, &proxy, "CreateSession", &token, &(options,))?;
+        let session = results
+            .get("session_handle")
+            .and_then(|v| <&str>::try_from(v).ok())
+            .context("CreateSession returned no session handle")?;
+        let session = OwnedObjectPath::try_from(session.to_string())
+            .context("CreateSession returned an invalid session handle")?;
+
+        let mut shortcuts = vec![(
+            DICTATE_ID,
+            HashMap::from([("description", Value::from("Hold to dictate"))]),
+        )];
+        if with_cancel {
+            shortcuts.push((
+                CANCEL_ID,
+                HashMap::from([("description", Value::from("Cancel dictation"))]),
+            ));
+        }
+        let bind_token = format!("{token}_bind");
+        let options = HashMap::from([("handle_token", Value::from(bind_token.as_str()))]);
+        let body = (ObjectPath::from(&session), shortcuts, "", options);
+        let results = call_request(&conn, &proxy, "BindShortcuts", &bind_token, &body)?;
+        let trigger_description = results
+            .get("shortcuts")
+            .and_then(|v| {
+                Vec::<(String, HashMap<String, OwnedValue>)>::try_from(v.try_clone().ok()?).ok()
+            })
+            .and_then(|bound| trigger_description(&bound));
+
+        Ok(Self {
+            conn,
+            session,
+            trigger_description,
+        })
+    }
+
+    /// Deliver Activated/Deactivated signals for this session until
+    /// `on_event` returns false or the connection is closed.
+    pub fn listen(&self, mut on_event: impl FnMut(ShortcutEvent) -> bool) -> Result<()> {
+        let proxy = Proxy::new(&self.conn, PORTAL_DEST, PORTAL_PATH, SHORTCUTS_IFACE)?;
+        for signal in proxy.receive_all_signals()? {
+            let header = signal.header();
+            let Some(pressed) = header.member().and_then(|m| signal_pressed(m.as_str())) else {
+                continue;
+            };
+            let (session, id, _timestamp, _options): (
+                OwnedObjectPath,
+                String,
+                u64,
+                HashMap<String, OwnedValue>,
+            ) = signal.body().deserialize()?;
+            if session != self.session {
+                continue;
+            }
+            if !on_event(ShortcutEvent { id, pressed }) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// End the session, which also stops a blocked `listen`.
+    pub fn close(&self) {
+        if let Err(e) = self.conn.clone().close() {
+            log::debug!("Closing the portal connection failed: {e}");
+        }
+    }
+}
+
+/// Whether a GlobalShortcuts signal is a press (Activated) or release
+/// (Deactivated); None for anything else.
+fn signal_pressed(member: &str) -> Option<bool> {
+    match member {
+        "Activated" => Some(true),
+        "Deactivated" => Some(false),
+        _ => None,
+    }
+}
+
+/// Object path of the Request the portal creates for `token`, so its
+/// Response can be subscribed to before the call is made.
+fn request_path(unique_name: &str, token: &str) -> String {
+    let sender = unique_name.trim_start_matches(':').replace('.', "_");
+    format!("{PORTAL_PATH}/request/{sender}/{token}")
+}
+
+/// The trigger the desktop assigned to the dictate shortcut.
+fn trigger_description(bound: &[(String, HashMap<String, OwnedValue>)]) -> Option<String> {
+    let (_, props) = bound.iter().find(|(id, _)| id == DICTATE_ID)?;
+    let desc = <&str>::try_from(props.get("trigger_description")?).ok()?;
+    (!desc.is_empty()).then(|| desc.to_string())
+}
+
+/// Call a portal method that answers through a Request's Response signal.
+fn call_request<B>(
+    conn: &Connection,
+    proxy: &Proxy,
+    method: &str,
+    token: &str,
+    body: &B,
+) -> Result<HashMap<String, OwnedValue>>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let unique_name = conn
+        .unique_name()
+        .context("No unique name on the session bus")?;
+    let request = Proxy::new(
+        conn,
+        PORTAL_DEST,
+        request_path(unique_name.as_str(), token),
+        REQUEST_IFACE,
+    )?;
+    let mut responses = request.receive_signal("Response")?;
+    proxy
+        .call_method(method, body)
+        .with_context(|| format!("GlobalShortcuts.{method} failed"))?;
+    let response = responses
+        .next()
+        .with_context(|| format!("No response to GlobalShortcuts.{method}"))?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = response.body().deserialize()?;
+    match code {
+        0 => Ok(results),
+        1 => bail!("GlobalShortcuts.{method} was cancelled"),
+        _ => bail!("GlobalShortcuts.{method} failed (response {code})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(
+            request_path(":1.42", "escucha7"),
+            "/org/freedesktop/portal/desktop/request/1_42/escucha7"
+        );
+    }
+
+    #[test]
+    fn test_signal_pressed() {
+        assert_eq!(signal_pressed("Activated"), Some(true));
+        assert_eq!(signal_pressed("Deactivated"), Some(false));
+        assert_eq!(signal_pressed("ShortcutsChanged"), None);
+    }
+
+    #[test]
+    fn test_trigger_description() {
+        let bound = vec![
+            (CANCEL_ID.to_string(), HashMap::new()),
+            (
+                DICTATE_ID.to_string(),
+                HashMap::from([(
+                    "trigger_description".to_string(),
+                    OwnedValue::try_from(Value::from("Ctrl+Alt+D")).unwrap(),
+                )]),
+            ),
+        ];
+        assert_eq!(trigger_description(&bound).as_deref(), Some("Ctrl+Alt+D"));
+        assert_eq!(trigger_description(&[]), None);
+    }
+}
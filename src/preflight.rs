@@ -1,5 +1,11 @@
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// First wait between preflight retries (`retry_preflight`); doubled after
+/// each failed attempt up to `MAX_RETRY_DELAY`.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 /// Severity of a preflight check result.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -76,16 +82,64 @@ impl fmt::Display for PreflightReport {
     }
 }
 
+/// Re-run `check_environment` until it has no critical failures or
+/// `timeout` has passed, for `retry_preflight`. Returns the last report.
+pub fn wait_for_environment(timeout: Duration) -> PreflightReport {
+    retry_until_ready(timeout, check_environment, std::thread::sleep)
+}
+
+fn retry_until_ready(
+    timeout: Duration,
+    mut check: impl FnMut() -> PreflightReport,
+    mut sleep: impl FnMut(Duration),
+) -> PreflightReport {
+    let mut waited = Duration::ZERO;
+    let mut delay = FIRST_RETRY_DELAY;
+    loop {
+        let report = check();
+        if !report.has_critical_failures() || waited >= timeout {
+            return report;
+        }
+        let wait = delay.min(timeout - waited);
+        log::info!(
+            "Waiting for the environment ({}); checking again in {}s",
+            blocking_checks(&report),
+            wait.as_secs_f32()
+        );
+        sleep(wait);
+        waited += wait;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// The failing critical checks with their messages, for the retry log.
+fn blocking_checks(report: &PreflightReport) -> String {
+    report
+        .checks
+        .iter()
+        .filter(|c| !c.passed && c.severity == CheckSeverity::Critical)
+        .map(|c| format!("{}: {}", c.name, c.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Run all environment checks and return a report.
 pub fn check_environment() -> PreflightReport {
+    let settings = crate::config::load_settings().unwrap_or_default();
+    crate::paste::set_ydotool_socket(&settings.ydotool_socket);
     let checks = vec![
-        check_input_access(),
+        check_input_access(&settings),
         check_arecord(),
-        check_paste_tool(),
-        check_curl(),
+        check_paste_tool(&settings),
+        check_curl(&settings),
+        check_model_language(&settings),
+        check_conflicting_tools(),
         check_directory(
             "config dir",
-            crate::config::config_dir(),
+            crate::config::config_path()
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(crate::config::config_dir),
             CheckSeverity::Critical,
         ),
         check_directory(
@@ -100,14 +154,26 @@ pub fn check_environment() -> PreflightReport {
                 .join("escucha"),
             CheckSeverity::Warning,
         ),
+        check_directory(
+            "recording dir",
+            crate::audio::recording_dir(&settings.recording_dir),
+            CheckSeverity::Critical,
+        ),
     ];
 
     PreflightReport { checks }
 }
 
 /// Check if we can access /dev/input devices (need input group).
-fn check_input_access() -> CheckResult {
+/// Only a warning with `trigger_backend = portal`, which needs them only
+/// as a fallback.
+fn check_input_access(settings: &crate::config::Settings) -> CheckResult {
     let name = "input devices";
+    let severity = if settings.trigger_backend.trim() == "portal" {
+        CheckSeverity::Warning
+    } else {
+        CheckSeverity::Critical
+    };
 
     let entries = match std::fs::read_dir("/dev/input") {
         Ok(e) => e,
@@ -115,7 +181,7 @@ fn check_input_access() -> CheckResult {
             return CheckResult {
                 name,
                 passed: false,
-                severity: CheckSeverity::Critical,
+                severity,
                 message: "Cannot read /dev/input".into(),
                 hint: Some("sudo usermod -aG input $USER  (then log out and back in)".into()),
             };
@@ -132,7 +198,7 @@ fn check_input_access() -> CheckResult {
             return CheckResult {
                 name,
                 passed: true,
-                severity: CheckSeverity::Critical,
+                severity,
                 message: format!("Can access {}", path.display()),
                 hint: None,
             };
@@ -142,7 +208,7 @@ fn check_input_access() -> CheckResult {
     CheckResult {
         name,
         passed: false,
-        severity: CheckSeverity::Critical,
+        severity,
         message: "No input devices accessible (permission denied)".into(),
         hint: Some("sudo usermod -aG input $USER  (then log out and back in)".into()),
     }
@@ -170,11 +236,40 @@ fn check_arecord() -> CheckResult {
 }
 
 /// Check if an appropriate paste tool is available.
-fn check_paste_tool() -> CheckResult {
+fn check_paste_tool(settings: &crate::config::Settings) -> CheckResult {
     let name = "paste tool";
     let is_wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
     let is_x11 = std::env::var("DISPLAY").is_ok();
 
+    if let Ok(crate::sink::OutputSink::File(path)) =
+        crate::sink::OutputSink::from_settings(settings)
+    {
+        return CheckResult {
+            name,
+            passed: true,
+            severity: CheckSeverity::Critical,
+            message: format!("Not needed (output_sink = file: {})", path.display()),
+            hint: None,
+        };
+    }
+
+    if !settings.auto_paste {
+        // Copy-only: just the clipboard tool is needed.
+        let tool = if is_wayland { "wl-copy" } else { "xclip" };
+        let found = which::which(tool).is_ok();
+        return CheckResult {
+            name,
+            passed: found,
+            severity: CheckSeverity::Critical,
+            message: if found {
+                format!("{tool} found (auto_paste off: copy only)")
+            } else {
+                format!("{tool} not found (needed to copy with auto_paste off)")
+            },
+            hint: (!found).then(|| format!("Install {tool}")),
+        };
+    }
+
     if is_wayland {
         if which::which("ydotool").is_ok() {
             if crate::paste::ydotool_ready() || crate::paste::ensure_ydotoold_running() {
@@ -271,11 +366,10 @@ fn check_paste_tool() -> CheckResult {
 }
 
 /// Check if curl is available (needed for model downloads).
-fn check_curl() -> CheckResult {
+fn check_curl(settings: &crate::config::Settings) -> CheckResult {
     let name = "curl";
 
-    // If the default model is already cached, curl isn't needed
-    let settings = crate::config::Settings::default();
+    // If the configured model is already cached, curl isn't needed
     let model_path = crate::transcribe::model_path(&settings.model);
     if model_path.exists() {
         return CheckResult {
@@ -286,6 +380,24 @@ fn check_curl() -> CheckResult {
             hint: None,
         };
     }
+    if crate::transcribe::explicit_model_path(&settings.model).is_some() {
+        return CheckResult {
+            name,
+            passed: false,
+            severity: CheckSeverity::Warning,
+            message: format!("Model file {} not found", model_path.display()),
+            hint: Some("Check the path, or set model to a name like base.en".into()),
+        };
+    }
+    if settings.offline {
+        return CheckResult {
+            name,
+            passed: false,
+            severity: CheckSeverity::Warning,
+            message: format!("Model {} missing and offline is set", settings.model),
+            hint: Some(format!("Place the model at {}", model_path.display())),
+        };
+    }
 
     match which::which("curl") {
         Ok(path) => CheckResult {
@@ -300,21 +412,160 @@ fn check_curl() -> CheckResult {
             passed: false,
             severity: CheckSeverity::Warning,
             message: "curl not found (needed to download Whisper model)".into(),
-            hint: Some("Install curl".into()),
+            hint: Some(format!(
+                "Install curl, or place the model at {}",
+                model_path.display()
+            )),
         },
     }
 }
 
-/// Check if a directory can be created/accessed.
-fn check_directory(name: &'static str, path: PathBuf, severity: CheckSeverity) -> CheckResult {
-    match std::fs::create_dir_all(&path) {
-        Ok(()) if path.is_dir() => CheckResult {
+/// Check that English-only models aren't paired with another language.
+fn check_model_language(settings: &crate::config::Settings) -> CheckResult {
+    let name = "model language";
+    let warning = [&settings.model, &settings.fallback_model]
+        .into_iter()
+        .find_map(|model| crate::transcribe::language_mismatch(model, &settings.language));
+
+    match warning {
+        Some(message) => CheckResult {
+            name,
+            passed: false,
+            severity: CheckSeverity::Warning,
+            message,
+            hint: Some("Set `model` to a multilingual model, or `language = en`".into()),
+        },
+        None => CheckResult {
             name,
             passed: true,
-            severity,
-            message: format!("{}", path.display()),
+            severity: CheckSeverity::Warning,
+            message: format!("{} ({})", settings.model, settings.language),
             hint: None,
         },
+    }
+}
+
+/// Other dictation tools that read the keyboard or type text; running one
+/// alongside escucha causes doubled text or "device busy" errors.
+const CONFLICTING_TOOLS: &[&str] = &[
+    "escucha",
+    "nerd-dictation",
+    "whisper-dictation",
+    "whisper-writer",
+    "hyprwhspr",
+    "voxtype",
+    "numen",
+];
+
+/// Names a process may be known by: its executable and, for interpreted
+/// tools like `python3 nerd-dictation`, its script.
+fn process_names(cmdline: &str) -> Vec<&str> {
+    cmdline
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .take(2)
+        .map(|arg| arg.rsplit('/').next().unwrap_or(arg))
+        .collect()
+}
+
+/// Descriptions of conflicting processes among `(pid, cmdline)` pairs, where
+/// `cmdline` is NUL-separated as in `/proc/<pid>/cmdline`.
+fn find_conflicting_processes(processes: &[(u32, String)], own_pid: u32) -> Vec<String> {
+    processes
+        .iter()
+        .filter(|(pid, _)| *pid != own_pid)
+        .filter_map(|(pid, cmdline)| {
+            let tool = process_names(cmdline)
+                .into_iter()
+                .find(|name| CONFLICTING_TOOLS.contains(name))?;
+            Some(if tool == "escucha" {
+                format!("another escucha instance (pid {pid})")
+            } else {
+                format!("{tool} (pid {pid})")
+            })
+        })
+        .collect()
+}
+
+fn running_processes() -> Vec<(u32, String)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let cmdline = std::fs::read(entry.path().join("cmdline")).ok()?;
+            Some((pid, String::from_utf8_lossy(&cmdline).into_owned()))
+        })
+        .collect()
+}
+
+/// Dictation tools and foreign ydotool daemons that may compete with escucha
+/// for the keyboard or the virtual input device.
+pub fn conflicting_tools() -> Vec<String> {
+    let mut conflicts = find_conflicting_processes(&running_processes(), std::process::id());
+    // A root-owned socket is the usual system-wide ydotoold; another user's
+    // daemon means a second session is typing through it.
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    if let Some((path, owner)) = crate::paste::ydotool_socket_owner()
+        && owner != 0
+        && owner != uid
+    {
+        conflicts.push(format!(
+            "ydotool socket {} owned by uid {owner}",
+            path.display()
+        ));
+    }
+    conflicts
+}
+
+/// Check for other dictation tools running alongside escucha.
+fn check_conflicting_tools() -> CheckResult {
+    let name = "other dictation";
+    let conflicts = conflicting_tools();
+    if conflicts.is_empty() {
+        return CheckResult {
+            name,
+            passed: true,
+            severity: CheckSeverity::Warning,
+            message: "No other dictation tools running".into(),
+            hint: None,
+        };
+    }
+    CheckResult {
+        name,
+        passed: false,
+        severity: CheckSeverity::Warning,
+        message: format!("Also running: {}", conflicts.join(", ")),
+        hint: Some(
+            "Run one dictation tool at a time (the tray app or escucha.service, not both) \
+             to avoid doubled text and busy devices"
+                .into(),
+        ),
+    }
+}
+
+/// Check if a directory can be created and written to.
+fn check_directory(name: &'static str, path: PathBuf, severity: CheckSeverity) -> CheckResult {
+    match std::fs::create_dir_all(&path) {
+        Ok(()) if path.is_dir() => match tempfile::tempfile_in(&path) {
+            Ok(_) => CheckResult {
+                name,
+                passed: true,
+                severity,
+                message: format!("{}", path.display()),
+                hint: None,
+            },
+            Err(e) => CheckResult {
+                name,
+                passed: false,
+                severity,
+                message: format!("{} is not writable: {e}", path.display()),
+                hint: Some("Check file system permissions".into()),
+            },
+        },
         Ok(()) => CheckResult {
             name,
             passed: false,
@@ -356,6 +607,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_retry_until_ready_stops_when_checks_pass() {
+        let mut attempts = 0;
+        let mut sleeps = Vec::new();
+        let report = retry_until_ready(
+            Duration::from_secs(60),
+            || {
+                attempts += 1;
+                let input = if attempts < 4 {
+                    fail("input", CheckSeverity::Critical)
+                } else {
+                    pass("input")
+                };
+                PreflightReport {
+                    checks: vec![input, fail("tray", CheckSeverity::Warning)],
+                }
+            },
+            |d| sleeps.push(d.as_secs()),
+        );
+        // Warnings don't keep it waiting.
+        assert!(!report.has_critical_failures());
+        assert_eq!(attempts, 4);
+        assert_eq!(sleeps, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_retry_until_ready_gives_up_after_timeout() {
+        let mut attempts = 0;
+        let mut sleeps = Vec::new();
+        let report = retry_until_ready(
+            Duration::from_secs(10),
+            || {
+                attempts += 1;
+                PreflightReport {
+                    checks: vec![fail("input", CheckSeverity::Critical)],
+                }
+            },
+            |d| sleeps.push(d.as_secs()),
+        );
+        assert!(report.has_critical_failures());
+        // 1 + 2 + 4 seconds, then the last wait is cut to the time left.
+        assert_eq!(sleeps, vec![1, 2, 4, 3]);
+        assert_eq!(attempts, 5);
+    }
+
+    #[test]
+    fn test_retry_until_ready_zero_timeout_checks_once() {
+        let mut attempts = 0;
+        retry_until_ready(
+            Duration::ZERO,
+            || {
+                attempts += 1;
+                PreflightReport {
+                    checks: vec![fail("input", CheckSeverity::Critical)],
+                }
+            },
+            |_| panic!("no waiting with a zero timeout"),
+        );
+        assert_eq!(attempts, 1);
+    }
+
     #[test]
     fn test_no_failures() {
         let report = PreflightReport {
@@ -435,15 +747,78 @@ mod tests {
         assert!(!result.name.is_empty());
     }
 
+    #[test]
+    fn test_check_model_language() {
+        let mut settings = crate::config::Settings {
+            model: "base.en".into(),
+            language: "es".into(),
+            ..Default::default()
+        };
+        let result = check_model_language(&settings);
+        assert!(!result.passed);
+        assert_eq!(result.severity, CheckSeverity::Warning);
+        assert!(result.message.contains("use base instead"));
+
+        settings.model = "base".into();
+        assert!(check_model_language(&settings).passed);
+
+        settings.fallback_model = "medium.en".into();
+        assert!(!check_model_language(&settings).passed);
+    }
+
+    #[test]
+    fn test_find_conflicting_processes() {
+        let processes = vec![
+            (100, "/usr/bin/escucha\0--gui\0".to_string()),
+            (200, "/usr/bin/escucha\0".to_string()),
+            (
+                300,
+                "python3\0/home/u/bin/nerd-dictation\0begin\0".to_string(),
+            ),
+            (400, "/usr/bin/firefox\0".to_string()),
+            (500, "".to_string()),
+        ];
+        assert_eq!(
+            find_conflicting_processes(&processes, 100),
+            [
+                "another escucha instance (pid 200)",
+                "nerd-dictation (pid 300)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_names() {
+        assert_eq!(
+            process_names("/usr/bin/python3\0/opt/x/whisper-writer\0-v\0"),
+            ["python3", "whisper-writer"]
+        );
+        assert_eq!(process_names("escucha"), ["escucha"]);
+        assert!(process_names("").is_empty());
+    }
+
     #[test]
     fn test_check_curl_does_not_panic() {
-        let result = check_curl();
+        let result = check_curl(&crate::config::Settings::default());
         assert!(!result.name.is_empty());
     }
 
+    #[test]
+    fn test_check_curl_offline_missing_model() {
+        let settings = crate::config::Settings {
+            model: "no-such-model".into(),
+            offline: true,
+            ..Default::default()
+        };
+        let result = check_curl(&settings);
+        assert!(!result.passed);
+        assert!(result.message.contains("offline"));
+        assert!(result.hint.unwrap().contains("ggml-no-such-model.bin"));
+    }
+
     #[test]
     fn test_check_paste_tool_does_not_panic() {
-        let result = check_paste_tool();
+        let result = check_paste_tool(&crate::config::Settings::default());
         assert!(!result.name.is_empty());
     }
 }
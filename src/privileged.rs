@@ -0,0 +1,109 @@
+//! Helpers for the shell scripts escucha runs as root through pkexec
+//! (input group and /dev/uinput setup).
+
+use anyhow::{Result, bail};
+
+/// Longest user name `useradd` accepts.
+const MAX_USERNAME_LEN: usize = 32;
+
+/// Quote `arg` as a single POSIX shell word.
+pub fn shell_quote(arg: &str) -> String {
+    let escaped = arg.replace('\'', "'\"'\"'");
+    format!("'{escaped}'")
+}
+
+/// Whether `name` is a user name `useradd` would create: ASCII letters,
+/// digits, `_`, `.` and `-` (not first), an optional trailing `$` for
+/// machine accounts, and not entirely digits or `.`/`..`.
+pub fn is_valid_username(name: &str) -> bool {
+    let base = name.strip_suffix('$').unwrap_or(name);
+    let mut chars = base.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    name.len() <= MAX_USERNAME_LEN
+        && (first.is_ascii_alphanumeric() || first == '_' || first == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+        && !base.chars().all(|c| c.is_ascii_digit())
+        && base != "."
+        && base != ".."
+}
+
+/// The current user (`$USER`), checked before it goes into a root script.
+pub fn current_user() -> Result<String> {
+    let user = std::env::var("USER").unwrap_or_default();
+    if user.is_empty() {
+        bail!("Could not determine current username");
+    }
+    if !is_valid_username(&user) {
+        bail!("Refusing to use username {user:?} in privileged setup");
+    }
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// What `sh` makes of a quoted word.
+    fn sh_word(quoted: &str) -> String {
+        let output = Command::new("sh")
+            .args(["-c", &format!("printf %s {quoted}")])
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("it's"), "'it'\"'\"'s'");
+        assert_eq!(shell_quote(""), "''");
+        for arg in [
+            "it's",
+            "''",
+            "\"double\"",
+            "two words",
+            "$(rm -rf ~) `id` $HOME",
+            "semi;colon && pipe | glob*?",
+            "back\\slash",
+            "line\nbreak",
+            "jürgen.müller",
+            "--flag",
+        ] {
+            assert_eq!(sh_word(&shell_quote(arg)), arg);
+        }
+    }
+
+    #[test]
+    fn test_is_valid_username() {
+        for name in [
+            "alice",
+            "j.doe",
+            "first.last-2",
+            "_svc",
+            ".hidden",
+            "build$",
+            "a1",
+        ] {
+            assert!(is_valid_username(name), "{name}");
+        }
+        for name in [
+            "",
+            "-rf",
+            "jürgen",
+            "two words",
+            "a;b",
+            "a'b",
+            "1234",
+            ".",
+            "..",
+            "$",
+            "a$b",
+            &"x".repeat(33),
+        ] {
+            assert!(!is_valid_username(name), "{name}");
+        }
+    }
+}
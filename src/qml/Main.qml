@@ -1,4 +1,6 @@
 import QtQuick
+import QtQuick.Controls
+import QtQuick.Layouts
 import Qt.labs.platform as Platform
 import io.github.escucha
 
@@ -29,6 +31,26 @@ Item {
         tray.showMessage("Escucha - " + backend.statusText, body, Platform.SystemTrayIcon.Information, 2500)
     }
 
+    function showSettings() {
+        settingsWindow.show()
+        settingsWindow.raise()
+        settingsWindow.requestActivate()
+    }
+
+    function toggleSettings() {
+        if (settingsWindow.visible) {
+            settingsWindow.hide()
+        } else {
+            root.showSettings()
+        }
+    }
+
+    Component.onCompleted: {
+        if (!backend.startMinimized) {
+            root.showSettings()
+        }
+    }
+
     Platform.SystemTrayIcon {
         id: tray
         visible: true
@@ -49,7 +71,17 @@ Item {
             }
 
             Platform.MenuItem {
-                text: backend.statusDetail ? backend.statusDetail : "Hold Right Ctrl to speak"
+                text: "Key: " + backend.triggerKeyLabel
+                enabled: false
+            }
+
+            Platform.MenuItem {
+                text: "Paste: " + (backend.pasteMethodLabel || "Detecting...")
+                enabled: false
+            }
+
+            Platform.MenuItem {
+                text: backend.statusDetail ? backend.statusDetail : ("Hold " + backend.triggerKeyLabel + " to speak")
                 enabled: false
             }
 
@@ -68,12 +100,46 @@ Item {
                 onTriggered: backend.fixPermissions()
             }
 
+            Platform.MenuItem {
+                text: "Choose Trigger Key..."
+                visible: backend.showKeyFixButton
+                onTriggered: {
+                    root.showSettings()
+                    backend.beginKeyCapture()
+                }
+            }
+
             Platform.MenuItem {
                 text: "Fix Paste Setup"
                 visible: backend.showPasteFixButton
                 onTriggered: backend.fixPasteSetup()
             }
 
+            Platform.MenuItem {
+                text: "Paste Last Transcription"
+                enabled: backend.history.length > 0
+                onTriggered: backend.repasteLast()
+            }
+
+            Platform.MenuItem {
+                text: "History..."
+                onTriggered: {
+                    historyWindow.show()
+                    historyWindow.raise()
+                    historyWindow.requestActivate()
+                }
+            }
+
+            Platform.MenuItem {
+                text: "Settings..."
+                onTriggered: root.showSettings()
+            }
+
+            Platform.MenuItem {
+                text: "Reload Config"
+                onTriggered: backend.reloadConfig()
+            }
+
             Platform.MenuItem {
                 text: "Quit Escucha"
                 onTriggered: {
@@ -85,11 +151,210 @@ Item {
 
         onActivated: function(reason) {
             if (reason === Platform.SystemTrayIcon.Trigger) {
+                root.toggleSettings()
+            } else if (reason === Platform.SystemTrayIcon.MiddleClick) {
                 root.notifyStatus()
             }
         }
     }
 
+    Window {
+        id: levelWindow
+        width: 220
+        height: 36
+        x: (Screen.width - width) / 2
+        y: Screen.height - height - 64
+        visible: backend.isRecording
+        color: "#e0202020"
+        flags: Qt.ToolTip | Qt.FramelessWindowHint | Qt.WindowStaysOnTopHint | Qt.WindowDoesNotAcceptFocus
+
+        Rectangle {
+            anchors.fill: parent
+            anchors.margins: 12
+            radius: height / 2
+            color: "#40ffffff"
+
+            Rectangle {
+                anchors.left: parent.left
+                anchors.top: parent.top
+                anchors.bottom: parent.bottom
+                width: parent.width * backend.audioLevel
+                radius: height / 2
+                color: backend.audioLevel > 0.9 ? "#e74c3c" : "#2ecc71"
+
+                Behavior on width {
+                    NumberAnimation { duration: 90 }
+                }
+            }
+        }
+    }
+
+    Window {
+        id: downloadWindow
+        width: 320
+        height: 72
+        x: (Screen.width - width) / 2
+        y: Screen.height - height - 64
+        visible: backend.isDownloading
+        color: "#e0202020"
+        flags: Qt.ToolTip | Qt.FramelessWindowHint | Qt.WindowStaysOnTopHint | Qt.WindowDoesNotAcceptFocus
+
+        ColumnLayout {
+            anchors.fill: parent
+            anchors.margins: 12
+
+            Label {
+                Layout.fillWidth: true
+                text: backend.statusDetail || backend.statusText
+                color: "white"
+                elide: Text.ElideRight
+            }
+
+            ProgressBar {
+                Layout.fillWidth: true
+                indeterminate: true
+            }
+        }
+    }
+
+    Window {
+        id: historyWindow
+        title: "Escucha History"
+        width: 420
+        height: 360
+        visible: false
+
+        ListView {
+            id: historyList
+            anchors.fill: parent
+            anchors.margins: 12
+            clip: true
+            spacing: 8
+            model: backend.history
+
+            ScrollBar.vertical: ScrollBar {}
+
+            delegate: RowLayout {
+                required property string modelData
+                required property int index
+
+                width: historyList.width
+                spacing: 8
+
+                Label {
+                    Layout.fillWidth: true
+                    text: modelData
+                    wrapMode: Text.WordWrap
+                }
+
+                Button {
+                    text: "Copy"
+                    onClicked: backend.copyEntry(index)
+                }
+            }
+
+            Label {
+                anchors.centerIn: parent
+                visible: historyList.count === 0
+                opacity: 0.7
+                text: "No transcriptions yet"
+            }
+        }
+    }
+
+    Window {
+        id: settingsWindow
+        title: "Escucha Settings"
+        width: 420
+        height: 300
+        visible: false
+
+        GridLayout {
+            anchors.fill: parent
+            anchors.margins: 16
+            columns: 2
+            columnSpacing: 12
+            rowSpacing: 8
+
+            Label { text: "Trigger key" }
+            RowLayout {
+                Layout.fillWidth: true
+
+                TextField {
+                    Layout.fillWidth: true
+                    text: backend.capturingKey ? "Press a key..." : backend.configKey
+                    placeholderText: "KEY_RIGHTCTRL"
+                    readOnly: backend.capturingKey
+                    onEditingFinished: {
+                        if (!backend.capturingKey && text !== backend.configKey) {
+                            backend.updateKey(text)
+                        }
+                    }
+                }
+
+                Button {
+                    text: backend.capturingKey ? "Cancel" : "Capture"
+                    onClicked: {
+                        if (backend.capturingKey) {
+                            backend.cancelKeyCapture()
+                        } else {
+                            backend.beginKeyCapture()
+                        }
+                    }
+                }
+            }
+
+            Label { text: "Input device" }
+            ComboBox {
+                Layout.fillWidth: true
+                model: ["auto"].concat(backend.inputDevices)
+                currentIndex: find(backend.activeDevice)
+                onActivated: backend.selectDevice(currentText.split(" - ")[0])
+            }
+
+            Label { text: "Model" }
+            ComboBox {
+                Layout.fillWidth: true
+                model: backend.modelNames
+                currentIndex: find(backend.configModel)
+                onActivated: backend.updateModel(currentText)
+            }
+
+            Label { text: "Language" }
+            TextField {
+                Layout.fillWidth: true
+                text: backend.configLanguage
+                placeholderText: "en"
+                onEditingFinished: {
+                    if (text !== backend.configLanguage) {
+                        backend.updateLanguage(text)
+                    }
+                }
+            }
+
+            Label { text: "Paste method" }
+            ComboBox {
+                Layout.fillWidth: true
+                model: backend.pasteMethods
+                currentIndex: find(backend.configPasteMethod)
+                onActivated: backend.updatePasteMethod(currentText)
+            }
+
+            Label {
+                Layout.columnSpan: 2
+                Layout.fillWidth: true
+                wrapMode: Text.WordWrap
+                opacity: 0.7
+                text: "Changes are saved to config.ini and applied by restarting the dictation service."
+            }
+
+            Item {
+                Layout.columnSpan: 2
+                Layout.fillHeight: true
+            }
+        }
+    }
+
     Connections {
         target: backend
 
@@ -0,0 +1,128 @@
+use crate::audio::{self, CaptureTiming, Recording, RecordingFormat};
+use crate::config::Settings;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// How long the self-test records for.
+const RECORD_DURATION: Duration = Duration::from_secs(2);
+
+/// Levels (dBFS) at or above this are clearly audible speech.
+const GOOD_LEVEL_DB: f32 = -40.0;
+
+/// Levels below this are treated as silence: a muted or wrong microphone.
+const SILENCE_LEVEL_DB: f32 = -55.0;
+
+/// How a recorded clip's level compares with what dictation needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Verdict {
+    Good,
+    Quiet,
+    Silent,
+}
+
+fn verdict(db: f32) -> Verdict {
+    if db >= GOOD_LEVEL_DB {
+        Verdict::Good
+    } else if db >= SILENCE_LEVEL_DB {
+        Verdict::Quiet
+    } else {
+        Verdict::Silent
+    }
+}
+
+fn guidance(db: f32) -> String {
+    match verdict(db) {
+        Verdict::Good => format!("PASS: we heard audio at {db:.0} dB"),
+        Verdict::Quiet => format!(
+            "WARN: audio is quiet ({db:.0} dB) - speak closer to the mic or raise its input gain"
+        ),
+        Verdict::Silent => {
+            let level = if db.is_finite() {
+                format!("{db:.0} dB")
+            } else {
+                "no signal".into()
+            };
+            format!(
+                "FAIL: near silence ({level}) - check the mic is connected, unmuted and selected \
+                 as the default input (pavucontrol / wpctl status)"
+            )
+        }
+    }
+}
+
+/// Record a short clip from the default input, report its level and play it
+/// back. Returns whether any audio was heard.
+pub fn run(settings: &Settings) -> Result<bool> {
+    let timing = CaptureTiming::from_settings(settings)?;
+    let format = RecordingFormat::from_settings(settings)?;
+    let dir = tempfile::tempdir().context("Failed to create temp dir")?;
+    let wav_path = dir.path().join("selftest.wav");
+
+    println!(
+        "Recording {} seconds - say something...",
+        RECORD_DURATION.as_secs()
+    );
+    let recording = Recording::start(&wav_path, &timing, &format)?;
+    std::thread::sleep(RECORD_DURATION);
+    let wav_path = recording.stop()?;
+
+    let samples = audio::read_wav_samples(&wav_path)?;
+    let db = audio::dbfs(audio::rms(&samples));
+    println!("{}", guidance(db));
+
+    match playback_command(&wav_path) {
+        Some(mut cmd) => {
+            println!("Playing the recording back...");
+            let status = cmd
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .context("Failed to start playback")?;
+            if !status.success() {
+                println!("Playback failed ({status}); check your output device");
+            }
+        }
+        None => println!("Skipping playback: neither pw-cat nor aplay is installed"),
+    }
+
+    Ok(verdict(db) != Verdict::Silent)
+}
+
+/// Player for the recorded clip: PipeWire's `pw-cat`, else ALSA's `aplay`.
+fn playback_command(path: &Path) -> Option<Command> {
+    if which::which("pw-cat").is_ok() {
+        let mut cmd = Command::new("pw-cat");
+        cmd.arg("--playback").arg(path);
+        Some(cmd)
+    } else if which::which("aplay").is_ok() {
+        let mut cmd = Command::new("aplay");
+        cmd.arg("-q").arg(path);
+        Some(cmd)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_thresholds() {
+        assert_eq!(verdict(-20.0), Verdict::Good);
+        assert_eq!(verdict(GOOD_LEVEL_DB), Verdict::Good);
+        assert_eq!(verdict(-45.0), Verdict::Quiet);
+        assert_eq!(verdict(-70.0), Verdict::Silent);
+        assert_eq!(verdict(f32::NEG_INFINITY), Verdict::Silent);
+    }
+
+    #[test]
+    fn test_guidance() {
+        assert_eq!(guidance(-20.4), "PASS: we heard audio at -20 dB");
+        assert!(guidance(-45.0).starts_with("WARN"));
+        assert!(guidance(f32::NEG_INFINITY).contains("near silence (no signal)"));
+        assert!(guidance(-80.0).contains("near silence (-80 dB)"));
+    }
+}
@@ -7,14 +7,20 @@ use std::sync::mpsc;
 
 use crate::audio::{self, Recording};
 use crate::config::Settings;
+use crate::hooks;
 use crate::input;
+use crate::meeting;
 use crate::paste::{self, PasteConfig};
-use crate::transcribe::Transcriber;
+use crate::portal;
+use crate::sink::{self, OutputSink};
+use crate::transcribe::{self, Transcriber, Transcription};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ServiceStatus {
     Stopped,
     Starting,
+    /// Fetching a missing model before it can be loaded.
+    Downloading,
     Ready,
     Recording,
     Transcribing,
@@ -26,6 +32,7 @@ impl std::fmt::Display for ServiceStatus {
         match self {
             ServiceStatus::Stopped => write!(f, "stopped"),
             ServiceStatus::Starting => write!(f, "starting"),
+            ServiceStatus::Downloading => write!(f, "downloading"),
             ServiceStatus::Ready => write!(f, "ready"),
             ServiceStatus::Recording => write!(f, "recording"),
             ServiceStatus::Transcribing => write!(f, "transcribing"),
@@ -39,66 +46,793 @@ pub trait ServiceCallbacks: Send {
     fn on_status(&mut self, status: ServiceStatus);
     fn on_status_msg(&mut self, msg: &str);
     fn on_text(&mut self, text: &str);
+    /// Interim text for a recording still in progress (`streaming` mode).
+    /// Superseded by `on_text` when the key is released; never pasted.
+    fn on_partial_text(&mut self, _text: &str) {}
     fn on_error(&mut self, error: &str);
+    /// Input level (0.0-1.0) sampled every `LEVEL_POLL_INTERVAL` while recording.
+    fn on_audio_level(&mut self, _level: f32) {}
+    /// Time since the key was pressed, reported about once a second while
+    /// recording, e.g. for an elapsed-time display.
+    fn on_recording_progress(&mut self, _elapsed_ms: u64) {}
+    /// The selected device doesn't advertise the trigger key, so dictation
+    /// can't start; `message` names keys it does have. Sent after `Ready`.
+    fn on_key_unsupported(&mut self, _message: &str) {}
+    /// Called on every pass of the event loop, at least every
+    /// `IDLE_POLL_INTERVAL` while idle; a sign the loop isn't hung.
+    fn on_tick(&mut self) {}
 }
 
 /// No-op callbacks for daemon mode (just logs).
-struct LogCallbacks;
+struct LogCallbacks {
+    /// Log only the length of transcriptions (`privacy_mode`).
+    privacy: bool,
+    /// Set when running as a `Type=notify` systemd service.
+    systemd: Option<crate::systemd::Notifier>,
+    ready_sent: bool,
+}
+
+impl LogCallbacks {
+    fn new(privacy: bool, systemd: Option<crate::systemd::Notifier>) -> Self {
+        Self {
+            privacy,
+            systemd,
+            ready_sent: false,
+        }
+    }
+
+    fn transcribed_message(&self, text: &str) -> String {
+        format!(
+            "Transcribed: {}",
+            crate::logging::loggable_text(text, self.privacy)
+        )
+    }
+}
 
 impl ServiceCallbacks for LogCallbacks {
     fn on_status(&mut self, status: ServiceStatus) {
         log::info!("Status: {status}");
+        let Some(systemd) = &mut self.systemd else {
+            return;
+        };
+        let state = match status {
+            ServiceStatus::Ready if !self.ready_sent => {
+                self.ready_sent = true;
+                "READY=1"
+            }
+            ServiceStatus::Stopping => "STOPPING=1",
+            _ => {
+                systemd.ping_watchdog();
+                return;
+            }
+        };
+        if let Err(e) = systemd.notify(state) {
+            log::warn!("{e:#}");
+        }
     }
     fn on_status_msg(&mut self, msg: &str) {
         log::info!("{msg}");
+        // Download progress keeps the watchdog fed before the loop starts.
+        if let Some(systemd) = &mut self.systemd {
+            systemd.ping_watchdog();
+        }
     }
     fn on_text(&mut self, text: &str) {
-        log::info!("Transcribed: {text}");
+        log::info!("{}", self.transcribed_message(text));
     }
     fn on_error(&mut self, error: &str) {
         log::error!("Error: {error}");
     }
+    fn on_tick(&mut self) {
+        if let Some(systemd) = &mut self.systemd {
+            systemd.ping_watchdog();
+        }
+    }
+}
+
+/// Whether a press-to-release interval is too short to be intentional dictation.
+fn is_accidental_tap(held: std::time::Duration, min_hold_ms: u32) -> bool {
+    held < std::time::Duration::from_millis(u64::from(min_hold_ms))
+}
+
+/// Accepted `auto_stop_silence_ms` range; shorter pauses cut people off
+/// mid-sentence.
+const AUTO_STOP_RANGE: std::ops::RangeInclusive<u32> = 300..=10_000;
+
+/// Silence after speech that ends a dictation (`auto_stop_silence_ms`), or
+/// None when recordings only stop on release.
+fn auto_stop_from_settings(settings: &Settings) -> Result<Option<std::time::Duration>> {
+    match settings.auto_stop_silence_ms {
+        0 => Ok(None),
+        ms if AUTO_STOP_RANGE.contains(&ms) => {
+            Ok(Some(std::time::Duration::from_millis(u64::from(ms))))
+        }
+        other => anyhow::bail!(
+            "Invalid auto_stop_silence_ms: {other} (expected 0 or {}-{})",
+            AUTO_STOP_RANGE.start(),
+            AUTO_STOP_RANGE.end()
+        ),
+    }
+}
+
+/// Size and length of a recording, read before the file is disposed of
+/// (`log_transcription_detail`).
+struct RecordingInfo {
+    bytes: u64,
+    duration: Result<std::time::Duration>,
+}
+
+impl RecordingInfo {
+    fn read(wav_path: &std::path::Path) -> Self {
+        Self {
+            bytes: std::fs::metadata(wav_path).map(|m| m.len()).unwrap_or(0),
+            duration: audio::wav_duration(wav_path),
+        }
+    }
+}
+
+/// Log recording size/duration and per-segment timing and confidence
+/// (`log_transcription_detail`). Includes the dictated text.
+fn log_transcription_detail(info: &RecordingInfo, transcription: &Transcription, privacy: bool) {
+    match &info.duration {
+        Ok(duration) => log::info!(
+            "Recording: {:.2}s, {} bytes, confidence {:.2}",
+            duration.as_secs_f64(),
+            info.bytes,
+            transcription.confidence
+        ),
+        Err(e) => log::warn!("Could not read recording duration: {e:#}"),
+    }
+    for segment in &transcription.segments {
+        if privacy {
+            log::info!(
+                "Segment [{:.2}s-{:.2}s] p={:.2} {}",
+                segment.start_ms as f64 / 1000.0,
+                segment.end_ms as f64 / 1000.0,
+                segment.confidence,
+                crate::logging::loggable_text(segment.text.trim(), true)
+            );
+        } else {
+            log::info!("Segment {segment}");
+        }
+    }
+}
+
+/// `incremental_paste` state for one dictation: which words have already
+/// been typed. Whisper may revise the end of a partial transcription as more
+/// audio arrives, so a word is only committed once two consecutive partials
+/// agree on it and it is not the last word (which may be cut off mid-word).
+#[derive(Debug, Default)]
+struct IncrementalPaste {
+    committed: Vec<String>,
+    last_partial: Vec<String>,
+    /// Last character typed for this dictation, separator included.
+    last_char: Option<char>,
+}
+
+impl IncrementalPaste {
+    /// Feed a new partial transcription; returns newly stable text to paste.
+    fn on_partial(&mut self, text: &str) -> Option<String> {
+        let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+        let stable = words
+            .iter()
+            .zip(&self.last_partial)
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(words.len().saturating_sub(1));
+        self.last_partial = words;
+        if stable <= self.committed.len()
+            || self.last_partial[..self.committed.len()] != self.committed[..]
+        {
+            return None;
+        }
+        let delta = self.last_partial[self.committed.len()..stable].to_vec();
+        self.committed.extend(delta.iter().cloned());
+        Some(delta.join(" "))
+    }
+
+    /// Whether part of this dictation has already been typed.
+    fn has_pasted(&self) -> bool {
+        !self.committed.is_empty()
+    }
+
+    /// Text of the final transcription not yet pasted. Words Whisper revised
+    /// after they were typed can't be taken back, so this is simply
+    /// everything past the committed word count.
+    fn finish(&mut self, text: &str) -> String {
+        let state = std::mem::take(self);
+        // Nothing typed yet: keep the text as is, newlines included.
+        if state.committed.is_empty() {
+            return text.to_string();
+        }
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.len() < state.committed.len()
+            || words[..state.committed.len()] != state.committed[..]
+        {
+            log::debug!("Final transcription revised already-pasted words");
+        }
+        words
+            .get(state.committed.len()..)
+            .unwrap_or_default()
+            .join(" ")
+    }
+}
+
+/// A transcription waiting for a confirming tap (`confirm_before_paste`).
+struct PendingPaste {
+    text: String,
+    deadline: std::time::Instant,
+}
+
+/// Presses shorter than this confirm a pending paste instead of dictating.
+const CONFIRM_TAP_MAX: std::time::Duration = std::time::Duration::from_millis(400);
+
+/// What a press made during a transcription does (`busy_press`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BusyPress {
+    /// Record right away; the clip is transcribed after the running one.
+    Queue,
+    /// Drop it.
+    Ignore,
+}
+
+impl BusyPress {
+    fn from_settings(settings: &Settings) -> Result<Self> {
+        match settings.busy_press.trim() {
+            "" | "queue" => Ok(Self::Queue),
+            "ignore" => Ok(Self::Ignore),
+            other => anyhow::bail!("Invalid busy_press: {other} (expected queue or ignore)"),
+        }
+    }
+}
+
+/// Largest accepted `queue_depth`.
+const MAX_QUEUE_DEPTH: u32 = 16;
+
+/// How many dictations may wait for or be in transcription (`queue_depth`).
+fn queue_depth_from_settings(settings: &Settings) -> Result<usize> {
+    match settings.queue_depth {
+        depth @ 1..=MAX_QUEUE_DEPTH => Ok(depth as usize),
+        other => anyhow::bail!("Invalid queue_depth: {other} (expected 1-{MAX_QUEUE_DEPTH})"),
+    }
+}
+
+/// Why a press can't start a recording while `transcribing` clips are still
+/// with the worker, or None if it can.
+fn press_refusal(mode: BusyPress, transcribing: usize, depth: usize) -> Option<&'static str> {
+    if transcribing == 0 {
+        None
+    } else if mode == BusyPress::Ignore {
+        Some("Still transcribing - press ignored")
+    } else if transcribing >= depth {
+        Some("Busy - please wait for earlier dictations to finish")
+    } else {
+        None
+    }
+}
+
+/// Status once no recording is running: still transcribing, or ready.
+fn settled_status(transcribing: usize) -> ServiceStatus {
+    if transcribing > 0 {
+        ServiceStatus::Transcribing
+    } else {
+        ServiceStatus::Ready
+    }
+}
+
+/// How often the input level is sampled while a recording is in progress.
+const LEVEL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// How often the audio so far is re-transcribed in `streaming` mode.
+const STREAMING_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How often the key reader thread wakes to check for shutdown, in ms.
+const READER_POLL_MS: u16 = 200;
+
+/// How often the idle loop wakes to check for shutdown.
+const IDLE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How often the loop checks for worker results while a clip is transcribing.
+const RESULT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// How long to wait for the next key event: short while recording (level
+/// updates), and no longer than the time left before the next deadline
+/// (pending append-mode audio or an unconfirmed paste).
+fn poll_timeout(
+    recording: bool,
+    deadline: Option<std::time::Instant>,
+    now: std::time::Instant,
+) -> std::time::Duration {
+    if recording {
+        LEVEL_POLL_INTERVAL
+    } else if let Some(deadline) = deadline {
+        deadline
+            .saturating_duration_since(now)
+            .min(IDLE_POLL_INTERVAL)
+    } else {
+        IDLE_POLL_INTERVAL
+    }
 }
 
 /// Key events sent from the reader thread.
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum KeyEvent {
     Press,
     Release,
+    Cancel,
     Error(String),
 }
 
-pub struct DictationService {
+/// Tracks the held state of each key in the trigger chord.
+/// The chord is "pressed" once every key is held and "released" as soon as any is let go.
+struct ChordState {
+    keys: Vec<evdev::Key>,
+    held: Vec<bool>,
+    active: bool,
+}
+
+impl ChordState {
+    fn new(keys: Vec<evdev::Key>) -> Self {
+        let held = vec![false; keys.len()];
+        Self {
+            keys,
+            held,
+            active: false,
+        }
+    }
+
+    /// Feed a raw key event (value 1 = press, 0 = release, 2 = repeat).
+    /// Returns a chord-level Press/Release when the combined state changes.
+    fn handle(&mut self, key: evdev::Key, value: i32) -> Option<KeyEvent> {
+        let idx = self.keys.iter().position(|k| *k == key)?;
+        match value {
+            1 => self.held[idx] = true,
+            0 => self.held[idx] = false,
+            _ => return None, // repeat, ignore
+        }
+
+        let all_held = self.held.iter().all(|h| *h);
+        if all_held && !self.active {
+            self.active = true;
+            Some(KeyEvent::Press)
+        } else if !all_held && self.active {
+            self.active = false;
+            Some(KeyEvent::Release)
+        } else {
+            None
+        }
+    }
+}
+
+/// Translates raw key events into service events: the trigger chord plus an
+/// optional cancel key, which fires on press only.
+struct KeyWatcher {
+    chord: ChordState,
+    cancel: Option<evdev::Key>,
+}
+
+impl KeyWatcher {
+    fn handle(&mut self, key: evdev::Key, value: i32) -> Option<KeyEvent> {
+        if self.cancel == Some(key) {
+            return (value == 1).then_some(KeyEvent::Cancel);
+        }
+        self.chord.handle(key, value)
+    }
+}
+
+/// Grab change for a chord event when `grab_key` is on: grab on press,
+/// release the grab on release.
+fn grab_transition(event: &KeyEvent) -> Option<bool> {
+    match event {
+        KeyEvent::Press => Some(true),
+        KeyEvent::Release => Some(false),
+        KeyEvent::Cancel | KeyEvent::Error(_) => None,
+    }
+}
+
+/// Resolve the optional cancel key setting. It must not be part of the trigger chord.
+fn resolve_cancel_key(setting: &str, trigger: &[evdev::Key]) -> Result<Option<evdev::Key>> {
+    let setting = setting.trim();
+    if setting.is_empty() {
+        return Ok(None);
+    }
+    let key = input::resolve_key(setting)?;
+    if trigger.contains(&key) {
+        anyhow::bail!("cancel_key {setting} is also part of the trigger key");
+    }
+    Ok(Some(key))
+}
+
+/// Bind the portal shortcuts when `trigger_backend = portal`. Returns None
+/// for evdev, including when the portal can't be used.
+fn pick_portal(backend: &str, with_cancel: bool) -> Result<Option<portal::Shortcuts>> {
+    match backend.trim() {
+        "" | "evdev" => Ok(None),
+        "portal" => match portal::Shortcuts::bind(with_cancel) {
+            Ok(shortcuts) => Ok(Some(shortcuts)),
+            Err(e) => {
+                log::warn!("GlobalShortcuts portal unavailable ({e:#}); falling back to evdev");
+                Ok(None)
+            }
+        },
+        other => anyhow::bail!("Invalid trigger_backend: {other} (expected evdev or portal)"),
+    }
+}
+
+/// Map a portal shortcut event onto the key events evdev would produce.
+fn portal_key_event(event: &portal::ShortcutEvent) -> Option<KeyEvent> {
+    match (event.id.as_str(), event.pressed) {
+        (portal::DICTATE_ID, true) => Some(KeyEvent::Press),
+        (portal::DICTATE_ID, false) => Some(KeyEvent::Release),
+        (portal::CANCEL_ID, true) => Some(KeyEvent::Cancel),
+        _ => None,
+    }
+}
+
+/// Forward portal shortcut signals until the session is closed.
+fn spawn_portal_reader(
+    shortcuts: portal::Shortcuts,
+    key_tx: mpsc::Sender<KeyEvent>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let result = shortcuts.listen(|event| match portal_key_event(&event) {
+            Some(ke) => key_tx.send(ke).is_ok(),
+            None => true,
+        });
+        if let Err(e) = result {
+            let _ = key_tx.send(KeyEvent::Error(format!("Shortcut portal error: {e:#}")));
+        }
+    })
+}
+
+/// The configured model plus the optional `fallback_model`, loaded on first
+/// use. Owned by the transcription worker, which also runs `streaming`
+/// partials on `primary`.
+struct Models {
+    primary: Arc<Transcriber>,
+    fallback_path: Option<PathBuf>,
+    fallback: Option<Transcriber>,
     settings: Settings,
-    device_path: PathBuf,
-    key: evdev::Key,
+    decode: transcribe::DecodeOptions,
+}
+
+impl Models {
+    /// Transcribe with the primary model, re-running low-confidence results on
+    /// the fallback model and keeping whichever is more confident.
+    fn transcribe(
+        &mut self,
+        samples: &[i16],
+        on_status: &mut dyn FnMut(&str),
+    ) -> Result<Transcription> {
+        let first = self.primary.transcribe_samples_scored(samples)?;
+        let Some(fallback_path) = &self.fallback_path else {
+            return Ok(first);
+        };
+        if !first.is_low_confidence() {
+            return Ok(first);
+        }
+
+        log::info!(
+            "Low confidence ({:.2}); retrying with {}",
+            first.confidence,
+            self.settings.fallback_model
+        );
+        on_status("Low confidence - retrying with larger model...");
+        if self.fallback.is_none() {
+            match Transcriber::new(
+                fallback_path,
+                &self.settings.language,
+                self.settings.use_gpu,
+            ) {
+                Ok(t) => {
+                    self.fallback = Some(
+                        t.with_threads(self.settings.n_threads)
+                            .with_numbers_as_digits(self.settings.numbers_as_digits)
+                            .with_decode_options(self.decode)
+                            .with_sticky_language(self.settings.auto_language_sticky),
+                    )
+                }
+                Err(e) => {
+                    log::warn!("Failed to load fallback model: {e:#}");
+                    return Ok(first);
+                }
+            }
+        }
+        let Some(fallback) = &self.fallback else {
+            return Ok(first);
+        };
+        match fallback.transcribe_samples_scored(samples) {
+            Ok(second) => {
+                log::info!("Fallback confidence: {:.2}", second.confidence);
+                Ok(transcribe::more_confident(first, second))
+            }
+            Err(e) => {
+                log::warn!("Fallback transcription failed: {e:#}");
+                Ok(first)
+            }
+        }
+    }
+}
+
+/// Audio (16kHz mono samples) for the transcription worker.
+enum Job {
+    /// A finished dictation.
+    Clip(Vec<i16>),
+    /// The audio so far of recording number `recording`, for a `streaming`
+    /// or `incremental_paste` partial.
+    Partial { recording: u64, samples: Vec<i16> },
+}
+
+/// Messages from the transcription worker.
+enum WorkerEvent {
+    /// Progress while a clip is transcribed, e.g. a fallback-model retry.
+    Status(String),
+    /// A clip finished. Results arrive in the order clips were sent.
+    Done(Result<Transcription>),
+    /// A partial transcription of recording number `recording`.
+    Partial {
+        recording: u64,
+        result: Result<String>,
+    },
+}
+
+/// Transcribe jobs one at a time, in order, on their own thread, so the
+/// event loop keeps handling keys and shutdown while Whisper runs. Clips go
+/// through `transcribe`, partials through `partial`. Dropping the sender
+/// stops the worker after its current job.
+fn spawn_transcribe_worker<F, P>(
+    mut transcribe: F,
+    mut partial: P,
+) -> (mpsc::Sender<Job>, mpsc::Receiver<WorkerEvent>)
+where
+    F: FnMut(&[i16], &mut dyn FnMut(&str)) -> Result<Transcription> + Send + 'static,
+    P: FnMut(&[i16]) -> Result<String> + Send + 'static,
+{
+    let (job_tx, job_rx) = mpsc::channel::<Job>();
+    let (event_tx, event_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for job in job_rx {
+            let event = match job {
+                Job::Clip(samples) => WorkerEvent::Done(transcribe(&samples, &mut |msg| {
+                    let _ = event_tx.send(WorkerEvent::Status(msg.to_string()));
+                })),
+                Job::Partial { recording, samples } => WorkerEvent::Partial {
+                    recording,
+                    result: partial(&samples),
+                },
+            };
+            if event_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    (job_tx, event_rx)
+}
+
+/// A clip handed to the worker, waiting for its result.
+struct InFlight {
+    /// Words of this dictation already typed by `incremental_paste`.
+    pasted: IncrementalPaste,
+    /// Set with `log_transcription_detail`.
+    info: Option<RecordingInfo>,
+}
+
+/// Where finished text goes: `text_filter`, then `post_hook`, then the
+/// output sink. Shared by dictations and the GUI's "Paste last".
+pub struct Output {
     paste_config: PasteConfig,
+    sink: OutputSink,
+    post_hook: Option<hooks::PostHook>,
+    text_filter: transcribe::TextFilter,
+    privacy: bool,
+}
+
+impl Output {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        Ok(Self {
+            paste_config: PasteConfig::from_settings(settings)?,
+            sink: OutputSink::from_settings(settings)?,
+            post_hook: hooks::PostHook::from_settings(settings),
+            text_filter: transcribe::TextFilter::from_settings(settings),
+            privacy: crate::logging::privacy_mode(settings),
+        })
+    }
+
+    /// Whether `text_filter` drops `text`; logged when it does.
+    fn rejects(&self, text: &str) -> bool {
+        if !self.text_filter.rejects(text) {
+            return false;
+        }
+        log::info!(
+            "Discarding transcription: {}",
+            crate::logging::loggable_text(text, self.privacy)
+        );
+        true
+    }
+
+    /// Filter, rewrite and deliver `text` as one whole dictation, e.g. an
+    /// earlier transcription pasted again.
+    pub fn deliver(&self, text: &str, callbacks: &mut dyn ServiceCallbacks) {
+        if self.rejects(text) {
+            callbacks.on_status_msg("Nothing meaningful transcribed");
+            return;
+        }
+        let text = self.run_post_hook(text.to_string(), callbacks);
+        if !text.is_empty() {
+            self.paste(&text, callbacks);
+        }
+    }
+
+    /// Rewrite the transcription with `post_hook`, keeping it unchanged if
+    /// the hook fails or times out.
+    fn run_post_hook(&self, text: String, callbacks: &mut dyn ServiceCallbacks) -> String {
+        let Some(hook) = &self.post_hook else {
+            return text;
+        };
+        match hook.apply(&text) {
+            Ok(output) => output,
+            Err(e) => {
+                log::warn!("post_hook failed, pasting the original text: {e:#}");
+                callbacks.on_status_msg("Post hook failed - pasted the original text");
+                text
+            }
+        }
+    }
+
+    /// Deliver text to the output sink: paste it, or append it to the notes file.
+    fn paste(&self, text: &str, callbacks: &mut dyn ServiceCallbacks) {
+        if let OutputSink::File(path) = &self.sink {
+            if let Err(e) = sink::append_note(path, text) {
+                callbacks.on_error(&format!("Saving note failed: {e:#}"));
+            }
+            return;
+        }
+        match paste::paste_text(text, &self.paste_config) {
+            Ok(plan) if plan.unfocused => {
+                callbacks.on_status_msg("No focused window - text copied to clipboard instead");
+            }
+            Ok(plan) if plan.ydotoold == Some(paste::YdotooldRecovery::Restarted) => {
+                callbacks.on_status_msg("Paste service (ydotoold) restarted");
+            }
+            Ok(plan) if plan.ydotoold == Some(paste::YdotooldRecovery::CopiedInstead) => {
+                callbacks.on_status_msg(
+                    "Paste service (ydotoold) stopped - text copied to clipboard instead",
+                );
+            }
+            Ok(_) => {}
+            Err(e) => callbacks.on_error(&format!("Paste failed: {e}")),
+        }
+    }
+}
+
+/// Where trigger presses come from (`trigger_backend`).
+enum TriggerSource {
+    Device(PathBuf),
+    Portal(portal::Shortcuts),
+}
+
+pub struct DictationService {
+    settings: Settings,
+    trigger: TriggerSource,
+    keys: Vec<evdev::Key>,
+    cancel_key: Option<evdev::Key>,
+    recording_dir: PathBuf,
+    /// Set when transcribed recordings are kept (`keep_recordings`).
+    archive: Option<audio::RecordingArchive>,
+    capture_timing: audio::CaptureTiming,
+    recording_format: audio::RecordingFormat,
+    decode: transcribe::DecodeOptions,
+    busy_press: BusyPress,
+    queue_depth: usize,
+    auto_stop: Option<std::time::Duration>,
+    record_hooks: Option<hooks::RecordHooks>,
+    /// Set when the device can't deliver the trigger key (`check_key_support`).
+    key_warning: Option<String>,
+    /// Grab the device while the trigger is held (`grab_key`, if safe).
+    grab_key: bool,
+    output: Output,
+    privacy: bool,
+    /// Starts the capture process: `Recording::start`, or a stand-in in tests.
+    start_recorder:
+        fn(&std::path::Path, &audio::CaptureTiming, &audio::RecordingFormat) -> Result<Recording>,
     shutdown: Arc<AtomicBool>,
 }
 
 impl DictationService {
     pub fn new(settings: Settings) -> Result<Self> {
-        let key = input::resolve_key(&settings.key)?;
-        let device_path = input::pick_keyboard_device(&settings.keyboard_device, key)?;
-        let paste_method = paste::pick_paste_method(&settings.paste_method)?;
-
-        let paste_config = PasteConfig {
-            method: paste_method,
-            hotkey: settings.paste_hotkey.clone(),
-            clipboard_paste: settings.clipboard_paste.clone(),
-            clipboard_paste_delay_ms: settings.clipboard_paste_delay_ms,
+        let keys = input::resolve_key_chord(&settings.key)?;
+        // For chords, pick the device by the last (non-modifier) component.
+        let primary = *keys.last().context("No trigger key configured")?;
+        let cancel_key = resolve_cancel_key(&settings.cancel_key, &keys)?;
+        let trigger = match pick_portal(&settings.trigger_backend, cancel_key.is_some())? {
+            Some(shortcuts) => TriggerSource::Portal(shortcuts),
+            None => TriggerSource::Device(input::pick_keyboard_device(
+                &settings.keyboard_device,
+                primary,
+            )?),
+        };
+        let key_warning = match &trigger {
+            TriggerSource::Device(path) => input::check_key_support(path, primary),
+            TriggerSource::Portal(_) => None,
         };
+        let grab_key = settings.grab_key && input::grab_is_safe(&keys);
+        if settings.grab_key && !grab_key {
+            log::warn!(
+                "grab_key ignored: {} includes a typing key, and grabbing would block typing",
+                settings.key
+            );
+        }
+        transcribe::set_model_aliases(&settings.model_aliases)?;
+        let output = Output::from_settings(&settings)?;
+        let recording_dir = audio::recording_dir(&settings.recording_dir);
+        let archive = audio::RecordingArchive::from_settings(&settings)?;
+        let capture_timing = audio::CaptureTiming::from_settings(&settings)?;
+        let recording_format = audio::RecordingFormat::from_settings(&settings)?;
+        if !recording_format.is_native() && archive.is_none() {
+            log::warn!(
+                "recording_channels/recording_rate only matter with keep_recordings; \
+                 audio is converted to 16kHz mono for Whisper anyway"
+            );
+        }
+        let decode = transcribe::DecodeOptions::from_settings(&settings)?;
+        let busy_press = BusyPress::from_settings(&settings)?;
+        let queue_depth = queue_depth_from_settings(&settings)?;
+        let auto_stop = auto_stop_from_settings(&settings)?;
+        let record_hooks = hooks::RecordHooks::from_settings(&settings);
+        let privacy = crate::logging::privacy_mode(&settings);
 
-        log::info!("Key: {} ({:?})", settings.key, key);
-        log::info!("Device: {}", device_path.display());
-        log::info!("Paste method: {paste_method}");
+        log::info!("Key: {} ({:?})", settings.key, keys);
+        if let Some(cancel) = cancel_key {
+            log::info!("Cancel key: {cancel:?}");
+        }
+        match &trigger {
+            TriggerSource::Device(path) => log::info!("Device: {}", path.display()),
+            TriggerSource::Portal(shortcuts) => log::info!(
+                "Trigger: desktop shortcut ({})",
+                shortcuts
+                    .trigger_description
+                    .as_deref()
+                    .unwrap_or("unassigned")
+            ),
+        }
+        if let Some(warning) = &key_warning {
+            log::warn!("{warning}");
+        }
+        match &output.sink {
+            OutputSink::Paste => log::info!("Paste method: {}", output.paste_config.method),
+            OutputSink::File(path) => log::info!("Appending transcriptions to {}", path.display()),
+        }
         log::info!("Model: {}", settings.model);
+        if privacy {
+            log::info!("Privacy mode: transcriptions are not logged or kept in history");
+        }
+        if let Some(archive) = &archive {
+            log::info!(
+                "Keeping the last {} recordings in {}",
+                archive.max,
+                archive.dir.display()
+            );
+        }
 
         Ok(Self {
             settings,
-            device_path,
-            key,
-            paste_config,
+            trigger,
+            keys,
+            cancel_key,
+            recording_dir,
+            archive,
+            capture_timing,
+            recording_format,
+            decode,
+            busy_press,
+            queue_depth,
+            auto_stop,
+            record_hooks,
+            key_warning,
+            grab_key,
+            output,
+            privacy,
+            start_recorder: Recording::start,
             shutdown: Arc::new(AtomicBool::new(false)),
         })
     }
@@ -108,37 +842,55 @@ impl DictationService {
         self.shutdown.clone()
     }
 
+    /// Whether transcriptions must stay out of logs and history.
+    pub fn privacy_mode(&self) -> bool {
+        self.privacy
+    }
+
+    /// Human-readable label for the trigger key, e.g. "Right Ctrl".
+    pub fn trigger_key_label(&self) -> String {
+        match &self.trigger {
+            TriggerSource::Portal(shortcuts) => shortcuts
+                .trigger_description
+                .clone()
+                .unwrap_or_else(|| "Desktop shortcut".into()),
+            TriggerSource::Device(_) => input::key_label(&self.settings.key),
+        }
+    }
+
+    /// The paste method in use after resolving "auto".
+    pub fn paste_method_label(&self) -> String {
+        self.output.paste_config.method.to_string()
+    }
+
     /// Human-readable label for the active input device.
     pub fn device_label(&self) -> String {
+        let device_path = match &self.trigger {
+            TriggerSource::Device(path) => path,
+            TriggerSource::Portal(_) => return "Desktop shortcut (GlobalShortcuts portal)".into(),
+        };
         // Include the device name if we can open it
-        if let Ok(dev) = evdev::Device::open(&self.device_path) {
+        if let Ok(dev) = evdev::Device::open(device_path) {
             let name = dev.name().unwrap_or("Unknown");
-            format!("{} - {}", self.device_path.display(), name)
+            format!("{} - {}", device_path.display(), name)
         } else {
-            self.device_path.display().to_string()
+            device_path.display().to_string()
         }
     }
 
-    /// Run the main event loop.
-    pub fn run_loop(&self, callbacks: &mut dyn ServiceCallbacks) -> Result<()> {
-        callbacks.on_status(ServiceStatus::Starting);
-
-        // Download model if missing
-        let model_path =
-            crate::transcribe::ensure_model_with_status(&self.settings.model, &mut |status| {
-                callbacks.on_status_msg(status)
-            })?;
-
-        callbacks.on_status_msg("Loading model...");
-        let transcriber = Transcriber::new(&model_path, &self.settings.language)
-            .context("Failed to load Whisper model")?;
-
-        // Spawn a dedicated thread to read evdev events.
-        // This avoids issues with poll + fetch_events interaction.
-        let (key_tx, key_rx) = mpsc::channel();
-        let device_path = self.device_path.clone();
-        let target_key = self.key;
+    /// Spawn a dedicated thread to read evdev events.
+    /// This avoids issues with poll + fetch_events interaction.
+    fn spawn_device_reader(
+        &self,
+        device_path: PathBuf,
+        key_tx: mpsc::Sender<KeyEvent>,
+    ) -> std::thread::JoinHandle<()> {
+        let mut watcher = KeyWatcher {
+            chord: ChordState::new(self.keys.clone()),
+            cancel: self.cancel_key,
+        };
         let shutdown_reader = self.shutdown.clone();
+        let grab_key = self.grab_key;
 
         std::thread::spawn(move || {
             let mut device = match evdev::Device::open(&device_path) {
@@ -159,22 +911,29 @@ impl DictationService {
             );
 
             while !shutdown_reader.load(Ordering::Relaxed) {
-                // fetch_events blocks until events are available
+                // Wait in slices so shutdown is noticed without a key press;
+                // fetch_events would block until the next event.
+                match input::wait_for_events(&device, READER_POLL_MS) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        let _ = key_tx.send(KeyEvent::Error(format!("{e:#}")));
+                        return;
+                    }
+                }
+                let mut grab = None;
                 match device.fetch_events() {
                     Ok(events) => {
                         for event in events {
                             if event.event_type() != EventType::KEY {
                                 continue;
                             }
-                            if let InputEventKind::Key(key) = event.kind() {
-                                if key != target_key {
-                                    continue;
+                            if let InputEventKind::Key(key) = event.kind()
+                                && let Some(ke) = watcher.handle(key, event.value())
+                            {
+                                if grab_key {
+                                    grab = grab_transition(&ke).or(grab);
                                 }
-                                let ke = match event.value() {
-                                    1 => KeyEvent::Press,
-                                    0 => KeyEvent::Release,
-                                    _ => continue, // repeat, ignore
-                                };
                                 if key_tx.send(ke).is_err() {
                                     return; // main thread gone
                                 }
@@ -189,105 +948,703 @@ impl DictationService {
                         return;
                     }
                 }
+                // The grab ends when `device` is dropped, so an early return
+                // can't leave the keyboard captured.
+                let result = match grab {
+                    Some(true) => device.grab(),
+                    Some(false) => device.ungrab(),
+                    None => Ok(()),
+                };
+                if let Err(e) = result {
+                    log::warn!("Failed to change device grab: {e}");
+                }
             }
-        });
+        })
+    }
+
+    /// Download the model and fallback model if missing, returning their paths.
+    fn ensure_models(
+        &self,
+        download: &transcribe::DownloadOptions,
+        callbacks: &mut dyn ServiceCallbacks,
+    ) -> Result<(PathBuf, Option<PathBuf>)> {
+        let model_path =
+            transcribe::ensure_model_with_status(&self.settings.model, download, &mut |status| {
+                callbacks.on_status_msg(status)
+            })?;
+        let fallback_path = if self.settings.fallback_model.is_empty() {
+            None
+        } else {
+            Some(transcribe::ensure_model_with_status(
+                &self.settings.fallback_model,
+                download,
+                &mut |status| callbacks.on_status_msg(status),
+            )?)
+        };
+        Ok((model_path, fallback_path))
+    }
+
+    /// Run the main event loop.
+    pub fn run_loop(&self, callbacks: &mut dyn ServiceCallbacks) -> Result<()> {
+        callbacks.on_status(ServiceStatus::Starting);
+
+        // Download model if missing
+        let download = transcribe::DownloadOptions::from_settings(&self.settings)
+            .with_cancel(self.shutdown.clone());
+        let downloading = !download.offline
+            && [&self.settings.model, &self.settings.fallback_model]
+                .iter()
+                .any(|model| transcribe::needs_download(model));
+        if downloading {
+            callbacks.on_status(ServiceStatus::Downloading);
+        }
+        let models = self.ensure_models(&download, callbacks);
+        let (model_path, fallback_path) = match models {
+            Ok(paths) => paths,
+            // Shutting down cancelled the download: stop cleanly.
+            Err(e) if self.shutdown.load(Ordering::Relaxed) => {
+                log::info!("{e:#}");
+                callbacks.on_status(ServiceStatus::Stopped);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        for model in [&self.settings.model, &self.settings.fallback_model] {
+            if let Some(warning) = transcribe::language_mismatch(model, &self.settings.language) {
+                callbacks.on_error(&warning);
+            }
+        }
+
+        if downloading {
+            callbacks.on_status(ServiceStatus::Starting);
+        }
+        callbacks.on_status_msg("Loading model...");
+        let primary = Arc::new(
+            Transcriber::new(&model_path, &self.settings.language, self.settings.use_gpu)
+                .context("Failed to load Whisper model")?
+                .with_threads(self.settings.n_threads)
+                .with_numbers_as_digits(self.settings.numbers_as_digits)
+                .with_decode_options(self.decode)
+                .with_sticky_language(self.settings.auto_language_sticky),
+        );
+        let mut models = Models {
+            primary: primary.clone(),
+            fallback_path,
+            fallback: None,
+            settings: self.settings.clone(),
+            decode: self.decode,
+        };
+        let (jobs, results) = spawn_transcribe_worker(
+            move |samples, on_status| models.transcribe(samples, on_status),
+            move |samples| primary.transcribe_samples(samples),
+        );
+
+        let (key_tx, key_rx) = mpsc::channel();
+        let reader = match &self.trigger {
+            TriggerSource::Device(path) => self.spawn_device_reader(path.clone(), key_tx),
+            TriggerSource::Portal(shortcuts) => spawn_portal_reader(shortcuts.clone(), key_tx),
+        };
 
         callbacks.on_status(ServiceStatus::Ready);
-        log::info!("Ready. Hold {:?} to dictate.", self.key);
+        log::info!("Ready. Hold {} to dictate.", self.trigger_key_label());
+        if let Some(warning) = &self.key_warning {
+            callbacks.on_key_unsupported(warning);
+        }
+
+        self.event_loop(callbacks, &key_rx, &jobs, &results);
+        // Don't wait for Whisper: the worker stops after its current clip.
+        drop(jobs);
+
+        // Release the device (and any grab) before a restarted service
+        // opens it again.
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let TriggerSource::Portal(shortcuts) = &self.trigger {
+            shortcuts.close();
+        }
+        if reader.join().is_err() {
+            log::warn!("Key reader thread panicked");
+        }
 
+        callbacks.on_status(ServiceStatus::Stopped);
+        Ok(())
+    }
+
+    /// Handle key events and worker results until shutdown or a reader or
+    /// worker failure: recording, transcription hand-off, append mode,
+    /// confirmation and cancelling.
+    fn event_loop(
+        &self,
+        callbacks: &mut dyn ServiceCallbacks,
+        key_rx: &mpsc::Receiver<KeyEvent>,
+        jobs: &mpsc::Sender<Job>,
+        results: &mpsc::Receiver<WorkerEvent>,
+    ) {
+        let mut in_flight: std::collections::VecDeque<InFlight> = Default::default();
         let mut recording: Option<Recording> = None;
+        let mut pressed_at = std::time::Instant::now();
+        let mut last_partial = pressed_at;
+        // Numbers recordings so a partial is only applied to its own.
+        let mut recording_id: u64 = 0;
+        let mut partial_queued = false;
+        let mut progress_secs = 0;
+        // Append mode: audio from earlier presses waiting to be transcribed together.
+        let mut pending_audio: Vec<i16> = Vec::new();
+        let mut append_deadline: Option<std::time::Instant> = None;
+        let append_window =
+            std::time::Duration::from_millis(u64::from(self.settings.append_window_ms));
+        // Incremental paste types words as they are recognized, which can't
+        // be reconciled with append mode joining presses after the fact.
+        let incremental = self.settings.incremental_paste
+            && append_window.is_zero()
+            && !self.settings.confirm_before_paste
+            && self.output.paste_config.auto_paste
+            && self.output.sink.is_paste()
+            && self.output.post_hook.is_none();
+        if self.settings.incremental_paste && !incremental {
+            log::warn!(
+                "incremental_paste is ignored while append_window_ms, confirm_before_paste, \
+                 auto_paste = false, output_sink = file or post_hook is set"
+            );
+        }
+        let mut pasted = IncrementalPaste::default();
+        let mut pending_paste: Option<PendingPaste> = None;
+        // Auto-stop: a tap started the recording, so no release will end it.
+        let mut hands_free = false;
+        let mut end_of_speech: Option<meeting::EndOfSpeech> = None;
+        let mut vad_reader = meeting::StreamReader::new(self.recording_format);
+        let mut speech_ended = false;
+        // `release_padding_ms`: the key is up, but capture goes on until then.
+        let mut release_deadline: Option<std::time::Instant> = None;
 
         loop {
-            // Wait for key events with timeout so we can check shutdown
-            match key_rx.recv_timeout(std::time::Duration::from_millis(500)) {
+            callbacks.on_tick();
+            // Wait for key events with timeout so we can check shutdown,
+            // waking more often while recording to report the input level.
+            let next_deadline = append_deadline
+                .into_iter()
+                .chain(pending_paste.as_ref().map(|p| p.deadline))
+                .min();
+            let mut timeout = poll_timeout(
+                recording.is_some(),
+                next_deadline,
+                std::time::Instant::now(),
+            );
+            if !in_flight.is_empty() || partial_queued {
+                timeout = timeout.min(RESULT_POLL_INTERVAL);
+            }
+            if let Some(deadline) = release_deadline {
+                timeout =
+                    timeout.min(deadline.saturating_duration_since(std::time::Instant::now()));
+            }
+            // A detected pause ends the recording as a release would, and so
+            // does the end of the release padding, without padding again.
+            let padded = release_deadline.is_some_and(|d| std::time::Instant::now() >= d);
+            if padded {
+                release_deadline = None;
+            }
+            let auto_stopped = std::mem::take(&mut speech_ended);
+            let event = if auto_stopped || padded {
+                Ok(KeyEvent::Release)
+            } else {
+                key_rx.recv_timeout(timeout)
+            };
+            match event {
                 Ok(KeyEvent::Press) => {
                     if recording.is_some() {
+                        // Pressed again during the release padding: still
+                        // the same dictation.
+                        release_deadline = None;
+                        continue;
+                    }
+                    if let Some(refusal) =
+                        press_refusal(self.busy_press, in_flight.len(), self.queue_depth)
+                    {
+                        callbacks.on_status_msg(refusal);
                         continue;
                     }
+                    pressed_at = std::time::Instant::now();
+                    last_partial = pressed_at;
+                    recording_id += 1;
+                    progress_secs = 0;
+                    pasted = IncrementalPaste::default();
                     callbacks.on_status(ServiceStatus::Recording);
-                    match audio::temp_wav_path() {
-                        Ok(wav_path) => match Recording::start(&wav_path) {
+                    match audio::temp_wav_path(&self.recording_dir) {
+                        Ok(wav_path) => match self.start_recording(&wav_path) {
                             Ok(rec) => {
                                 log::info!("Recording started");
                                 recording = Some(rec);
+                                hands_free = false;
+                                end_of_speech = self.auto_stop.map(meeting::EndOfSpeech::new);
+                                vad_reader = meeting::StreamReader::new(self.recording_format);
                             }
                             Err(e) => {
                                 callbacks.on_error(&format!("Failed to start recording: {e}"));
-                                callbacks.on_status(ServiceStatus::Ready);
+                                callbacks.on_status(settled_status(in_flight.len()));
                             }
                         },
                         Err(e) => {
                             callbacks.on_error(&format!("Failed to create temp file: {e}"));
-                            callbacks.on_status(ServiceStatus::Ready);
+                            callbacks.on_status(settled_status(in_flight.len()));
                         }
                     }
                 }
                 Ok(KeyEvent::Release) => {
                     if let Some(rec) = recording.take() {
-                        callbacks.on_status(ServiceStatus::Transcribing);
-                        match rec.stop() {
-                            Ok(wav_path) => {
-                                match transcriber.transcribe(&wav_path) {
-                                    Ok(text) => {
-                                        if !text.is_empty() {
-                                            callbacks.on_text(&text);
-                                            if let Err(e) =
-                                                paste::paste_text(&text, &self.paste_config)
-                                            {
-                                                callbacks.on_error(&format!("Paste failed: {e}"));
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        callbacks.on_error(&format!("Transcription failed: {e}"));
+                        if let Some(pending) = pending_paste.take() {
+                            if pressed_at.elapsed() < CONFIRM_TAP_MAX {
+                                if let Ok(path) = self.stop_recording(rec) {
+                                    audio::cleanup_recording(&path);
+                                }
+                                callbacks.on_status(settled_status(in_flight.len()));
+                                self.output.paste(&pending.text, callbacks);
+                                continue;
+                            }
+                            log::info!("Unconfirmed transcription replaced by a new dictation");
+                        }
+                        if self.auto_stop.is_some()
+                            && !auto_stopped
+                            && !padded
+                            && !hands_free
+                            && pressed_at.elapsed() < CONFIRM_TAP_MAX
+                        {
+                            // Keep listening until the speaker pauses or taps again.
+                            hands_free = true;
+                            recording = Some(rec);
+                            callbacks.on_status_msg("Listening - stops when you pause");
+                            continue;
+                        }
+                        if is_accidental_tap(pressed_at.elapsed(), self.settings.min_hold_ms) {
+                            log::info!("Key released after {:?}; discarding", pressed_at.elapsed());
+                            if let Ok(path) = self.stop_recording(rec) {
+                                audio::cleanup_recording(&path);
+                            }
+                            callbacks.on_status(settled_status(in_flight.len()));
+                            callbacks.on_status_msg("Too short - recording discarded");
+                            continue;
+                        }
+                        if self.settings.release_padding_ms > 0 && !auto_stopped && !padded {
+                            // Keep capturing briefly so the last word isn't clipped.
+                            release_deadline = Some(
+                                std::time::Instant::now()
+                                    + std::time::Duration::from_millis(u64::from(
+                                        self.settings.release_padding_ms,
+                                    )),
+                            );
+                            recording = Some(rec);
+                            continue;
+                        }
+                        if append_window.is_zero() {
+                            callbacks.on_status(ServiceStatus::Transcribing);
+                            match self.stop_recording(rec) {
+                                Ok(wav_path) => {
+                                    if let Err(e) = self.queue_transcription(
+                                        &wav_path,
+                                        std::mem::take(&mut pasted),
+                                        jobs,
+                                        &mut in_flight,
+                                    ) {
+                                        callbacks.on_error(&format!("Transcription failed: {e:#}"));
                                     }
                                 }
-                                audio::cleanup_recording(&wav_path);
-                            }
-                            Err(e) => {
-                                callbacks.on_error(&format!("Failed to stop recording: {e}"));
+                                Err(e) => {
+                                    callbacks.on_error(&format!("Failed to stop recording: {e}"));
+                                }
+                            }
+                            callbacks.on_status(settled_status(in_flight.len()));
+                        } else {
+                            // Hold the audio; another press within the window continues it.
+                            match self.stop_recording(rec).and_then(|path| {
+                                let samples = audio::read_wav_samples(&path);
+                                audio::cleanup_recording(&path);
+                                samples
+                            }) {
+                                Ok(samples) => pending_audio.extend(samples),
+                                Err(e) => {
+                                    callbacks.on_error(&format!("Failed to stop recording: {e}"));
+                                }
+                            }
+                            append_deadline = Some(std::time::Instant::now() + append_window);
+                            callbacks.on_status(settled_status(in_flight.len()));
+                            callbacks.on_status_msg("Hold again to keep dictating");
+                        }
+                    }
+                }
+                Ok(KeyEvent::Cancel) => {
+                    // Only meaningful while recording or holding append-mode
+                    // audio; once transcription has started it is too late
+                    // and the event is ignored.
+                    if let Some(rec) = recording.take() {
+                        log::info!("Recording cancelled");
+                        release_deadline = None;
+                        if let Ok(path) = self.stop_recording(rec) {
+                            audio::cleanup_recording(&path);
+                        }
+                    } else if pending_paste.take().is_some() {
+                        log::info!("Pending paste discarded");
+                    } else if append_deadline.is_none() {
+                        continue;
+                    }
+                    pending_audio.clear();
+                    append_deadline = None;
+                    callbacks.on_status(settled_status(in_flight.len()));
+                    callbacks.on_status_msg("Cancelled");
+                }
+                Ok(KeyEvent::Error(e)) => {
+                    callbacks.on_error(&e);
+                    break;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if let Some(rec) = &recording {
+                        callbacks.on_audio_level(rec.current_level());
+                        if let Some(end) = &mut end_of_speech {
+                            match vad_reader.read(rec.path()) {
+                                Ok(samples) if end.push(&samples) => {
+                                    log::info!("Pause detected; stopping recording");
+                                    speech_ended = true;
+                                }
+                                Ok(_) => {}
+                                Err(e) => log::warn!("Silence detection failed: {e:#}"),
+                            }
+                        }
+                        let elapsed = pressed_at.elapsed();
+                        if elapsed.as_secs() > progress_secs {
+                            progress_secs = elapsed.as_secs();
+                            callbacks.on_recording_progress(
+                                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+                            );
+                        }
+                        // One partial at a time, so a slow model isn't handed
+                        // a backlog of ever-longer clips.
+                        if (self.settings.streaming || incremental)
+                            && !partial_queued
+                            && last_partial.elapsed() >= STREAMING_INTERVAL
+                        {
+                            last_partial = std::time::Instant::now();
+                            match rec.samples_so_far() {
+                                Ok(samples) => {
+                                    partial_queued = jobs
+                                        .send(Job::Partial {
+                                            recording: recording_id,
+                                            samples,
+                                        })
+                                        .is_ok();
+                                }
+                                Err(e) => log::warn!("Partial transcription failed: {e}"),
                             }
                         }
-                        callbacks.on_status(ServiceStatus::Ready);
                     }
                 }
-                Ok(KeyEvent::Error(e)) => {
-                    callbacks.on_error(&e);
-                    break;
-                }
-                Err(mpsc::RecvTimeoutError::Timeout) => {}
                 Err(mpsc::RecvTimeoutError::Disconnected) => {
                     callbacks.on_error("Event reader thread exited");
                     break;
                 }
             }
 
+            if recording.is_none()
+                && let Some(deadline) = append_deadline
+                && std::time::Instant::now() >= deadline
+            {
+                append_deadline = None;
+                let samples = std::mem::take(&mut pending_audio);
+                self.transcribe_pending(&samples, callbacks, jobs, &mut in_flight);
+            }
+
+            let mut worker_exited = false;
+            loop {
+                match results.try_recv() {
+                    Ok(WorkerEvent::Status(msg)) => callbacks.on_status_msg(&msg),
+                    Ok(WorkerEvent::Partial {
+                        recording: id,
+                        result,
+                    }) => {
+                        partial_queued = false;
+                        // A partial finishing after its key was released is stale.
+                        if recording.is_none() || id != recording_id {
+                            continue;
+                        }
+                        match result {
+                            Ok(text) if !text.is_empty() => {
+                                callbacks.on_partial_text(&text);
+                                // Typing now would land ahead of the text of
+                                // dictations still being transcribed.
+                                if incremental
+                                    && in_flight.is_empty()
+                                    && let Some(delta) = pasted.on_partial(&text)
+                                {
+                                    pasted.last_char = self.paste_continuation(
+                                        &delta,
+                                        pasted.last_char,
+                                        callbacks,
+                                    );
+                                }
+                            }
+                            Ok(_) => {}
+                            Err(e) => log::warn!("Partial transcription failed: {e}"),
+                        }
+                    }
+                    Ok(WorkerEvent::Done(result)) => {
+                        let Some(job) = in_flight.pop_front() else {
+                            continue;
+                        };
+                        self.deliver(result, job, callbacks, &mut pending_paste);
+                        if recording.is_none() && in_flight.is_empty() {
+                            callbacks.on_status(ServiceStatus::Ready);
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        worker_exited = true;
+                        break;
+                    }
+                }
+            }
+            if worker_exited {
+                callbacks.on_error("Transcription worker exited");
+                break;
+            }
+
+            if recording.is_none()
+                && pending_paste
+                    .as_ref()
+                    .is_some_and(|p| std::time::Instant::now() >= p.deadline)
+            {
+                pending_paste = None;
+                callbacks.on_status_msg("Not pasted - confirmation timed out");
+            }
+
             if self.shutdown.load(Ordering::Relaxed) {
                 callbacks.on_status(ServiceStatus::Stopping);
                 break;
             }
         }
 
+        if !pending_audio.is_empty() {
+            log::info!("Discarding untranscribed append-mode audio on shutdown");
+        }
+        if !in_flight.is_empty() {
+            log::info!(
+                "Discarding {} unfinished transcription(s) on shutdown",
+                in_flight.len()
+            );
+        }
+
         // Cleanup any in-progress recording
         if let Some(rec) = recording
-            && let Ok(path) = rec.stop()
+            && let Ok(path) = self.stop_recording(rec)
         {
             audio::cleanup_recording(&path);
         }
+    }
 
-        callbacks.on_status(ServiceStatus::Stopped);
+    /// Dispose of a transcribed recording: move it into the archive when
+    /// `keep_recordings` is on, otherwise delete it.
+    fn finish_recording(&self, wav_path: &std::path::Path) {
+        let Some(archive) = &self.archive else {
+            audio::cleanup_recording(wav_path);
+            return;
+        };
+        match archive.keep(wav_path) {
+            Ok(kept) => log::info!("Recording kept: {}", kept.display()),
+            Err(e) => {
+                log::warn!("Failed to keep recording: {e:#}");
+                audio::cleanup_recording(wav_path);
+            }
+        }
+    }
+
+    /// Read a finished recording, dispose of the file and hand the audio to
+    /// the transcription worker.
+    fn queue_transcription(
+        &self,
+        wav_path: &std::path::Path,
+        pasted: IncrementalPaste,
+        jobs: &mpsc::Sender<Job>,
+        in_flight: &mut std::collections::VecDeque<InFlight>,
+    ) -> Result<()> {
+        let info = self
+            .settings
+            .log_transcription_detail
+            .then(|| RecordingInfo::read(wav_path));
+        let samples = audio::read_wav_samples(wav_path);
+        self.finish_recording(wav_path);
+        jobs.send(Job::Clip(samples?))
+            .map_err(|_| anyhow::anyhow!("transcription worker exited"))?;
+        in_flight.push_back(InFlight { pasted, info });
         Ok(())
     }
+
+    /// Paste the worker's result for `job`. With `incremental_paste`, only
+    /// the part not typed while recording is pasted; with
+    /// `confirm_before_paste`, it is held in `pending` instead.
+    fn deliver(
+        &self,
+        result: Result<Transcription>,
+        job: InFlight,
+        callbacks: &mut dyn ServiceCallbacks,
+        pending: &mut Option<PendingPaste>,
+    ) {
+        let InFlight { mut pasted, info } = job;
+        match result {
+            Ok(transcription) => {
+                if let Some(info) = &info {
+                    log_transcription_detail(info, &transcription, self.privacy);
+                }
+                let text = transcription.text;
+                let continues = pasted.has_pasted();
+                // Once words were typed incrementally the dictation counts.
+                if !continues && self.output.rejects(&text) {
+                    callbacks.on_status_msg("Nothing meaningful transcribed");
+                    return;
+                }
+                let text = self.output.run_post_hook(text, callbacks);
+                let previous = pasted.last_char;
+                let remainder = pasted.finish(&text);
+                if !text.is_empty() {
+                    callbacks.on_text(&text);
+                }
+                if remainder.is_empty() {
+                    return;
+                }
+                if self.settings.confirm_before_paste {
+                    let window = u64::from(self.settings.confirm_window_ms);
+                    *pending = Some(PendingPaste {
+                        text: remainder,
+                        deadline: std::time::Instant::now()
+                            + std::time::Duration::from_millis(window),
+                    });
+                    callbacks.on_status_msg(&format!(
+                        "Tap {} to paste",
+                        input::key_label(&self.settings.key)
+                    ));
+                } else {
+                    self.paste_continuation(&remainder, previous, callbacks);
+                }
+            }
+            Err(e) => {
+                callbacks.on_error(&format!("Transcription failed: {e}"));
+            }
+        }
+    }
+
+    /// Start arecord, firing `pre_record_hook` just before and
+    /// `post_record_hook` if it fails to start.
+    fn start_recording(&self, wav_path: &std::path::Path) -> Result<Recording> {
+        if let Some(hooks) = &self.record_hooks {
+            hooks.before_recording();
+        }
+        let result = (self.start_recorder)(wav_path, &self.capture_timing, &self.recording_format);
+        if result.is_err()
+            && let Some(hooks) = &self.record_hooks
+        {
+            hooks.after_recording();
+        }
+        result
+    }
+
+    /// Stop arecord and fire `post_record_hook`.
+    fn stop_recording(&self, rec: Recording) -> Result<std::path::PathBuf> {
+        let result = rec.stop();
+        if let Some(hooks) = &self.record_hooks {
+            hooks.after_recording();
+        }
+        result
+    }
+
+    /// Paste the next part of an incrementally pasted dictation, separated
+    /// from the part that ended with `previous` if that didn't end in
+    /// whitespace. Returns the last character typed.
+    fn paste_continuation(
+        &self,
+        text: &str,
+        previous: Option<char>,
+        callbacks: &mut dyn ServiceCallbacks,
+    ) -> Option<char> {
+        let text = format!("{}{text}", paste::leading_separator(previous, text));
+        self.output.paste(&text, callbacks);
+        paste::payload_end(&text, &self.output.paste_config)
+    }
+
+    /// Queue append-mode audio joined from several presses as one clip.
+    fn transcribe_pending(
+        &self,
+        samples: &[i16],
+        callbacks: &mut dyn ServiceCallbacks,
+        jobs: &mpsc::Sender<Job>,
+        in_flight: &mut std::collections::VecDeque<InFlight>,
+    ) {
+        if samples.is_empty() {
+            return;
+        }
+        callbacks.on_status(ServiceStatus::Transcribing);
+        // Written out so `keep_recordings` archives the joined clip.
+        match audio::temp_wav_path(&self.recording_dir)
+            .and_then(|path| audio::write_wav_samples(&path, samples).map(|()| path))
+        {
+            Ok(wav_path) => {
+                if let Err(e) = self.queue_transcription(
+                    &wav_path,
+                    IncrementalPaste::default(),
+                    jobs,
+                    in_flight,
+                ) {
+                    callbacks.on_error(&format!("Transcription failed: {e:#}"));
+                }
+            }
+            Err(e) => callbacks.on_error(&format!("Failed to write recording: {e}")),
+        }
+        callbacks.on_status(settled_status(in_flight.len()));
+    }
 }
 
 /// Global shutdown flag for signal handler.
 static SHUTDOWN_FLAG: AtomicBool = AtomicBool::new(false);
 
-/// Run as a daemon (default mode).
-pub fn run_daemon() -> Result<()> {
+/// Run as a daemon (default mode). With `json_events`, callbacks are also
+/// written to stdout as `crate::events::Event` lines. With `watch_config`,
+/// the service restarts with fresh settings whenever the config file is
+/// saved, and waits for the next save when they fail to load.
+pub fn run_daemon(json_events: bool, watch_config: bool) -> Result<()> {
+    SHUTDOWN_FLAG.store(false, Ordering::Relaxed);
+    if !watch_config {
+        return run_service(json_events, None);
+    }
+
+    let path = crate::config::config_path();
+    let reload = crate::watch::watch_config(&path)?;
+    log::info!("Watching {} for changes", path.display());
+    loop {
+        let result = run_service(json_events, Some(&reload));
+        if SHUTDOWN_FLAG.load(Ordering::Relaxed) {
+            return result;
+        }
+        if let Err(e) = result {
+            log::error!("{e:#}");
+            log::info!("Waiting for {} to change...", path.display());
+        }
+        while !reload.swap(false, Ordering::Relaxed) {
+            if SHUTDOWN_FLAG.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        log::info!("Restarting with the new settings");
+    }
+}
+
+/// Load settings and run one `DictationService` until shutdown, or until
+/// `reload` is set.
+fn run_service(json_events: bool, reload: Option<&Arc<AtomicBool>>) -> Result<()> {
     let settings = crate::config::load_settings()?;
 
-    let report = crate::preflight::check_environment();
+    // Under systemd, starting before the session is ready would crash-loop.
+    let report = if settings.retry_preflight {
+        crate::preflight::wait_for_environment(std::time::Duration::from_secs(u64::from(
+            settings.retry_preflight_timeout_secs,
+        )))
+    } else {
+        crate::preflight::check_environment()
+    };
     if report.has_critical_failures() {
         anyhow::bail!("{}", report.critical_failure_summary());
     }
@@ -295,7 +1652,6 @@ pub fn run_daemon() -> Result<()> {
     let service = DictationService::new(settings)?;
 
     let shutdown = service.shutdown_handle();
-    SHUTDOWN_FLAG.store(false, Ordering::Relaxed);
 
     unsafe {
         libc::signal(
@@ -309,9 +1665,12 @@ pub fn run_daemon() -> Result<()> {
     }
 
     let shutdown_clone = shutdown.clone();
+    let reload = reload.cloned();
     std::thread::spawn(move || {
         loop {
-            if SHUTDOWN_FLAG.load(Ordering::Relaxed) {
+            if SHUTDOWN_FLAG.load(Ordering::Relaxed)
+                || reload.as_ref().is_some_and(|r| r.load(Ordering::Relaxed))
+            {
                 shutdown_clone.store(true, Ordering::Relaxed);
                 break;
             }
@@ -319,8 +1678,14 @@ pub fn run_daemon() -> Result<()> {
         }
     });
 
-    let mut callbacks = LogCallbacks;
-    service.run_loop(&mut callbacks)
+    let mut callbacks =
+        LogCallbacks::new(service.privacy_mode(), crate::systemd::Notifier::from_env());
+    if json_events {
+        let mut events = crate::events::JsonEvents::new(std::io::stdout(), callbacks);
+        service.run_loop(&mut events)
+    } else {
+        service.run_loop(&mut callbacks)
+    }
 }
 
 extern "C" fn signal_handler(_sig: libc::c_int) {
@@ -331,16 +1696,453 @@ extern "C" fn signal_handler(_sig: libc::c_int) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_press_refusal() {
+        for mode in [BusyPress::Queue, BusyPress::Ignore] {
+            assert_eq!(press_refusal(mode, 0, 1), None);
+        }
+        assert_eq!(press_refusal(BusyPress::Queue, 2, 3), None);
+        assert!(
+            press_refusal(BusyPress::Ignore, 1, 3)
+                .unwrap()
+                .contains("ignored")
+        );
+        // A full queue asks the user to wait instead of dropping or overlapping.
+        assert!(
+            press_refusal(BusyPress::Queue, 3, 3)
+                .unwrap()
+                .starts_with("Busy")
+        );
+        assert_eq!(settled_status(0), ServiceStatus::Ready);
+        assert_eq!(settled_status(1), ServiceStatus::Transcribing);
+    }
+
+    fn transcription(text: &str) -> Transcription {
+        Transcription {
+            text: text.into(),
+            confidence: 0.9,
+            segments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_transcribe_worker_keeps_order() {
+        let (jobs, results) = spawn_transcribe_worker(
+            |samples, on_status| {
+                if samples.is_empty() {
+                    anyhow::bail!("no audio");
+                }
+                on_status("working");
+                // Later clips finish faster; results must still come back in order.
+                std::thread::sleep(std::time::Duration::from_millis(30 / samples.len() as u64));
+                Ok(transcription(&samples.len().to_string()))
+            },
+            |_| unreachable!("no partials sent"),
+        );
+        for clip in [vec![0; 1], vec![], vec![0; 3]] {
+            jobs.send(Job::Clip(clip)).unwrap();
+        }
+        let mut done = Vec::new();
+        let mut statuses = 0;
+        while done.len() < 3 {
+            match results
+                .recv_timeout(std::time::Duration::from_secs(5))
+                .unwrap()
+            {
+                WorkerEvent::Status(msg) => {
+                    assert_eq!(msg, "working");
+                    statuses += 1;
+                }
+                WorkerEvent::Done(result) => {
+                    done.push(result.map(|t| t.text).map_err(|e| e.to_string()))
+                }
+                WorkerEvent::Partial { .. } => panic!("no partials sent"),
+            }
+        }
+        assert_eq!(
+            done,
+            vec![
+                Ok("1".to_string()),
+                Err("no audio".to_string()),
+                Ok("3".to_string())
+            ]
+        );
+        assert_eq!(statuses, 2);
+
+        // Dropping the sender stops the worker.
+        drop(jobs);
+        assert!(matches!(
+            results.recv_timeout(std::time::Duration::from_secs(5)),
+            Err(mpsc::RecvTimeoutError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_transcribe_worker_drains_full_queue() {
+        let (jobs, results) = spawn_transcribe_worker(
+            |samples, _| Ok(transcription(&samples.len().to_string())),
+            |_| unreachable!("no partials sent"),
+        );
+        let depth = MAX_QUEUE_DEPTH as usize;
+        for n in 1..=depth {
+            jobs.send(Job::Clip(vec![0; n])).unwrap();
+        }
+        let texts: Vec<String> = (0..depth)
+            .map(
+                |_| match results.recv_timeout(std::time::Duration::from_secs(5)) {
+                    Ok(WorkerEvent::Done(Ok(t))) => t.text,
+                    _ => panic!("expected a transcription"),
+                },
+            )
+            .collect();
+        let expected: Vec<String> = (1..=depth).map(|n| n.to_string()).collect();
+        assert_eq!(texts, expected);
+    }
+
+    #[test]
+    fn test_transcribe_worker_runs_partials() {
+        let (jobs, results) = spawn_transcribe_worker(
+            |samples, _| Ok(transcription(&format!("clip {}", samples.len()))),
+            |samples| {
+                if samples.is_empty() {
+                    anyhow::bail!("no audio yet");
+                }
+                Ok(format!("partial {}", samples.len()))
+            },
+        );
+        jobs.send(Job::Partial {
+            recording: 1,
+            samples: vec![0; 2],
+        })
+        .unwrap();
+        jobs.send(Job::Clip(vec![0; 4])).unwrap();
+        jobs.send(Job::Partial {
+            recording: 2,
+            samples: vec![],
+        })
+        .unwrap();
+        let mut events = Vec::new();
+        for _ in 0..3 {
+            events.push(
+                match results
+                    .recv_timeout(std::time::Duration::from_secs(5))
+                    .unwrap()
+                {
+                    WorkerEvent::Partial { recording, result } => {
+                        format!("{recording}: {}", result.unwrap_or_else(|e| e.to_string()))
+                    }
+                    WorkerEvent::Done(result) => result.unwrap().text,
+                    WorkerEvent::Status(msg) => msg,
+                },
+            );
+        }
+        assert_eq!(events, ["1: partial 2", "clip 4", "2: no audio yet"]);
+    }
+
+    #[test]
+    fn test_auto_stop_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(auto_stop_from_settings(&settings).unwrap(), None);
+        settings.auto_stop_silence_ms = 1500;
+        assert_eq!(
+            auto_stop_from_settings(&settings).unwrap(),
+            Some(std::time::Duration::from_millis(1500))
+        );
+        for invalid in [100, 20_000] {
+            settings.auto_stop_silence_ms = invalid;
+            assert!(auto_stop_from_settings(&settings).is_err());
+        }
+    }
+
+    #[test]
+    fn test_queue_depth_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(queue_depth_from_settings(&settings).unwrap(), 3);
+        settings.queue_depth = MAX_QUEUE_DEPTH;
+        assert_eq!(queue_depth_from_settings(&settings).unwrap(), 16);
+        for invalid in [0, MAX_QUEUE_DEPTH + 1] {
+            settings.queue_depth = invalid;
+            assert!(queue_depth_from_settings(&settings).is_err());
+        }
+    }
+
+    #[test]
+    fn test_busy_press_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            BusyPress::from_settings(&settings).unwrap(),
+            BusyPress::Queue
+        );
+        settings.busy_press = "ignore".into();
+        assert_eq!(
+            BusyPress::from_settings(&settings).unwrap(),
+            BusyPress::Ignore
+        );
+        settings.busy_press = "wait".into();
+        assert!(BusyPress::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_incremental_paste_commits_stable_prefix() {
+        let mut pasted = IncrementalPaste::default();
+        // First partial: nothing to compare against yet.
+        assert_eq!(pasted.on_partial("the quick"), None);
+        // "the" and "quick" agree, but the last word may still change.
+        assert_eq!(
+            pasted.on_partial("the quick brow"),
+            Some("the quick".into())
+        );
+        assert_eq!(pasted.on_partial("the quick brown fox"), None);
+        assert_eq!(
+            pasted.on_partial("the quick brown fox jumps"),
+            Some("brown fox".into())
+        );
+        assert_eq!(
+            pasted.finish("the quick brown fox jumps over."),
+            "jumps over."
+        );
+    }
+
+    #[test]
+    fn test_incremental_paste_ignores_revised_words() {
+        let mut pasted = IncrementalPaste::default();
+        pasted.on_partial("wreck a nice");
+        assert_eq!(
+            pasted.on_partial("wreck a nice beach"),
+            Some("wreck a nice".into())
+        );
+        // Whisper revises already-typed words: nothing more is committed
+        // while partials disagree with what was typed.
+        assert_eq!(pasted.on_partial("recognize speech today"), None);
+        assert_eq!(pasted.on_partial("recognize speech today ok"), None);
+        // The final text is pasted past the number of words already typed.
+        assert_eq!(pasted.finish("recognize speech today please"), "please");
+    }
+
+    #[test]
+    fn test_incremental_paste_finish_without_partials() {
+        let mut pasted = IncrementalPaste::default();
+        assert_eq!(pasted.finish("hello world"), "hello world");
+        assert_eq!(pasted.finish(""), "");
+        // A newline from post_hook survives, so no space is added after it.
+        assert_eq!(pasted.finish("- milk\n"), "- milk\n");
+    }
+
+    #[test]
+    fn test_log_callbacks_redact_text_in_privacy_mode() {
+        let private = LogCallbacks::new(true, None);
+        assert_eq!(
+            private.transcribed_message("my bank pin"),
+            "Transcribed: <11 chars redacted>"
+        );
+        let open = LogCallbacks::new(false, None);
+        assert_eq!(
+            open.transcribed_message("my bank pin"),
+            "Transcribed: my bank pin"
+        );
+    }
+
     #[test]
     fn test_service_status_display() {
         assert_eq!(ServiceStatus::Stopped.to_string(), "stopped");
         assert_eq!(ServiceStatus::Starting.to_string(), "starting");
+        assert_eq!(ServiceStatus::Downloading.to_string(), "downloading");
         assert_eq!(ServiceStatus::Ready.to_string(), "ready");
         assert_eq!(ServiceStatus::Recording.to_string(), "recording");
         assert_eq!(ServiceStatus::Transcribing.to_string(), "transcribing");
         assert_eq!(ServiceStatus::Stopping.to_string(), "stopping");
     }
 
+    #[test]
+    fn test_is_accidental_tap() {
+        use std::time::Duration;
+        assert!(is_accidental_tap(Duration::from_millis(120), 250));
+        assert!(!is_accidental_tap(Duration::from_millis(250), 250));
+        assert!(!is_accidental_tap(Duration::from_millis(900), 250));
+        // Disabled when set to 0
+        assert!(!is_accidental_tap(Duration::ZERO, 0));
+    }
+
+    #[test]
+    fn test_poll_timeout() {
+        use std::time::{Duration, Instant};
+        let now = Instant::now();
+        assert_eq!(poll_timeout(true, None, now), LEVEL_POLL_INTERVAL);
+        assert_eq!(poll_timeout(false, None, now), IDLE_POLL_INTERVAL);
+        assert_eq!(
+            poll_timeout(false, Some(now + Duration::from_millis(120)), now),
+            Duration::from_millis(120)
+        );
+        assert_eq!(
+            poll_timeout(false, Some(now + Duration::from_secs(5)), now),
+            IDLE_POLL_INTERVAL
+        );
+        // Deadline already passed: don't block
+        assert_eq!(poll_timeout(false, Some(now), now), Duration::ZERO);
+        // Recording takes priority over a pending deadline
+        assert_eq!(poll_timeout(true, Some(now), now), LEVEL_POLL_INTERVAL);
+    }
+
+    #[test]
+    fn test_grab_transition() {
+        assert_eq!(grab_transition(&KeyEvent::Press), Some(true));
+        assert_eq!(grab_transition(&KeyEvent::Release), Some(false));
+        // The cancel key leaves the grab alone; it ends on release.
+        assert_eq!(grab_transition(&KeyEvent::Cancel), None);
+        assert_eq!(grab_transition(&KeyEvent::Error("x".into())), None);
+    }
+
+    #[test]
+    fn test_chord_single_key() {
+        let mut chord = ChordState::new(vec![evdev::Key::KEY_RIGHTCTRL]);
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_RIGHTCTRL, 1),
+            Some(KeyEvent::Press)
+        );
+        assert_eq!(chord.handle(evdev::Key::KEY_RIGHTCTRL, 2), None);
+        assert_eq!(chord.handle(evdev::Key::KEY_A, 1), None);
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_RIGHTCTRL, 0),
+            Some(KeyEvent::Release)
+        );
+    }
+
+    #[test]
+    fn test_chord_requires_all_keys_held() {
+        let mut chord = ChordState::new(vec![evdev::Key::KEY_LEFTCTRL, evdev::Key::KEY_LEFTALT]);
+        assert_eq!(chord.handle(evdev::Key::KEY_LEFTCTRL, 1), None);
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_LEFTALT, 1),
+            Some(KeyEvent::Press)
+        );
+        // Releasing either key ends the chord
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_LEFTALT, 0),
+            Some(KeyEvent::Release)
+        );
+        assert_eq!(chord.handle(evdev::Key::KEY_LEFTCTRL, 0), None);
+    }
+
+    #[test]
+    fn test_chord_repress_after_partial_release() {
+        let mut chord = ChordState::new(vec![evdev::Key::KEY_LEFTCTRL, evdev::Key::KEY_LEFTALT]);
+        chord.handle(evdev::Key::KEY_LEFTALT, 1);
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_LEFTCTRL, 1),
+            Some(KeyEvent::Press)
+        );
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_LEFTCTRL, 0),
+            Some(KeyEvent::Release)
+        );
+        assert_eq!(
+            chord.handle(evdev::Key::KEY_LEFTCTRL, 1),
+            Some(KeyEvent::Press)
+        );
+    }
+
+    fn watcher(cancel: Option<evdev::Key>) -> KeyWatcher {
+        KeyWatcher {
+            chord: ChordState::new(vec![evdev::Key::KEY_RIGHTCTRL]),
+            cancel,
+        }
+    }
+
+    #[test]
+    fn test_watcher_cancel_fires_on_press_only() {
+        let mut w = watcher(Some(evdev::Key::KEY_ESC));
+        assert_eq!(w.handle(evdev::Key::KEY_ESC, 1), Some(KeyEvent::Cancel));
+        assert_eq!(w.handle(evdev::Key::KEY_ESC, 2), None);
+        assert_eq!(w.handle(evdev::Key::KEY_ESC, 0), None);
+    }
+
+    #[test]
+    fn test_watcher_cancel_while_trigger_held() {
+        let mut w = watcher(Some(evdev::Key::KEY_ESC));
+        assert_eq!(
+            w.handle(evdev::Key::KEY_RIGHTCTRL, 1),
+            Some(KeyEvent::Press)
+        );
+        assert_eq!(w.handle(evdev::Key::KEY_ESC, 1), Some(KeyEvent::Cancel));
+        assert_eq!(
+            w.handle(evdev::Key::KEY_RIGHTCTRL, 0),
+            Some(KeyEvent::Release)
+        );
+    }
+
+    #[test]
+    fn test_watcher_without_cancel_key() {
+        let mut w = watcher(None);
+        assert_eq!(w.handle(evdev::Key::KEY_ESC, 1), None);
+        assert_eq!(
+            w.handle(evdev::Key::KEY_RIGHTCTRL, 1),
+            Some(KeyEvent::Press)
+        );
+    }
+
+    #[test]
+    fn test_resolve_cancel_key() {
+        let trigger = [evdev::Key::KEY_RIGHTCTRL];
+        assert_eq!(resolve_cancel_key("", &trigger).unwrap(), None);
+        assert_eq!(
+            resolve_cancel_key("KEY_ESC", &trigger).unwrap(),
+            Some(evdev::Key::KEY_ESC)
+        );
+        assert!(resolve_cancel_key("KEY_RIGHTCTRL", &trigger).is_err());
+        assert!(resolve_cancel_key("KEY_NOPE", &trigger).is_err());
+    }
+
+    #[test]
+    fn test_pick_portal() {
+        assert!(pick_portal("evdev", false).unwrap().is_none());
+        assert!(pick_portal("", false).unwrap().is_none());
+        assert!(pick_portal("libinput", false).is_err());
+    }
+
+    #[test]
+    fn test_portal_key_event() {
+        let event = |id: &str, pressed| portal::ShortcutEvent {
+            id: id.into(),
+            pressed,
+        };
+        assert!(matches!(
+            portal_key_event(&event(portal::DICTATE_ID, true)),
+            Some(KeyEvent::Press)
+        ));
+        assert!(matches!(
+            portal_key_event(&event(portal::DICTATE_ID, false)),
+            Some(KeyEvent::Release)
+        ));
+        assert!(matches!(
+            portal_key_event(&event(portal::CANCEL_ID, true)),
+            Some(KeyEvent::Cancel)
+        ));
+        assert!(portal_key_event(&event(portal::CANCEL_ID, false)).is_none());
+        assert!(portal_key_event(&event("other", true)).is_none());
+    }
+
+    #[test]
+    fn test_output_deliver_uses_sink_filter_and_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.txt");
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            output_sink: "file".into(),
+            sink_file: notes.to_string_lossy().into_owned(),
+            post_hook: "tr a-z A-Z".into(),
+            min_chars: 3,
+            ..Default::default()
+        };
+        let output = Output::from_settings(&settings).unwrap();
+        let mut cb = TestCallbacks::new();
+        output.deliver("ok", &mut cb);
+        assert!(!notes.exists());
+        output.deliver("hello again", &mut cb);
+        let saved = std::fs::read_to_string(&notes).unwrap();
+        assert!(saved.trim_end().ends_with("] HELLO AGAIN"), "{saved}");
+        assert!(cb.errors.is_empty());
+    }
+
     #[test]
     fn test_service_status_equality() {
         assert_eq!(ServiceStatus::Ready, ServiceStatus::Ready);
@@ -376,6 +2178,226 @@ mod tests {
         }
     }
 
+    /// Stands in for arecord: leaves 0.1s of audio and runs until stopped.
+    fn fake_recorder(
+        path: &std::path::Path,
+        _timing: &audio::CaptureTiming,
+        format: &audio::RecordingFormat,
+    ) -> Result<Recording> {
+        audio::write_wav_samples(path, &[1000; 1600])?;
+        Recording::spawn(
+            std::process::Command::new("sleep").arg("60"),
+            "arecord",
+            path,
+            format,
+        )
+    }
+
+    /// A service whose recordings go to `dir` and whose output is appended
+    /// to `dir/notes.txt`.
+    fn loop_service(dir: &std::path::Path) -> DictationService {
+        let settings = Settings {
+            paste_method: "wl-copy".into(),
+            output_sink: "file".into(),
+            sink_file: dir.join("notes.txt").to_string_lossy().into_owned(),
+            min_hold_ms: 0,
+            release_padding_ms: 0,
+            ..Default::default()
+        };
+        DictationService {
+            trigger: TriggerSource::Device(PathBuf::from("/dev/input/event-test")),
+            keys: vec![evdev::Key::KEY_RIGHTCTRL],
+            cancel_key: Some(evdev::Key::KEY_ESC),
+            recording_dir: dir.join("recordings"),
+            archive: None,
+            capture_timing: audio::CaptureTiming::from_settings(&settings).unwrap(),
+            recording_format: audio::RecordingFormat::default(),
+            decode: transcribe::DecodeOptions::from_settings(&settings).unwrap(),
+            busy_press: BusyPress::from_settings(&settings).unwrap(),
+            queue_depth: queue_depth_from_settings(&settings).unwrap(),
+            auto_stop: None,
+            record_hooks: None,
+            key_warning: None,
+            grab_key: false,
+            output: Output::from_settings(&settings).unwrap(),
+            privacy: false,
+            start_recorder: fake_recorder,
+            shutdown: Arc::new(AtomicBool::new(false)),
+            settings,
+        }
+    }
+
+    /// Callbacks written down as they happen, e.g. "status Ready".
+    struct LoggedCallbacks(mpsc::Sender<String>);
+
+    impl ServiceCallbacks for LoggedCallbacks {
+        fn on_status(&mut self, status: ServiceStatus) {
+            let _ = self.0.send(format!("status {status:?}"));
+        }
+        fn on_status_msg(&mut self, msg: &str) {
+            let _ = self.0.send(format!("msg {msg}"));
+        }
+        fn on_text(&mut self, text: &str) {
+            let _ = self.0.send(format!("text {text}"));
+        }
+        fn on_error(&mut self, error: &str) {
+            let _ = self.0.send(format!("error {error}"));
+        }
+    }
+
+    /// The test's side of `event_loop`: the key reader, the transcription
+    /// worker and the callbacks seen so far.
+    struct LoopHarness {
+        /// Unbuffered, so a send returns once the loop has taken the key.
+        keys: mpsc::SyncSender<KeyEvent>,
+        jobs: mpsc::Receiver<Job>,
+        results: mpsc::Sender<WorkerEvent>,
+        log: mpsc::Receiver<String>,
+        seen: Vec<String>,
+    }
+
+    impl LoopHarness {
+        fn wait_for(&mut self, entry: &str) {
+            loop {
+                let next = self
+                    .log
+                    .recv_timeout(std::time::Duration::from_secs(5))
+                    .unwrap_or_else(|_| panic!("no {entry:?} after {:?}", self.seen));
+                self.seen.push(next.clone());
+                if next == entry {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Run `event_loop` while `script` plays the key reader and worker,
+    /// then shut it down. Returns the harness with every callback seen.
+    fn drive(service: &DictationService, script: impl FnOnce(&mut LoopHarness)) -> LoopHarness {
+        let (key_tx, key_rx) = mpsc::sync_channel(0);
+        let (job_tx, job_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel();
+        let (log_tx, log_rx) = mpsc::channel();
+        let mut harness = LoopHarness {
+            keys: key_tx,
+            jobs: job_rx,
+            results: result_tx,
+            log: log_rx,
+            seen: Vec::new(),
+        };
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                service.event_loop(&mut LoggedCallbacks(log_tx), &key_rx, &job_tx, &result_rx)
+            });
+            script(&mut harness);
+            service.shutdown.store(true, Ordering::Relaxed);
+        });
+        let rest: Vec<String> = harness.log.try_iter().collect();
+        harness.seen.extend(rest);
+        harness
+    }
+
+    #[test]
+    fn test_event_loop_cancel_while_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = loop_service(dir.path());
+        let harness = drive(&service, |h| {
+            h.keys.send(KeyEvent::Press).unwrap();
+            h.keys.send(KeyEvent::Cancel).unwrap();
+        });
+        assert_eq!(
+            harness.seen,
+            [
+                "status Recording",
+                "status Ready",
+                "msg Cancelled",
+                "status Stopping"
+            ]
+        );
+        // Nothing reached the worker, and the recording was deleted.
+        assert!(harness.jobs.try_recv().is_err());
+        let left = std::fs::read_dir(dir.path().join("recordings")).unwrap();
+        assert_eq!(left.count(), 0);
+    }
+
+    #[test]
+    fn test_event_loop_cancel_while_transcribing() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = loop_service(dir.path());
+        let harness = drive(&service, |h| {
+            h.keys.send(KeyEvent::Press).unwrap();
+            h.keys.send(KeyEvent::Release).unwrap();
+            let job = h.jobs.recv_timeout(std::time::Duration::from_secs(5));
+            let Ok(Job::Clip(samples)) = job else {
+                panic!("no clip queued");
+            };
+            assert_eq!(samples.len(), 1600);
+            // Too late to cancel: the result is still delivered.
+            h.keys.send(KeyEvent::Cancel).unwrap();
+            h.results
+                .send(WorkerEvent::Done(Ok(Transcription {
+                    text: "hello world".into(),
+                    confidence: 1.0,
+                    segments: Vec::new(),
+                })))
+                .unwrap();
+            h.wait_for("text hello world");
+        });
+        assert_eq!(
+            harness.seen,
+            [
+                "status Recording",
+                "status Transcribing",
+                "status Transcribing",
+                "text hello world",
+                "status Ready",
+                "status Stopping"
+            ]
+        );
+        let notes = std::fs::read_to_string(dir.path().join("notes.txt")).unwrap();
+        assert!(notes.trim_end().ends_with("] hello world"), "{notes}");
+    }
+
+    #[test]
+    fn test_event_loop_cancel_while_idle() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = loop_service(dir.path());
+        let harness = drive(&service, |h| {
+            h.keys.send(KeyEvent::Cancel).unwrap();
+        });
+        assert_eq!(harness.seen, ["status Stopping"]);
+        assert!(harness.jobs.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_loop_release_padding() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut service = loop_service(dir.path());
+        service.settings.release_padding_ms = 200;
+        let harness = drive(&service, |h| {
+            h.keys.send(KeyEvent::Press).unwrap();
+            h.keys.send(KeyEvent::Release).unwrap();
+            // The loop keeps taking keys while padding; a press continues
+            // the same dictation.
+            h.keys.send(KeyEvent::Press).unwrap();
+            h.keys.send(KeyEvent::Release).unwrap();
+            let released = std::time::Instant::now();
+            let job = h.jobs.recv_timeout(std::time::Duration::from_secs(5));
+            assert!(matches!(job, Ok(Job::Clip(_))));
+            assert!(released.elapsed() >= std::time::Duration::from_millis(200));
+        });
+        assert_eq!(
+            harness.seen,
+            [
+                "status Recording",
+                "status Transcribing",
+                "status Transcribing",
+                "status Stopping"
+            ]
+        );
+        assert!(harness.jobs.try_recv().is_err());
+    }
+
     #[test]
     fn test_callbacks() {
         let mut cb = TestCallbacks::new();
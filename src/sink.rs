@@ -0,0 +1,166 @@
+//! Where transcriptions go (`output_sink`): pasted at the cursor, or
+//! appended to a notes file.
+
+use anyhow::{Context, Result, bail};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::Settings;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputSink {
+    Paste,
+    File(PathBuf),
+}
+
+impl OutputSink {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        match settings.output_sink.trim() {
+            "" | "paste" => Ok(Self::Paste),
+            "file" => Ok(Self::File(sink_file_path(&settings.sink_file))),
+            other => bail!("Invalid output_sink: {other} (expected paste or file)"),
+        }
+    }
+
+    pub fn is_paste(&self) -> bool {
+        matches!(self, Self::Paste)
+    }
+}
+
+/// Resolve `sink_file`; "auto" is escucha-notes.md in the documents dir.
+pub fn sink_file_path(setting: &str) -> PathBuf {
+    let setting = setting.trim();
+    if setting.is_empty() || setting == "auto" {
+        dirs::document_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("escucha-notes.md")
+    } else {
+        crate::logging::expand_home(setting)
+    }
+}
+
+/// Append one timestamped line to the notes file, creating it (and its
+/// directory) if needed.
+pub fn append_note(path: &Path, text: &str) -> Result<()> {
+    append_entry(path, &format_entry(&local_timestamp(), text))
+}
+
+fn format_entry(stamp: &str, text: &str) -> String {
+    format!("[{stamp}] {}\n", text.trim())
+}
+
+fn append_entry(path: &Path, entry: &str) -> Result<()> {
+    if let Some(dir) = path.parent()
+        && !dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    // Start on a fresh line if the file was edited by hand.
+    let mut prefix = "";
+    if file.seek(SeekFrom::End(0))? > 0 {
+        file.seek(SeekFrom::End(-1))?;
+        let mut last = [0u8];
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            prefix = "\n";
+        }
+    }
+    file.write_all(format!("{prefix}{entry}").as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Current local time as "YYYY-MM-DD HH:MM".
+pub(crate) fn local_timestamp() -> String {
+    let now = libc::time_t::try_from(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    )
+    .unwrap_or_default();
+    // SAFETY: localtime_r only writes the tm we pass it.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return String::new();
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_sink_from_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            OutputSink::from_settings(&settings).unwrap(),
+            OutputSink::Paste
+        );
+        settings.output_sink = "file".into();
+        settings.sink_file = "/tmp/notes.md".into();
+        assert_eq!(
+            OutputSink::from_settings(&settings).unwrap(),
+            OutputSink::File(PathBuf::from("/tmp/notes.md"))
+        );
+        settings.output_sink = "clipboard".into();
+        assert!(OutputSink::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_sink_file_path() {
+        assert!(sink_file_path("auto").ends_with("escucha-notes.md"));
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(sink_file_path("~/journal.md"), home.join("journal.md"));
+    }
+
+    #[test]
+    fn test_append_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes").join("journal.md");
+        append_entry(&path, &format_entry("2026-01-02 09:05", " first note ")).unwrap();
+        append_entry(&path, &format_entry("2026-01-02 09:06", "second")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[2026-01-02 09:05] first note\n[2026-01-02 09:06] second\n"
+        );
+    }
+
+    #[test]
+    fn test_append_entry_adds_missing_newline() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.md");
+        std::fs::write(&path, "# Journal").unwrap();
+        append_entry(&path, &format_entry("2026-01-02 09:05", "hello")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Journal\n[2026-01-02 09:05] hello\n"
+        );
+    }
+
+    #[test]
+    fn test_append_note_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.md");
+        append_note(&path, "hello").unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        // "[YYYY-MM-DD HH:MM] hello\n"
+        assert_eq!(content.len(), "[2026-01-02 09:05] hello\n".len());
+        assert!(content.starts_with('[') && content.ends_with("] hello\n"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Running as a systemd user service: writing the unit
+//! (`--install-service`), and sd_notify support for `Type=notify`: READY=1
+//! once the model is loaded, and WATCHDOG=1 keep-alives when the unit sets
+//! `WatchdogSec=`. Notification is a no-op outside systemd.
+
+use anyhow::{Context, Result, bail};
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// The user unit `--install-service` manages.
+pub const UNIT_NAME: &str = "escucha.service";
+
+/// The unit shipped in systemd/; only its ExecStart is rewritten.
+const UNIT_TEMPLATE: &str = include_str!("../systemd/escucha.service");
+
+const YDOTOOLD_UNIT: &str = "ydotoold.service";
+
+/// Same unit install.sh writes for ydotoold.
+const YDOTOOLD_UNIT_TEMPLATE: &str = "[Unit]
+Description=ydotool daemon for input automation
+After=graphical-session.target
+
+[Service]
+Type=simple
+{exec_start}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=default.target
+";
+
+/// Connection to the service manager's notification socket.
+pub struct Notifier {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+    watchdog: Option<Watchdog>,
+}
+
+/// When the next keep-alive is due.
+struct Watchdog {
+    interval: Duration,
+    last_ping: Option<Instant>,
+}
+
+impl Notifier {
+    /// Notifier for `$NOTIFY_SOCKET`, or None when not started by systemd
+    /// (or the socket can't be used, which is logged).
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var(name).ok();
+        match Self::from_vars(
+            var("NOTIFY_SOCKET").as_deref(),
+            var("WATCHDOG_USEC").as_deref(),
+            var("WATCHDOG_PID").as_deref(),
+            std::process::id(),
+        ) {
+            Ok(notifier) => notifier,
+            Err(e) => {
+                log::warn!("systemd notification disabled: {e:#}");
+                None
+            }
+        }
+    }
+
+    fn from_vars(
+        notify_socket: Option<&str>,
+        watchdog_usec: Option<&str>,
+        watchdog_pid: Option<&str>,
+        pid: u32,
+    ) -> Result<Option<Self>> {
+        let Some(path) = notify_socket.filter(|path| !path.is_empty()) else {
+            return Ok(None);
+        };
+        let addr = socket_addr(path)?;
+        let socket = UnixDatagram::unbound().context("Failed to create notify socket")?;
+        let watchdog = watchdog_interval(watchdog_usec, watchdog_pid, pid).map(|interval| {
+            log::info!("systemd watchdog: pinging every {}ms", interval.as_millis());
+            Watchdog {
+                interval,
+                last_ping: None,
+            }
+        });
+        Ok(Some(Self {
+            socket,
+            addr,
+            watchdog,
+        }))
+    }
+
+    /// Send a raw state string, e.g. "READY=1".
+    pub fn notify(&self, state: &str) -> Result<()> {
+        self.socket
+            .send_to_addr(state.as_bytes(), &self.addr)
+            .with_context(|| format!("Failed to send {state} to systemd"))?;
+        Ok(())
+    }
+
+    /// Send WATCHDOG=1 if the unit has a watchdog and a ping is due.
+    pub fn ping_watchdog(&mut self) {
+        let Some(watchdog) = &mut self.watchdog else {
+            return;
+        };
+        if watchdog
+            .last_ping
+            .is_some_and(|last| last.elapsed() < watchdog.interval)
+        {
+            return;
+        }
+        watchdog.last_ping = Some(Instant::now());
+        if let Err(e) = self.notify("WATCHDOG=1") {
+            log::warn!("{e:#}");
+        }
+    }
+}
+
+/// Write ~/.config/systemd/user/escucha.service for the running executable
+/// and reload the user manager; with `enable`, also enable and start it.
+/// Prints each step.
+pub fn install_service(enable: bool) -> Result<()> {
+    let exe = std::env::current_exe().context("Could not locate the escucha executable")?;
+    let unit = unit_for_exe(&exe)?;
+    let path = user_unit_path(UNIT_NAME)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, unit).with_context(|| format!("Failed to write {}", path.display()))?;
+    println!("Wrote {} (ExecStart={})", path.display(), exe.display());
+
+    systemctl_user(&["daemon-reload"])?;
+    println!("Reloaded the user systemd manager");
+
+    if enable {
+        systemctl_user(&["enable", "--now", UNIT_NAME])?;
+        println!("Enabled and started {UNIT_NAME}");
+    } else {
+        println!("Start it with: systemctl --user enable --now {UNIT_NAME}");
+    }
+    Ok(())
+}
+
+/// Stop and disable escucha.service, remove its unit file and reload the
+/// user manager. Prints each step.
+pub fn uninstall_service() -> Result<()> {
+    let path = user_unit_path(UNIT_NAME)?;
+    if !path.exists() {
+        println!("No unit at {}; nothing to remove", path.display());
+        return Ok(());
+    }
+    match systemctl_user(&["disable", "--now", UNIT_NAME]) {
+        Ok(()) => println!("Stopped and disabled {UNIT_NAME}"),
+        Err(e) => println!("Could not disable {UNIT_NAME}: {e:#}"),
+    }
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    println!("Removed {}", path.display());
+    systemctl_user(&["daemon-reload"])?;
+    println!("Reloaded the user systemd manager");
+    Ok(())
+}
+
+/// Where a user unit named `name` lives.
+pub fn user_unit_path(name: &str) -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Could not determine config directory")?
+        .join("systemd")
+        .join("user")
+        .join(name))
+}
+
+/// Set up ydotoold for pasting (`--install-service --with-ydotoold`): its
+/// user unit, access to /dev/uinput, and a running daemon. Prints each
+/// step; false if ydotoold still isn't usable at the end.
+pub fn install_ydotoold_service() -> bool {
+    let unit = ensure_ydotoold_unit();
+    print_step("ydotoold unit", &unit);
+    if unit.is_err() {
+        return false;
+    }
+
+    let uinput = if crate::paste::uinput_accessible() {
+        Ok("/dev/uinput is accessible".to_string())
+    } else {
+        crate::paste::repair_uinput_permissions()
+            .map(|()| "Installed the udev rule and added you to the input group".to_string())
+    };
+    print_step("uinput", &uinput);
+
+    let running = if crate::paste::ensure_ydotoold_running() {
+        Ok("ydotoold is running".to_string())
+    } else if uinput.is_ok() && !crate::paste::uinput_accessible() {
+        Err(anyhow::anyhow!(
+            "Not running yet; log out and back in so the input group applies"
+        ))
+    } else {
+        Err(anyhow::anyhow!(
+            "Not running; see journalctl --user -u ydotoold.service"
+        ))
+    };
+    print_step("ydotoold", &running);
+    running.is_ok()
+}
+
+/// Make sure a ydotoold.service user unit exists, writing one for the
+/// ydotoold in PATH unless a package already provides it.
+fn ensure_ydotoold_unit() -> Result<String> {
+    if systemctl_user(&["cat", YDOTOOLD_UNIT]).is_ok() {
+        return Ok(format!("{YDOTOOLD_UNIT} is already installed"));
+    }
+    let ydotoold = which::which("ydotoold").context("ydotoold is not installed")?;
+    let path = user_unit_path(YDOTOOLD_UNIT)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let unit = YDOTOOLD_UNIT_TEMPLATE.replace("{exec_start}", &exec_start(&ydotoold)?);
+    std::fs::write(&path, unit).with_context(|| format!("Failed to write {}", path.display()))?;
+    systemctl_user(&["daemon-reload"])?;
+    Ok(format!("Wrote {}", path.display()))
+}
+
+fn print_step(name: &str, result: &Result<String>) {
+    match result {
+        Ok(message) => println!("  [ PASS] {name:<14} {message}"),
+        Err(e) => println!("  [ FAIL] {name:<14} {e:#}"),
+    }
+}
+
+/// The shipped unit with ExecStart pointing at `exe`.
+fn unit_for_exe(exe: &Path) -> Result<String> {
+    let exec_start = exec_start(exe)?;
+    Ok(UNIT_TEMPLATE
+        .lines()
+        .map(|line| {
+            if line.starts_with("ExecStart=") {
+                exec_start.as_str()
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n")
+}
+
+/// An `ExecStart=` line running `exe`.
+fn exec_start(exe: &Path) -> Result<String> {
+    if !exe.is_absolute() {
+        bail!("Executable path is not absolute: {}", exe.display());
+    }
+    let exe = exe.to_str().context("Executable path is not valid UTF-8")?;
+    // A path that needs quoting (or one /proc reports as " (deleted)")
+    // isn't worth the risk of a unit that runs something else.
+    if exe.contains(|c: char| c.is_whitespace() || c.is_control() || "\"'\\".contains(c)) {
+        bail!("Executable path can't be used in a unit: {exe}");
+    }
+    // % and $ are specifiers / variable references in ExecStart.
+    Ok(format!(
+        "ExecStart={}",
+        exe.replace('%', "%%").replace('$', "$$")
+    ))
+}
+
+fn systemctl_user(args: &[&str]) -> Result<()> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .context("Failed to run systemctl")?;
+    if !output.status.success() {
+        bail!(
+            "systemctl --user {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Address for `$NOTIFY_SOCKET`: a filesystem path, or "@name" for a
+/// Linux abstract socket.
+fn socket_addr(path: &str) -> Result<SocketAddr> {
+    if let Some(name) = path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        return SocketAddr::from_abstract_name(name.as_bytes())
+            .with_context(|| format!("Invalid NOTIFY_SOCKET: {path}"));
+    }
+    if !path.starts_with('/') {
+        bail!("Invalid NOTIFY_SOCKET: {path} (expected an absolute path or @name)");
+    }
+    SocketAddr::from_pathname(path).with_context(|| format!("Invalid NOTIFY_SOCKET: {path}"))
+}
+
+/// How often to ping: half of `$WATCHDOG_USEC`, as sd_watchdog_enabled(3)
+/// recommends. None without a watchdog, or when `$WATCHDOG_PID` names
+/// another process.
+fn watchdog_interval(usec: Option<&str>, pid_var: Option<&str>, pid: u32) -> Option<Duration> {
+    if let Some(watchdog_pid) = pid_var
+        && watchdog_pid.trim().parse::<u32>().ok() != Some(pid)
+    {
+        return None;
+    }
+    let usec = usec?.trim().parse::<u64>().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(listener: &UnixDatagram) -> String {
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buf = [0u8; 64];
+        let len = listener.recv(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..len]).into_owned()
+    }
+
+    #[test]
+    fn test_no_notify_socket() {
+        assert!(Notifier::from_vars(None, None, None, 1).unwrap().is_none());
+        assert!(
+            Notifier::from_vars(Some(""), None, None, 1)
+                .unwrap()
+                .is_none()
+        );
+        assert!(Notifier::from_vars(Some("relative"), None, None, 1).is_err());
+    }
+
+    #[test]
+    fn test_notify_path_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify");
+        let listener = UnixDatagram::bind(&path).unwrap();
+        let notifier = Notifier::from_vars(path.to_str(), None, None, 1)
+            .unwrap()
+            .unwrap();
+        notifier.notify("READY=1").unwrap();
+        assert_eq!(receive(&listener), "READY=1");
+    }
+
+    #[test]
+    fn test_notify_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        let name = format!("escucha-test-{}", std::process::id());
+        let listener =
+            UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(name.as_bytes()).unwrap())
+                .unwrap();
+        let mut notifier =
+            Notifier::from_vars(Some(&format!("@{name}")), Some("30000000"), None, 1)
+                .unwrap()
+                .unwrap();
+        notifier.ping_watchdog();
+        assert_eq!(receive(&listener), "WATCHDOG=1");
+        // The next ping isn't due for another 15s.
+        notifier.ping_watchdog();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener.recv(&mut [0u8; 64]).is_err());
+    }
+
+    #[test]
+    fn test_unit_for_exe() {
+        let unit = unit_for_exe(Path::new("/home/me/.local/bin/escucha")).unwrap();
+        assert!(unit.contains("\nExecStart=/home/me/.local/bin/escucha\n"));
+        assert!(!unit.contains("/usr/bin/escucha"));
+        assert!(unit.contains("Type=notify"));
+        assert!(unit.ends_with("WantedBy=default.target\n"));
+
+        let unit = unit_for_exe(Path::new("/opt/50%/escucha")).unwrap();
+        assert!(unit.contains("ExecStart=/opt/50%%/escucha\n"));
+        assert!(unit_for_exe(Path::new("target/release/escucha")).is_err());
+        assert!(unit_for_exe(Path::new("/usr/bin/escucha (deleted)")).is_err());
+    }
+
+    #[test]
+    fn test_ydotoold_unit() {
+        let unit = YDOTOOLD_UNIT_TEMPLATE.replace(
+            "{exec_start}",
+            &exec_start(Path::new("/usr/bin/ydotoold")).unwrap(),
+        );
+        assert!(unit.contains("\nExecStart=/usr/bin/ydotoold\nRestart=on-failure\n"));
+    }
+
+    #[test]
+    fn test_user_unit_path() {
+        assert!(
+            user_unit_path(UNIT_NAME)
+                .unwrap()
+                .ends_with("systemd/user/escucha.service")
+        );
+    }
+
+    #[test]
+    fn test_watchdog_interval() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 7),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("7"), 7),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("8"), 7), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 7), None);
+        assert_eq!(watchdog_interval(None, None, 7), None);
+    }
+}
@@ -1,40 +1,491 @@
 use anyhow::{Context, Result};
-use std::io::Write;
+use serde::Serialize;
+use std::fmt;
+use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
 const HF_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
 
+/// Whether this build includes a GPU backend for whisper.cpp.
+pub const GPU_SUPPORTED: bool = cfg!(any(
+    feature = "cuda",
+    feature = "vulkan",
+    feature = "hipblas"
+));
+
+/// Where Whisper inference runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComputeBackend {
+    Cpu,
+    Gpu,
+}
+
+impl std::fmt::Display for ComputeBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComputeBackend::Cpu => write!(f, "cpu"),
+            ComputeBackend::Gpu => write!(f, "gpu"),
+        }
+    }
+}
+
+/// Backend a model load will try first for the `use_gpu` setting.
+pub fn preferred_backend(use_gpu: bool) -> ComputeBackend {
+    if use_gpu && GPU_SUPPORTED {
+        ComputeBackend::Gpu
+    } else {
+        ComputeBackend::Cpu
+    }
+}
+
+/// Mean token probability below which `fallback_model` is tried.
+pub const FALLBACK_CONFIDENCE_THRESHOLD: f32 = 0.6;
+
+/// Transcribed text with the mean probability of its (non-special) tokens.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Transcription {
+    pub text: String,
+    pub confidence: f32,
+    pub segments: Vec<Segment>,
+}
+
+/// One Whisper segment with its position in the clip.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    pub confidence: f32,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:.2}s-{:.2}s] p={:.2} {}",
+            self.start_ms as f64 / 1000.0,
+            self.end_ms as f64 / 1000.0,
+            self.confidence,
+            self.text.trim()
+        )
+    }
+}
+
+impl Transcription {
+    /// Whether this result is worth re-running with a larger model.
+    /// Empty results (silence) are never retried.
+    pub fn is_low_confidence(&self) -> bool {
+        !self.text.is_empty() && self.confidence < FALLBACK_CONFIDENCE_THRESHOLD
+    }
+}
+
+/// Pick the more confident of two transcriptions of the same clip,
+/// preferring the first on a tie.
+pub fn more_confident(first: Transcription, second: Transcription) -> Transcription {
+    if second.confidence > first.confidence {
+        second
+    } else {
+        first
+    }
+}
+
+/// Largest accepted `max_segment_len`, in characters.
+const MAX_SEGMENT_LEN: u32 = 1000;
+
+/// Decoder settings applied to every Whisper run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeOptions {
+    /// Initial sampling temperature; 0 is deterministic.
+    pub temperature: f32,
+    /// Step added to the temperature when a decode fails Whisper's quality
+    /// checks; 0 disables the fallback ladder.
+    pub temperature_inc: f32,
+    /// Suppress non-speech tokens and drop bracketed annotations like `[music]`.
+    pub suppress_non_speech: bool,
+    /// Trim segment text and collapse whitespace in the result; off keeps
+    /// Whisper's raw segment spacing.
+    pub trim_result: bool,
+    /// Longest segment in characters (`max_segment_len`); 0 keeps Whisper's
+    /// own segmentation.
+    pub max_segment_len: u32,
+    /// Let whisper.cpp print progress and decoded segments to stderr
+    /// (`debug_print_whisper`).
+    pub debug_print: bool,
+    /// Filtering applied to the audio first (`denoise`).
+    pub denoise: Denoise,
+    /// Boost quiet audio to `NORMALIZE_PEAK` (`normalize_audio`).
+    pub normalize: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            suppress_non_speech: false,
+            trim_result: true,
+            max_segment_len: 0,
+            debug_print: false,
+            denoise: Denoise::Off,
+            normalize: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    pub fn from_settings(settings: &crate::config::Settings) -> Result<Self> {
+        // whisper.cpp prints the decoded speech itself, bypassing the logs.
+        let privacy = crate::logging::privacy_mode(settings);
+        if settings.debug_print_whisper && privacy {
+            log::warn!("debug_print_whisper is ignored in privacy_mode");
+        }
+        let options = Self {
+            temperature: settings.temperature,
+            temperature_inc: settings.temperature_inc,
+            suppress_non_speech: settings.suppress_non_speech,
+            trim_result: settings.trim_result,
+            max_segment_len: settings.max_segment_len,
+            debug_print: settings.debug_print_whisper && !privacy,
+            denoise: Denoise::from_settings(settings)?,
+            normalize: settings.normalize_audio,
+        };
+        if !(0.0..=1.0).contains(&options.temperature) {
+            anyhow::bail!(
+                "temperature ({}) must be between 0.0 and 1.0",
+                options.temperature
+            );
+        }
+        if !(0.0..=1.0).contains(&options.temperature_inc) {
+            anyhow::bail!(
+                "temperature_inc ({}) must be between 0.0 and 1.0",
+                options.temperature_inc
+            );
+        }
+        if options.max_segment_len > MAX_SEGMENT_LEN {
+            anyhow::bail!(
+                "max_segment_len ({}) must be at most {MAX_SEGMENT_LEN} (0 for no limit)",
+                options.max_segment_len
+            );
+        }
+        Ok(options)
+    }
+
+    /// `denoise` and `normalize_audio` applied to 16kHz mono audio; None when
+    /// it goes to Whisper unchanged.
+    fn prepare(&self, audio: &[f32]) -> Option<Vec<f32>> {
+        let filtered = self.denoise.apply(audio);
+        if !self.normalize {
+            return filtered;
+        }
+        let audio = filtered.as_deref().unwrap_or(audio);
+        normalize_peak(audio).or(filtered)
+    }
+
+    fn apply(&self, params: &mut impl DecodeParams) {
+        params.set_temperature(self.temperature);
+        params.set_temperature_inc(self.temperature_inc);
+        params.set_suppress_nst(self.suppress_non_speech);
+        params.set_print_special(self.debug_print);
+        params.set_print_progress(self.debug_print);
+        params.set_print_realtime(self.debug_print);
+        // Timestamps of the segments printed in realtime.
+        params.set_print_timestamps(self.debug_print);
+        if self.max_segment_len > 0 {
+            // whisper.cpp only honors max_len with token timestamps on;
+            // splitting on words keeps it from cutting words in half.
+            params.set_token_timestamps(true);
+            params.set_max_len(i32::try_from(self.max_segment_len).unwrap_or(i32::MAX));
+            params.set_split_on_word(true);
+        }
+    }
+}
+
+/// Cleanup applied to audio before transcription (`denoise`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denoise {
+    Off,
+    /// Cut hum and rumble below `HIGH_PASS_CUTOFF_HZ`.
+    HighPass,
+    /// Turn down frames near the noise floor.
+    Gate,
+    /// High-pass, then gate.
+    On,
+}
+
+impl Denoise {
+    fn from_settings(settings: &crate::config::Settings) -> Result<Self> {
+        match settings.denoise.trim() {
+            "" | "off" => Ok(Self::Off),
+            "highpass" => Ok(Self::HighPass),
+            "gate" => Ok(Self::Gate),
+            "on" => Ok(Self::On),
+            other => anyhow::bail!("Invalid denoise: {other} (expected off, highpass, gate or on)"),
+        }
+    }
+
+    /// Filter 16kHz mono audio; None when there is nothing to do.
+    fn apply(self, audio: &[f32]) -> Option<Vec<f32>> {
+        match self {
+            Self::Off => None,
+            Self::HighPass => Some(high_pass(audio)),
+            Self::Gate => Some(noise_gate(audio)),
+            Self::On => Some(noise_gate(&high_pass(audio))),
+        }
+    }
+}
+
+/// Below speech's fundamental, above mains hum and desk rumble.
+const HIGH_PASS_CUTOFF_HZ: f32 = 80.0;
+
+/// First-order (RC) high-pass filter at `HIGH_PASS_CUTOFF_HZ` for 16kHz audio.
+fn high_pass(audio: &[f32]) -> Vec<f32> {
+    let rc = 1.0 / (2.0 * std::f32::consts::PI * HIGH_PASS_CUTOFF_HZ);
+    let dt = 1.0 / crate::audio::WHISPER_SAMPLE_RATE as f32;
+    let alpha = rc / (rc + dt);
+    let mut out = Vec::with_capacity(audio.len());
+    let (mut prev_in, mut prev_out) = (0.0, 0.0);
+    for &x in audio {
+        prev_out = alpha * (prev_out + x - prev_in);
+        prev_in = x;
+        out.push(prev_out);
+    }
+    out
+}
+
+/// Gate frames: 20ms at 16kHz.
+const GATE_FRAME: usize = 320;
+/// Frames quieter than this many times the noise floor are gated.
+const GATE_RATIO: f32 = 2.0;
+/// Never gate frames louder than this RMS (-40 dBFS), so a recording with
+/// no quiet frames to estimate the floor from keeps its speech.
+const GATE_MAX_RMS: f32 = 0.01;
+/// Gain for gated frames (-20 dB): quieter, not silent, so soft word
+/// endings aren't chopped.
+const GATE_GAIN: f32 = 0.1;
+
+/// Noise gate: the floor is the 10th-percentile frame level, and frames
+/// close to it are turned down.
+fn noise_gate(audio: &[f32]) -> Vec<f32> {
+    let frame_rms =
+        |frame: &[f32]| (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+    let mut levels: Vec<f32> = audio.chunks(GATE_FRAME).map(frame_rms).collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+    levels.sort_by(f32::total_cmp);
+    let floor = levels[levels.len() / 10];
+    let threshold = (floor * GATE_RATIO).min(GATE_MAX_RMS);
+    audio
+        .chunks(GATE_FRAME)
+        .flat_map(|frame| {
+            let gain = if frame_rms(frame) < threshold {
+                GATE_GAIN
+            } else {
+                1.0
+            };
+            frame.iter().map(move |s| s * gain)
+        })
+        .collect()
+}
+
+/// Peak level quiet audio is boosted to: -3 dBFS.
+const NORMALIZE_PEAK: f32 = 0.708;
+/// Clips peaking below this (-50 dBFS) are essentially silent; boosting them
+/// would only amplify noise.
+const NORMALIZE_MIN_PEAK: f32 = 0.003;
+
+/// Scale audio so its peak reaches `NORMALIZE_PEAK`. None when it is already
+/// that loud or essentially silent.
+fn normalize_peak(audio: &[f32]) -> Option<Vec<f32>> {
+    let peak = audio.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if !(NORMALIZE_MIN_PEAK..NORMALIZE_PEAK).contains(&peak) {
+        return None;
+    }
+    let gain = NORMALIZE_PEAK / peak;
+    Some(audio.iter().map(|s| s * gain).collect())
+}
+
+/// The `FullParams` setters `DecodeOptions` uses, so tests can check what
+/// Whisper receives.
+trait DecodeParams {
+    fn set_temperature(&mut self, temperature: f32);
+    fn set_temperature_inc(&mut self, temperature_inc: f32);
+    fn set_suppress_nst(&mut self, suppress_nst: bool);
+    fn set_token_timestamps(&mut self, token_timestamps: bool);
+    fn set_max_len(&mut self, max_len: i32);
+    fn set_split_on_word(&mut self, split_on_word: bool);
+    fn set_print_special(&mut self, print_special: bool);
+    fn set_print_progress(&mut self, print_progress: bool);
+    fn set_print_realtime(&mut self, print_realtime: bool);
+    fn set_print_timestamps(&mut self, print_timestamps: bool);
+}
+
+impl DecodeParams for FullParams<'_, '_> {
+    fn set_temperature(&mut self, temperature: f32) {
+        FullParams::set_temperature(self, temperature);
+    }
+
+    fn set_temperature_inc(&mut self, temperature_inc: f32) {
+        FullParams::set_temperature_inc(self, temperature_inc);
+    }
+
+    fn set_suppress_nst(&mut self, suppress_nst: bool) {
+        FullParams::set_suppress_nst(self, suppress_nst);
+    }
+
+    fn set_token_timestamps(&mut self, token_timestamps: bool) {
+        FullParams::set_token_timestamps(self, token_timestamps);
+    }
+
+    fn set_max_len(&mut self, max_len: i32) {
+        FullParams::set_max_len(self, max_len);
+    }
+
+    fn set_split_on_word(&mut self, split_on_word: bool) {
+        FullParams::set_split_on_word(self, split_on_word);
+    }
+
+    fn set_print_special(&mut self, print_special: bool) {
+        FullParams::set_print_special(self, print_special);
+    }
+
+    fn set_print_progress(&mut self, print_progress: bool) {
+        FullParams::set_print_progress(self, print_progress);
+    }
+
+    fn set_print_realtime(&mut self, print_realtime: bool) {
+        FullParams::set_print_realtime(self, print_realtime);
+    }
+
+    fn set_print_timestamps(&mut self, print_timestamps: bool) {
+        FullParams::set_print_timestamps(self, print_timestamps);
+    }
+}
+
 pub struct Transcriber {
     ctx: WhisperContext,
     language: String,
+    backend: ComputeBackend,
+    n_threads: usize,
+    numbers_as_digits: bool,
+    decode: DecodeOptions,
+    /// Pin the first language detected with `language = auto`.
+    sticky_language: bool,
+    detected_language: Mutex<Option<String>>,
 }
 
 impl Transcriber {
-    /// Load a Whisper model.
-    pub fn new(model_path: &Path, language: &str) -> Result<Self> {
-        let ctx = WhisperContext::new_with_params(
-            model_path.to_str().unwrap_or(""),
-            WhisperContextParameters::default(),
-        )
-        .context("Failed to load Whisper model")?;
+    /// Load a Whisper model. With `use_gpu`, GPU init is attempted first and
+    /// a failure falls back to CPU rather than erroring out.
+    pub fn new(model_path: &Path, language: &str, use_gpu: bool) -> Result<Self> {
+        check_model_file(model_path)?;
+        if use_gpu && !GPU_SUPPORTED {
+            log::warn!("use_gpu is set but this build has no GPU backend; using CPU");
+        }
+
+        let backend = preferred_backend(use_gpu);
+        let (ctx, backend) = match load_context(model_path, backend) {
+            Ok(ctx) => (ctx, backend),
+            Err(e) if backend == ComputeBackend::Gpu => {
+                log::warn!("GPU initialization failed ({e:#}); falling back to CPU");
+                (
+                    load_context(model_path, ComputeBackend::Cpu)?,
+                    ComputeBackend::Cpu,
+                )
+            }
+            Err(e) => return Err(e),
+        };
+        log::info!("Whisper compute backend: {backend}");
 
         Ok(Self {
             ctx,
             language: language.to_string(),
+            backend,
+            n_threads: resolve_thread_count(0, physical_cores(), available_cores()),
+            numbers_as_digits: false,
+            decode: DecodeOptions::default(),
+            sticky_language: false,
+            detected_language: Mutex::new(None),
         })
     }
 
+    /// Set the decoding and audio options: temperature and its fallback
+    /// step, non-speech suppression, result trimming, segment length,
+    /// whisper.cpp debug printing, `denoise` and `normalize_audio`.
+    pub fn with_decode_options(mut self, decode: DecodeOptions) -> Self {
+        self.decode = decode;
+        self
+    }
+
+    /// Set the `numbers_as_digits` setting. Only applies to English.
+    pub fn with_numbers_as_digits(mut self, enabled: bool) -> Self {
+        self.numbers_as_digits = enabled && self.language.starts_with("en");
+        self
+    }
+
+    /// Set the `auto_language_sticky` setting. Only applies to `language = auto`.
+    pub fn with_sticky_language(mut self, enabled: bool) -> Self {
+        self.sticky_language = enabled && self.language == "auto";
+        self
+    }
+
+    /// Set the `n_threads` setting: 0 means one per physical core, other
+    /// values are clamped to the cores available to this process.
+    pub fn with_threads(mut self, n_threads: u32) -> Self {
+        self.n_threads = resolve_thread_count(n_threads, physical_cores(), available_cores());
+        log::info!("Whisper threads: {}", self.n_threads);
+        self
+    }
+
+    /// Backend the model was actually loaded on.
+    pub fn backend(&self) -> ComputeBackend {
+        self.backend
+    }
+
     /// Transcribe a WAV file and return the text.
     pub fn transcribe(&self, wav_path: &Path) -> Result<String> {
+        self.transcribe_scored(wav_path).map(|t| t.text)
+    }
+
+    /// Transcribe a WAV file, also reporting how confident the model was.
+    pub fn transcribe_scored(&self, wav_path: &Path) -> Result<Transcription> {
         let audio = load_wav_f32(wav_path)?;
+        self.transcribe_audio(&audio)
+    }
+
+    /// Transcribe WAV data from a reader, e.g. audio piped to stdin.
+    pub fn transcribe_reader<R: Read>(&self, reader: R) -> Result<Transcription> {
+        let audio = load_wav_f32_from_reader(reader)?;
+        self.transcribe_audio(&audio)
+    }
+
+    /// Transcribe raw 16kHz mono S16 samples, e.g. a recording still in progress.
+    pub fn transcribe_samples(&self, samples: &[i16]) -> Result<String> {
+        self.transcribe_samples_scored(samples).map(|t| t.text)
+    }
+
+    /// Transcribe raw 16kHz mono S16 samples, also reporting confidence.
+    pub fn transcribe_samples_scored(&self, samples: &[i16]) -> Result<Transcription> {
+        self.transcribe_audio(&samples_to_f32(samples))
+    }
 
+    fn transcribe_audio(&self, audio: &[f32]) -> Result<Transcription> {
+        let pinned = self
+            .detected_language
+            .lock()
+            .ok()
+            .and_then(|detected| detected.clone());
+        let language = clip_language(&self.language, self.sticky_language, pinned.as_deref());
+        let prepared = self.decode.prepare(audio);
+        let audio = prepared.as_deref().unwrap_or(audio);
         let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
-        params.set_language(Some(&self.language));
-        params.set_print_special(false);
-        params.set_print_progress(false);
-        params.set_print_realtime(false);
-        params.set_print_timestamps(false);
+        params.set_language(Some(language));
+        params.set_n_threads(i32::try_from(self.n_threads).unwrap_or(i32::MAX));
+        self.decode.apply(&mut params);
 
         let mut state = self
             .ctx
@@ -42,237 +493,1957 @@ impl Transcriber {
             .context("Failed to create Whisper state")?;
 
         state
-            .full(params, &audio)
+            .full(params, audio)
             .context("Whisper transcription failed")?;
 
-        let num_segments = state
-            .full_n_segments()
-            .context("Failed to get segment count")?;
+        let num_segments = state
+            .full_n_segments()
+            .context("Failed to get segment count")?;
+
+        if self.sticky_language
+            && pinned.is_none()
+            && num_segments > 0
+            && let Some(lang) = state
+                .full_lang_id_from_state()
+                .ok()
+                .and_then(whisper_rs::get_lang_str)
+            && let Ok(mut detected) = self.detected_language.lock()
+        {
+            log::info!("Detected language {lang}; using it for the rest of the session");
+            *detected = Some(lang.to_string());
+        }
+
+        let eot = self.ctx.token_eot();
+        let mut raw_texts = Vec::new();
+        let mut segments = Vec::new();
+        let mut prob_sum = 0.0;
+        let mut token_count = 0;
+        for i in 0..num_segments {
+            let segment_text = state.full_get_segment_text(i).unwrap_or_default();
+            let mut segment_sum = 0.0;
+            let mut segment_count = 0;
+            for j in 0..state.full_n_tokens(i).unwrap_or(0) {
+                // Timestamps and other special tokens sort after end-of-text.
+                if state.full_get_token_id(i, j).is_ok_and(|id| id < eot)
+                    && let Ok(p) = state.full_get_token_prob(i, j)
+                {
+                    segment_sum += p;
+                    segment_count += 1;
+                }
+            }
+            prob_sum += segment_sum;
+            token_count += segment_count;
+            // Whisper timestamps are in centiseconds.
+            segments.push(Segment {
+                start_ms: centis_to_ms(state.full_get_segment_t0(i).unwrap_or(0)),
+                end_ms: centis_to_ms(state.full_get_segment_t1(i).unwrap_or(0)),
+                text: segment_text_for(&segment_text, self.decode.trim_result),
+                confidence: mean(segment_sum, segment_count),
+            });
+            raw_texts.push(segment_text);
+        }
+
+        let mut text = join_segments(
+            &raw_texts,
+            self.decode.suppress_non_speech,
+            self.decode.trim_result,
+        );
+        if self.numbers_as_digits {
+            text = numbers_to_digits(&text);
+        }
+
+        Ok(Transcription {
+            text,
+            confidence: mean(prob_sum, token_count),
+            segments,
+        })
+    }
+}
+
+/// Language to decode a clip with: the configured one, or with
+/// `auto_language_sticky` the language pinned from an earlier clip.
+fn clip_language<'a>(configured: &'a str, sticky: bool, pinned: Option<&'a str>) -> &'a str {
+    match pinned {
+        Some(lang) if sticky => lang,
+        _ => configured,
+    }
+}
+
+fn mean(sum: f32, count: usize) -> f32 {
+    if count == 0 { 0.0 } else { sum / count as f32 }
+}
+
+fn centis_to_ms(centis: i64) -> u64 {
+    u64::try_from(centis).unwrap_or(0).saturating_mul(10)
+}
+
+/// Threads to run Whisper with for a requested count (0 = auto).
+fn resolve_thread_count(requested: u32, physical: usize, available: usize) -> usize {
+    let available = available.max(1);
+    match requested {
+        0 => physical.clamp(1, available),
+        n => (n as usize).min(available),
+    }
+}
+
+/// Logical CPUs this process may run on.
+fn available_cores() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Physical core count from /proc/cpuinfo, falling back to logical CPUs.
+fn physical_cores() -> usize {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|info| count_physical_cores(&info))
+        .unwrap_or_else(available_cores)
+}
+
+/// Count distinct (physical id, core id) pairs in /proc/cpuinfo contents.
+fn count_physical_cores(cpuinfo: &str) -> Option<usize> {
+    let mut cores = std::collections::HashSet::new();
+    for block in cpuinfo.split("\n\n") {
+        let field = |name: &str| {
+            block.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                (key.trim() == name).then(|| value.trim().to_string())
+            })
+        };
+        if let Some(core) = field("core id") {
+            cores.insert((field("physical id").unwrap_or_default(), core));
+        }
+    }
+    (!cores.is_empty()).then_some(cores.len())
+}
+
+/// First bytes of a whisper.cpp model: the ggml magic 0x67676d6c, little-endian.
+const GGML_MAGIC: [u8; 4] = *b"lmgg";
+
+/// Catch model files whisper.cpp would reject with a cryptic error: not a
+/// ggml model at all, or a catalog model cut short (e.g. an old interrupted
+/// download). A missing file is left for whisper.cpp to report.
+fn check_model_file(model_path: &Path) -> Result<()> {
+    let Ok(mut file) = std::fs::File::open(model_path) else {
+        return Ok(());
+    };
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let fix = "delete it and restart escucha to download it again, \
+               or point `model` at a working file";
+    let mut magic = [0u8; 4];
+    if file.read_exact(&mut magic).is_err() || magic != GGML_MAGIC {
+        anyhow::bail!(
+            "Model file {} is not a whisper.cpp ggml model (corrupt or incomplete download); {fix}",
+            model_path.display()
+        );
+    }
+    let name = model_path.to_string_lossy();
+    if let Some(model) = MODELS.iter().find(|m| m.name == model_base_name(&name))
+        && size < model.size_bytes / 2
+    {
+        anyhow::bail!(
+            "Model file {} is truncated ({} of about {}); {fix}",
+            model_path.display(),
+            format_size(size),
+            format_size(model.size_bytes)
+        );
+    }
+    Ok(())
+}
+
+fn load_context(model_path: &Path, backend: ComputeBackend) -> Result<WhisperContext> {
+    let mut params = WhisperContextParameters::default();
+    params.use_gpu(backend == ComputeBackend::Gpu);
+    WhisperContext::new_with_params(model_path.to_str().unwrap_or(""), params)
+        .context("Failed to load Whisper model")
+}
+
+/// Scale 16-bit PCM samples to -1.0..1.0.
+fn samples_to_f32(samples: &[i16]) -> Vec<f32> {
+    samples.iter().map(|&s| s as f32 / 32768.0).collect()
+}
+
+/// Load a WAV file as f32 samples at 16kHz mono, converting other rates and
+/// channel counts.
+fn load_wav_f32(path: &Path) -> Result<Vec<f32>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    load_wav_f32_from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to read WAV file: {}", path.display()))
+}
+
+/// Load WAV data from any reader, e.g. stdin, as f32 samples at 16kHz mono.
+pub fn load_wav_f32_from_reader<R: Read>(reader: R) -> Result<Vec<f32>> {
+    let reader = hound::WavReader::new(reader).context("Invalid WAV data")?;
+
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let scale = int_sample_scale(spec.bits_per_sample).context("Unsupported WAV data")?;
+            // hound sign-extends packed 24-bit samples into i32, so every
+            // width maps onto [-1, 1) by dividing by 2^(bits - 1).
+            reader
+                .into_samples::<i32>()
+                .filter_map(|s| s.ok())
+                .map(|s| (s as f64 / scale) as f32)
+                .collect()
+        }
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .filter_map(|s| s.ok())
+            .collect(),
+    };
+
+    let mono = crate::audio::downmix(&samples, spec.channels);
+    Ok(crate::audio::resample(
+        &mono,
+        spec.sample_rate,
+        crate::audio::WHISPER_SAMPLE_RATE,
+    ))
+}
+
+/// Full-scale magnitude of a signed integer PCM sample of the given width.
+fn int_sample_scale(bits_per_sample: u16) -> Result<f64> {
+    match bits_per_sample {
+        8 | 16 | 24 | 32 => Ok(f64::from(1u32 << (bits_per_sample - 1))),
+        other => anyhow::bail!("{other}-bit integer PCM is not supported"),
+    }
+}
+
+/// Text kept for one segment: Whisper starts most segments with a space,
+/// which is dropped unless `trim_result` is off.
+fn segment_text_for(raw: &str, trim: bool) -> String {
+    if trim {
+        raw.trim().to_string()
+    } else {
+        raw.to_string()
+    }
+}
+
+/// Join raw segment texts into the transcription. Segments are concatenated
+/// as Whisper wrote them, so a word split across segments stays whole;
+/// with `trim`, the result is then trimmed and runs of spaces collapsed.
+fn join_segments(raw: &[String], strip: bool, trim: bool) -> String {
+    let joined = raw.concat();
+    let joined = if strip {
+        strip_annotations(&joined)
+    } else {
+        joined
+    };
+    if trim {
+        normalize_whitespace(&joined)
+    } else {
+        joined
+    }
+}
+
+/// Normalize whitespace: trim and collapse multiple spaces.
+pub fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Remove non-speech annotations Whisper writes in brackets or parentheses,
+/// e.g. `[music]`, `(applause)` or `[BLANK_AUDIO]`, plus `♪` markers.
+fn strip_annotations(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut closer = None;
+    for c in text.chars() {
+        match closer {
+            Some(close) if c == close => closer = None,
+            Some(_) => {}
+            None => match c {
+                '[' => closer = Some(']'),
+                '(' => closer = Some(')'),
+                '♪' => {}
+                _ => out.push(c),
+            },
+        }
+    }
+    out
+}
+
+/// Hesitation sounds Whisper transcribes on their own for short or empty
+/// clips, matched case-insensitively.
+const FILLERS: &[&str] = &[
+    "ah", "eh", "er", "erm", "hm", "hmm", "huh", "mhm", "mm", "mm-hmm", "mmm", "uh", "uh-huh",
+    "uhm", "um", "umm",
+];
+
+/// Drops transcriptions with nothing worth pasting (`min_chars`,
+/// `drop_fillers`).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextFilter {
+    /// Fewest letters/digits a transcription needs; 0 disables.
+    pub min_chars: usize,
+    /// Drop transcriptions that are only punctuation and filler words.
+    pub drop_fillers: bool,
+}
+
+impl TextFilter {
+    pub fn from_settings(settings: &crate::config::Settings) -> Self {
+        Self {
+            min_chars: settings.min_chars as usize,
+            drop_fillers: settings.drop_fillers,
+        }
+    }
+
+    /// Whether `text` should be discarded instead of pasted.
+    pub fn rejects(&self, text: &str) -> bool {
+        let chars = text.chars().filter(|c| c.is_alphanumeric()).count();
+        if chars < self.min_chars {
+            return true;
+        }
+        self.drop_fillers
+            && text
+                .split_whitespace()
+                .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+                .filter(|word| !word.is_empty())
+                .all(|word| FILLERS.iter().any(|f| f.eq_ignore_ascii_case(word)))
+    }
+}
+
+/// Value of an English number word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberWord {
+    /// zero through nineteen, and the tens.
+    Small(u64),
+    Hundred,
+    /// thousand, million, billion.
+    Scale(u64),
+    /// "and", as in "one hundred and five".
+    And,
+}
+
+fn number_word(word: &str) -> Option<NumberWord> {
+    const SMALL: [&str; 20] = [
+        "zero",
+        "one",
+        "two",
+        "three",
+        "four",
+        "five",
+        "six",
+        "seven",
+        "eight",
+        "nine",
+        "ten",
+        "eleven",
+        "twelve",
+        "thirteen",
+        "fourteen",
+        "fifteen",
+        "sixteen",
+        "seventeen",
+        "eighteen",
+        "nineteen",
+    ];
+    const TENS: [&str; 8] = [
+        "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+    ];
+    let word = word.to_ascii_lowercase();
+    if let Some(n) = SMALL.iter().position(|w| *w == word) {
+        return Some(NumberWord::Small(n as u64));
+    }
+    if let Some(n) = TENS.iter().position(|w| *w == word) {
+        return Some(NumberWord::Small(20 + 10 * n as u64));
+    }
+    match word.as_str() {
+        "hundred" => Some(NumberWord::Hundred),
+        "thousand" => Some(NumberWord::Scale(1_000)),
+        "million" => Some(NumberWord::Scale(1_000_000)),
+        "billion" => Some(NumberWord::Scale(1_000_000_000)),
+        "and" => Some(NumberWord::And),
+        _ => None,
+    }
+}
+
+/// A word of the transcription with its trailing punctuation split off.
+struct Word<'a> {
+    core: &'a str,
+    punct: &'a str,
+    /// Byte offset of `core` in the text.
+    start: usize,
+}
+
+impl Word<'_> {
+    /// Byte offset just past the word's punctuation.
+    fn end(&self) -> usize {
+        self.start + self.core.len() + self.punct.len()
+    }
+}
+
+fn split_words(text: &str) -> Vec<Word<'_>> {
+    // Tokens and parts are slices of `text`, so pointers give their offsets.
+    let offset = |s: &str| s.as_ptr() as usize - text.as_ptr() as usize;
+    let mut words = Vec::new();
+    for token in text.split_whitespace() {
+        let core = token.trim_end_matches(|c: char| c.is_ascii_punctuation());
+        let punct = &token[core.len()..];
+        let parts: Vec<&str> = core.split('-').collect();
+        // "twenty-five" is two number words; other hyphenated words stay whole.
+        if parts.len() > 1 && parts.iter().all(|p| number_word(p).is_some()) {
+            let last = parts.len() - 1;
+            for (i, part) in parts.into_iter().enumerate() {
+                let punct = if i == last { punct } else { "" };
+                words.push(Word {
+                    core: part,
+                    punct,
+                    start: offset(part),
+                });
+            }
+        } else {
+            words.push(Word {
+                core,
+                punct,
+                start: offset(core),
+            });
+        }
+    }
+    words
+}
+
+/// Parse one number from the start of `words`, returning its value and the
+/// number of words it used. Stops at the first word that can't extend it, so
+/// "one two" parses as 1 and leaves "two".
+fn parse_number(words: &[Word<'_>]) -> Option<(u64, usize)> {
+    let mut total = 0;
+    let mut current: u64 = 0;
+    let mut started = false;
+    // True at the start and right after a scale word.
+    let mut fresh = true;
+    let mut last_scale = u64::MAX;
+    let mut used = 0;
+
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 && !words[i - 1].punct.is_empty() {
+            break;
+        }
+        match number_word(word.core) {
+            Some(NumberWord::Small(0)) => {
+                if !started {
+                    return Some((0, 1));
+                }
+                break;
+            }
+            Some(NumberWord::Small(n)) => {
+                let last = current % 100;
+                let after_tens = n < 10 && last >= 20 && last.is_multiple_of(10);
+                if !(after_tens || (last == 0 && (fresh || current > 0))) {
+                    break;
+                }
+                current += n;
+                started = true;
+                fresh = false;
+            }
+            Some(NumberWord::Hundred) if (1..100).contains(&current) => current *= 100,
+            Some(NumberWord::Scale(scale)) if current > 0 && scale < last_scale => {
+                total += current * scale;
+                current = 0;
+                fresh = true;
+                last_scale = scale;
+            }
+            Some(NumberWord::And)
+                if total + current >= 100
+                    && word.punct.is_empty()
+                    && words
+                        .get(i + 1)
+                        .and_then(|w| number_word(w.core))
+                        .is_some_and(|w| matches!(w, NumberWord::Small(n) if n > 0)) =>
+            {
+                continue;
+            }
+            _ => break,
+        }
+        used = i + 1;
+    }
+
+    started.then_some((total + current, used))
+}
+
+/// Rewrite spoken English numbers as digits: "one hundred twenty three" →
+/// "123", "twenty-five" → "25", and year-style pairs such as "twenty twenty
+/// five" → "2025". A lone "one" is left alone since it is usually a pronoun
+/// ("no one", "one of them"); in a sequence ("one two three") it is converted.
+/// Only the number words are replaced; all other text, including whitespace
+/// and line breaks, is kept byte for byte.
+pub fn numbers_to_digits(text: &str) -> String {
+    let words = split_words(text);
+    let mut out = String::with_capacity(text.len());
+    // End of the text already copied to `out`.
+    let mut copied = 0;
+    let mut prev_was_number = false;
+    let mut i = 0;
+    while i < words.len() {
+        let Some((first, used)) = parse_number(&words[i..]) else {
+            prev_was_number = false;
+            i += 1;
+            continue;
+        };
+        let next_is_number =
+            words[i].punct.is_empty() && parse_number(&words[i + used..]).is_some();
+        if used == 1
+            && words[i].core.eq_ignore_ascii_case("one")
+            && !prev_was_number
+            && !next_is_number
+        {
+            i += 1;
+            continue;
+        }
+        let mut value = first.to_string();
+        let mut end = i + used;
+        // Years: "nineteen eighty four", "twenty twenty five".
+        if (11..=20).contains(&first)
+            && words[end - 1].punct.is_empty()
+            && let Some((second, used2)) = parse_number(&words[end..])
+            && (10..100).contains(&second)
+        {
+            value = format!("{first}{second}");
+            end += used2;
+        }
+        let last = &words[end - 1];
+        out.push_str(&text[copied..words[i].start]);
+        out.push_str(&value);
+        out.push_str(last.punct);
+        copied = last.end();
+        prev_was_number = last.punct.is_empty();
+        i = end;
+    }
+    out.push_str(&text[copied..]);
+    out
+}
+
+/// Get the default model directory path.
+pub fn default_model_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("~/.local/share"))
+        .join("escucha")
+        .join("models")
+}
+
+/// A `[model_aliases]` entry: a friendly name for a model file that isn't
+/// in the catalog, such as a fine-tuned or renamed model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelAlias {
+    pub path: PathBuf,
+    /// Where to download the file when it is missing. Without one, the
+    /// alias behaves like `model` set to `path`.
+    pub url: Option<String>,
+}
+
+impl ModelAlias {
+    /// Parse an alias target: a download URL (saved under its file name in
+    /// the model dir), a model file (a path, or a file name in the model
+    /// dir), or a model file followed by its download URL.
+    pub fn parse(value: &str) -> Result<Self> {
+        let is_url = |s: &str| s.starts_with("https://") || s.starts_with("http://");
+        let (file, url) = match value.split_whitespace().collect::<Vec<_>>()[..] {
+            [url] if is_url(url) => {
+                let file = url.split(['?', '#']).next().unwrap_or(url);
+                (file.rsplit('/').next().unwrap_or_default(), Some(url))
+            }
+            [file] if !is_url(file) => (file, None),
+            [file, url] if !is_url(file) && is_url(url) => (file, Some(url)),
+            _ => anyhow::bail!(
+                "expected a model file, a download URL, or a file followed by its URL"
+            ),
+        };
+        if file.is_empty() {
+            anyhow::bail!("{value} doesn't end in a file name");
+        }
+        let path = if file.contains('/') {
+            crate::logging::expand_home(file)
+        } else {
+            default_model_dir().join(file)
+        };
+        Ok(Self {
+            path,
+            url: url.map(str::to_string),
+        })
+    }
+}
+
+/// `[model_aliases]` from the config, set by `set_model_aliases`.
+static MODEL_ALIASES: RwLock<Vec<(String, ModelAlias)>> = RwLock::new(Vec::new());
+
+/// Make `model = <alias>` (and `fallback_model`, `--benchmark --models`)
+/// resolve through `[model_aliases]`. Nothing changes if an entry is invalid.
+/// Called again for every service run, so reloads pick up edited aliases.
+pub fn set_model_aliases(aliases: &[(String, String)]) -> Result<()> {
+    let parsed = aliases
+        .iter()
+        .map(|(name, value)| {
+            ModelAlias::parse(value)
+                .map(|alias| (name.clone(), alias))
+                .with_context(|| format!("Invalid model alias {name} = {value}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Ok(mut configured) = MODEL_ALIASES.write() {
+        *configured = parsed;
+    }
+    Ok(())
+}
+
+fn model_alias(model: &str) -> Option<ModelAlias> {
+    let aliases = MODEL_ALIASES.read().ok()?;
+    aliases
+        .iter()
+        .find(|(name, _)| name == model)
+        .map(|(_, alias)| alias.clone())
+}
+
+/// Get the path for a model: its `[model_aliases]` file, `model` used
+/// verbatim when it is a path (absolute, or containing a `/`), else
+/// `ggml-<name>.bin` in the model dir.
+pub fn model_path(model: &str) -> PathBuf {
+    if let Some(alias) = model_alias(model) {
+        return alias.path;
+    }
+    explicit_model_path(model)
+        .unwrap_or_else(|| default_model_dir().join(format!("ggml-{model}.bin")))
+}
+
+/// `model` as a file path, if it names one rather than a model that can be
+/// downloaded: a path (a leading `~/` is expanded) or an alias without URL.
+pub fn explicit_model_path(model: &str) -> Option<PathBuf> {
+    if let Some(alias) = model_alias(model) {
+        return alias.url.is_none().then_some(alias.path);
+    }
+    model
+        .contains('/')
+        .then(|| crate::logging::expand_home(model))
+}
+
+/// Whether `ensure_model` would have to download `model`: a catalog model
+/// not yet in the model dir. Paths are never downloaded.
+pub fn needs_download(model: &str) -> bool {
+    !model.is_empty() && explicit_model_path(model).is_none() && !model_path(model).exists()
+}
+
+/// Check a `model` value before saving it: a catalog model, a
+/// `[model_aliases]` name, or the path of an existing model file.
+pub fn check_model_setting(model: &str) -> Result<()> {
+    if MODELS.iter().any(|m| m.name == model) || model_alias(model).is_some() {
+        return Ok(());
+    }
+    match explicit_model_path(model) {
+        Some(path) if path.is_file() => Ok(()),
+        Some(path) => anyhow::bail!("Model file not found: {}", path.display()),
+        None => anyhow::bail!(
+            "Unknown model {model:?}; pick one from `escucha --list-models`, \
+             a [model_aliases] name or a path to a ggml file"
+        ),
+    }
+}
+
+/// Model names to offer in the settings UI: the catalog, then the
+/// configured `[model_aliases]`.
+pub fn model_choices() -> Vec<String> {
+    let mut names: Vec<String> = MODELS.iter().map(|m| m.name.to_string()).collect();
+    if let Ok(aliases) = MODEL_ALIASES.read() {
+        names.extend(aliases.iter().map(|(name, _)| name.clone()));
+    }
+    names
+}
+
+/// Catalog-style name of a model: the setting itself, or for a path the
+/// file stem without the `ggml-` prefix (`/nas/ggml-base.en.bin` -> `base.en`).
+fn model_base_name(model: &str) -> &str {
+    if !model.contains('/') {
+        return model;
+    }
+    let file = model.rsplit('/').next().unwrap_or(model);
+    let stem = file.strip_suffix(".bin").unwrap_or(file);
+    stem.strip_prefix("ggml-").unwrap_or(stem)
+}
+
+/// A model from the whisper.cpp ggml catalog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelInfo {
+    pub name: &'static str,
+    /// Approximate download size.
+    pub size_bytes: u64,
+}
+
+/// Models known to download from `HF_BASE_URL`, smallest first.
+pub const MODELS: &[ModelInfo] = &[
+    ModelInfo {
+        name: "tiny.en",
+        size_bytes: 77_700_000,
+    },
+    ModelInfo {
+        name: "tiny",
+        size_bytes: 77_700_000,
+    },
+    ModelInfo {
+        name: "base.en",
+        size_bytes: 148_000_000,
+    },
+    ModelInfo {
+        name: "base",
+        size_bytes: 148_000_000,
+    },
+    ModelInfo {
+        name: "small.en",
+        size_bytes: 488_000_000,
+    },
+    ModelInfo {
+        name: "small",
+        size_bytes: 488_000_000,
+    },
+    ModelInfo {
+        name: "medium.en",
+        size_bytes: 1_530_000_000,
+    },
+    ModelInfo {
+        name: "medium",
+        size_bytes: 1_530_000_000,
+    },
+    ModelInfo {
+        name: "large-v3-turbo",
+        size_bytes: 1_620_000_000,
+    },
+    ModelInfo {
+        name: "large-v3",
+        size_bytes: 3_100_000_000,
+    },
+];
+
+/// The model catalog as a table, marking models for which `downloaded` is true.
+fn format_model_list(downloaded: impl Fn(&str) -> bool) -> String {
+    let mut out = String::new();
+    for model in MODELS {
+        let marker = if downloaded(model.name) { '*' } else { ' ' };
+        let kind = if is_english_only(model.name) {
+            "English-only"
+        } else {
+            "multilingual"
+        };
+        out.push_str(&format!(
+            "  {marker} {:<16} {:>8}  {kind}\n",
+            model.name,
+            format_size(model.size_bytes)
+        ));
+    }
+    out
+}
+
+/// Load the configured model (downloading it if needed) for one-shot CLI
+/// modes like `--transcribe`.
+pub fn load_transcriber(settings: &crate::config::Settings) -> Result<Transcriber> {
+    let model_path = ensure_model(&settings.model, &DownloadOptions::from_settings(settings))?;
+    Ok(
+        Transcriber::new(&model_path, &settings.language, settings.use_gpu)?
+            .with_threads(settings.n_threads)
+            .with_numbers_as_digits(settings.numbers_as_digits)
+            .with_decode_options(DecodeOptions::from_settings(settings)?),
+    )
+}
+
+/// Transcribe a WAV file, or stdin for `-`, with the configured model and
+/// print it in `format`, for `--transcribe`.
+pub fn transcribe_cli(
+    input: &str,
+    format: crate::formats::OutputFormat,
+    settings: &crate::config::Settings,
+) -> Result<()> {
+    let transcriber = load_transcriber(settings)?;
+
+    let transcription = if input == "-" {
+        transcriber
+            .transcribe_reader(std::io::stdin().lock())
+            .context("Failed to transcribe audio from stdin")?
+    } else {
+        transcriber.transcribe_scored(Path::new(input))?
+    };
+    print!("{}", crate::formats::render(format, &transcription)?);
+    Ok(())
+}
+
+/// Print the model catalog for `--list-models`.
+pub fn list_models_cli() {
+    println!(
+        "Whisper models (* = downloaded to {}):",
+        default_model_dir().display()
+    );
+    print!("{}", format_model_list(|name| model_path(name).exists()));
+}
+
+/// Whether a model is English-only (`base.en`, `small.en-q5_1`, ...),
+/// judged by its file name so aliases count too.
+fn is_english_only(model_name: &str) -> bool {
+    let path = model_path(model_name);
+    let path = path.to_string_lossy();
+    let model_name = model_base_name(&path);
+    model_name.ends_with(".en") || model_name.contains(".en-")
+}
+
+/// Warning for an English-only model paired with another language, which
+/// Whisper silently transcribes poorly. `None` when the pair is fine.
+pub fn language_mismatch(model_name: &str, language: &str) -> Option<String> {
+    let language = language.trim();
+    if !is_english_only(model_name)
+        || language.is_empty()
+        || language.eq_ignore_ascii_case("en")
+        || language.eq_ignore_ascii_case("auto")
+    {
+        return None;
+    }
+    let path = model_path(model_name);
+    let multilingual = model_base_name(&path.to_string_lossy()).replacen(".en", "", 1);
+    Some(format!(
+        "Model {model_name} is English-only but language is {language}; \
+         use {multilingual} instead"
+    ))
+}
+
+/// Download URL for a model: its alias's URL, else the catalog's.
+fn model_url(model_name: &str) -> String {
+    model_alias(model_name)
+        .and_then(|alias| alias.url)
+        .unwrap_or_else(|| format!("{HF_BASE_URL}/ggml-{model_name}.bin"))
+}
+
+/// How model downloads are confirmed, retried and cancelled.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Retries after the first attempt.
+    pub retries: u32,
+    /// Delay before the first retry; doubled for each later one.
+    pub backoff: Duration,
+    /// Ask before downloading when running in a terminal.
+    pub confirm: bool,
+    /// Never download: a missing model is an error (`offline`).
+    pub offline: bool,
+    /// Abort the download when set, e.g. the service's shutdown flag.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            backoff: Duration::from_secs(2),
+            confirm: false,
+            offline: false,
+            cancel: None,
+        }
+    }
+}
+
+impl DownloadOptions {
+    pub fn from_settings(settings: &crate::config::Settings) -> Self {
+        Self {
+            retries: settings.download_retries,
+            backoff: Duration::from_millis(u64::from(settings.download_retry_delay_ms)),
+            confirm: settings.confirm_download,
+            offline: settings.offline,
+            cancel: None,
+        }
+    }
+
+    pub fn with_cancel(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Sleep for `delay`, waking early if cancelled. Returns false then.
+    fn sleep(&self, delay: Duration) -> bool {
+        let deadline = std::time::Instant::now() + delay;
+        loop {
+            if self.cancelled() {
+                return false;
+            }
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                return true;
+            }
+            std::thread::sleep(left.min(DOWNLOAD_POLL));
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based): the backoff doubled per
+    /// retry, capped at `MAX_DOWNLOAD_BACKOFF`.
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.backoff
+            .checked_mul(factor)
+            .unwrap_or(MAX_DOWNLOAD_BACKOFF)
+            .min(MAX_DOWNLOAD_BACKOFF)
+    }
+}
+
+const MAX_DOWNLOAD_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often a running download checks for cancellation.
+const DOWNLOAD_POLL: Duration = Duration::from_millis(100);
+
+/// curl exit code when the server doesn't support resuming (range requests).
+const CURL_RANGE_ERROR: i32 = 33;
+
+/// Ensure the model exists locally, downloading it if needed.
+/// Returns the path to the model file.
+pub fn ensure_model(model_name: &str, options: &DownloadOptions) -> Result<PathBuf> {
+    ensure_model_with_status(model_name, options, &mut |status| log::info!("{status}"))
+}
+
+/// Ensure the model exists, with a progress callback for GUI use.
+/// Interrupted downloads are resumed from the partial `.part` file on each
+/// retry; the partial file is only deleted once all retries are exhausted,
+/// or when the download is cancelled.
+pub fn ensure_model_with_status(
+    model_name: &str,
+    options: &DownloadOptions,
+    on_status: &mut dyn FnMut(&str),
+) -> Result<PathBuf> {
+    let path = model_path(model_name);
+    if path.exists() {
+        return Ok(path);
+    }
+    if explicit_model_path(model_name).is_some() {
+        anyhow::bail!(
+            "Model file {} not found (model is set to a path or an alias without a URL, \
+             so nothing is downloaded)",
+            path.display()
+        );
+    }
+
+    if options.offline {
+        anyhow::bail!(
+            "Model {model_name} not found and offline is set; place the model at {}",
+            path.display()
+        );
+    }
+
+    if options.cancelled() {
+        anyhow::bail!("Download of model '{model_name}' cancelled");
+    }
+
+    let url = model_url(model_name);
+    if which::which("curl").is_err() {
+        anyhow::bail!(no_curl_message(model_name, &path, &url));
+    }
+    let size = remote_size(&url)
+        .map(|bytes| format!(" ({})", format_size(bytes)))
+        .unwrap_or_default();
+    if options.confirm && std::io::stdin().is_terminal() && !confirm_download(model_name, &size)? {
+        anyhow::bail!("Download of model '{model_name}' declined");
+    }
+    log::info!("Downloading Whisper model '{model_name}'{size} from {url}");
+    on_status(&format!("Downloading model '{model_name}'{size}..."));
+
+    let dir = path
+        .parent()
+        .map_or_else(default_model_dir, Path::to_path_buf);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create model dir {}", dir.display()))?;
+
+    let tmp_path = path.with_extension("bin.part");
+    let attempts = options.retries.saturating_add(1);
+    let mut attempt = 1;
+    loop {
+        match download(&url, &tmp_path, options) {
+            Ok(()) => break,
+            Err(_) if options.cancelled() => {
+                let _ = std::fs::remove_file(&tmp_path);
+                log::info!("Download of model '{model_name}' cancelled");
+                anyhow::bail!("Download of model '{model_name}' cancelled");
+            }
+            Err(e) if attempt < attempts => {
+                let delay = options.delay(attempt);
+                log::warn!("Model download failed: {e:#}");
+                on_status(&format!(
+                    "Download interrupted; retrying in {:.1}s (attempt {} of {attempts})...",
+                    delay.as_secs_f32(),
+                    attempt + 1
+                ));
+                if !options.sleep(delay) {
+                    let _ = std::fs::remove_file(&tmp_path);
+                    anyhow::bail!("Download of model '{model_name}' cancelled");
+                }
+                attempt += 1;
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&tmp_path);
+                return Err(e.context(format!("Failed to download model from {url}")));
+            }
+        }
+    }
+
+    // Verify we got something reasonable (> 1MB)
+    let metadata = std::fs::metadata(&tmp_path).context("Downloaded file not found")?;
+    if metadata.len() < 1_000_000 {
+        let _ = std::fs::remove_file(&tmp_path);
+        anyhow::bail!(
+            "Downloaded file too small ({}B) - likely a download error",
+            metadata.len()
+        );
+    }
+
+    std::fs::rename(&tmp_path, &path).context("Failed to move downloaded model into place")?;
+
+    log::info!("Model downloaded to {}", path.display());
+    on_status("Model downloaded");
+
+    // Flush any buffered output
+    let _ = std::io::stdout().flush();
+
+    Ok(path)
+}
+
+/// Error for a missing model that can't be downloaded because curl isn't
+/// installed, naming where the file would have to go.
+fn no_curl_message(model_name: &str, path: &Path, url: &str) -> String {
+    format!(
+        "Model {model_name} not found and curl is unavailable; install curl or place the model at {} (download it from {url})",
+        path.display()
+    )
+}
+
+/// Ask on the terminal whether to go ahead with a download.
+fn confirm_download(model_name: &str, size: &str) -> Result<bool> {
+    eprint!("About to download Whisper model '{model_name}'{size}. Continue? [y/N] ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Size of the file at `url` from a HEAD request, if the server reports it.
+fn remote_size(url: &str) -> Option<u64> {
+    let output = std::process::Command::new("curl")
+        .args(["-sIL", "--fail", "--max-time", "10", url])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_content_length(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// `Content-Length` of the final response in curl's header dump (`-IL`
+/// prints the headers of every redirect hop).
+fn parse_content_length(headers: &str) -> Option<u64> {
+    headers
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse().ok())?
+        })
+        .next_back()
+}
+
+/// Human-readable size, e.g. "1.5 GB" or "142 MB".
+pub fn format_size(bytes: u64) -> String {
+    const GB: f64 = 1_000_000_000.0;
+    const MB: f64 = 1_000_000.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else {
+        format!("{:.0} MB", bytes / MB)
+    }
+}
+
+/// One curl attempt, continuing `tmp_path` if a previous attempt left it and
+/// killing curl if the download is cancelled.
+fn download(url: &str, tmp_path: &Path, options: &DownloadOptions) -> Result<()> {
+    let mut child = std::process::Command::new("curl")
+        .args(["-L", "--fail", "--progress-bar", "-C", "-", "-o"])
+        .arg(tmp_path)
+        .arg(url)
+        .spawn()
+        .context("Failed to run curl. Is curl installed?")?;
+    let status = loop {
+        if let Some(status) = child.try_wait().context("Failed to wait for curl")? {
+            break status;
+        }
+        if options.cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("Download cancelled");
+        }
+        std::thread::sleep(DOWNLOAD_POLL);
+    };
+
+    if status.code() == Some(CURL_RANGE_ERROR) {
+        // The server can't resume; start over on the next attempt.
+        let _ = std::fs::remove_file(tmp_path);
+    }
+    if !status.success() {
+        anyhow::bail!("curl exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_content_length() {
+        let headers = "HTTP/2 302\r\nlocation: https://cdn.example/x\r\ncontent-length: 1142\r\n\r\n\
+                       HTTP/2 200\r\nContent-Type: application/octet-stream\r\n\
+                       Content-Length: 147951465\r\n\r\n";
+        assert_eq!(parse_content_length(headers), Some(147_951_465));
+        assert_eq!(parse_content_length("HTTP/2 200\r\n\r\n"), None);
+        assert_eq!(parse_content_length(""), None);
+    }
+
+    #[test]
+    fn test_no_curl_message() {
+        let path = model_path("base.en");
+        let message = no_curl_message("base.en", &path, &model_url("base.en"));
+        assert!(message.starts_with("Model base.en not found and curl is unavailable"));
+        assert!(message.contains(&path.display().to_string()));
+        assert!(message.contains("ggml-base.en.bin"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(1_550_000_000), "1.6 GB");
+        assert_eq!(format_size(3_095_033_483), "3.1 GB");
+        assert_eq!(format_size(147_951_465), "148 MB");
+        assert_eq!(format_size(0), "0 MB");
+    }
+
+    #[test]
+    fn test_download_retry_backoff_schedule() {
+        let retry = DownloadOptions {
+            retries: 8,
+            backoff: Duration::from_secs(2),
+            ..DownloadOptions::default()
+        };
+        let delays: Vec<u64> = (1..=7).map(|n| retry.delay(n).as_secs()).collect();
+        assert_eq!(delays, vec![2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(retry.delay(40), MAX_DOWNLOAD_BACKOFF);
+    }
+
+    #[test]
+    fn test_offline_missing_model_errors_without_download() {
+        let options = DownloadOptions {
+            offline: true,
+            ..DownloadOptions::default()
+        };
+        let mut statuses = Vec::new();
+        let err = ensure_model_with_status("no-such-model", &options, &mut |status| {
+            statuses.push(status.to_string())
+        })
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("offline"));
+        assert!(message.contains(&model_path("no-such-model").display().to_string()));
+        // No download was started, so no progress was reported.
+        assert!(statuses.is_empty());
+        assert!(
+            !model_path("no-such-model")
+                .with_extension("bin.part")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_cancelled_download_stops_before_starting() {
+        let cancel = Arc::new(AtomicBool::new(true));
+        let options = DownloadOptions::default().with_cancel(cancel);
+        let mut statuses = Vec::new();
+        let err = ensure_model_with_status("no-such-model", &options, &mut |status| {
+            statuses.push(status.to_string())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+        assert!(statuses.is_empty());
+        assert!(
+            !model_path("no-such-model")
+                .with_extension("bin.part")
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_download_sleep_wakes_on_cancel() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let options = DownloadOptions::default().with_cancel(cancel.clone());
+        assert!(options.sleep(Duration::ZERO));
+        cancel.store(true, Ordering::Relaxed);
+        let started = std::time::Instant::now();
+        assert!(!options.sleep(Duration::from_secs(60)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_download_retry_zero_backoff() {
+        let retry = DownloadOptions {
+            retries: 3,
+            backoff: Duration::ZERO,
+            ..DownloadOptions::default()
+        };
+        assert_eq!(retry.delay(1), Duration::ZERO);
+        assert_eq!(retry.delay(3), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_text_filter_fillers() {
+        let filter = TextFilter {
+            drop_fillers: true,
+            ..TextFilter::default()
+        };
+        for junk in [".", "Uh.", "Mm-hmm.", "um, uh...", "Hmm?", "", " - "] {
+            assert!(filter.rejects(junk), "{junk:?}");
+        }
+        for text in ["Uh, hello.", "OK.", "No.", "I", "Umbrella"] {
+            assert!(!filter.rejects(text), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_text_filter_min_chars() {
+        let filter = TextFilter {
+            min_chars: 2,
+            ..TextFilter::default()
+        };
+        assert!(filter.rejects("."));
+        assert!(filter.rejects("I."));
+        assert!(!filter.rejects("OK."));
+        // Fillers pass unless drop_fillers is on.
+        assert!(!filter.rejects("Uh."));
+        assert!(!TextFilter::default().rejects("."));
+    }
+
+    #[test]
+    fn test_numbers_to_digits_basic() {
+        assert_eq!(numbers_to_digits("one hundred twenty three"), "123");
+        assert_eq!(numbers_to_digits("I have three cats"), "I have 3 cats");
+        assert_eq!(numbers_to_digits("seventeen"), "17");
+        assert_eq!(numbers_to_digits("forty two"), "42");
+        assert_eq!(numbers_to_digits("zero"), "0");
+        assert_eq!(numbers_to_digits("ninety nine"), "99");
+    }
+
+    #[test]
+    fn test_numbers_to_digits_compounds() {
+        assert_eq!(numbers_to_digits("twenty-five"), "25");
+        assert_eq!(numbers_to_digits("one hundred and five"), "105");
+        assert_eq!(numbers_to_digits("two thousand twenty five"), "2025");
+        assert_eq!(
+            numbers_to_digits("three million four hundred thousand"),
+            "3400000"
+        );
+        assert_eq!(
+            numbers_to_digits("one thousand two hundred thirty four"),
+            "1234"
+        );
+        assert_eq!(numbers_to_digits("five hundred"), "500");
+    }
+
+    #[test]
+    fn test_numbers_to_digits_years() {
+        assert_eq!(numbers_to_digits("twenty twenty five"), "2025");
+        assert_eq!(numbers_to_digits("in nineteen eighty four"), "in 1984");
+        assert_eq!(numbers_to_digits("nineteen hundred"), "1900");
+        assert_eq!(numbers_to_digits("twenty twenty"), "2020");
+    }
+
+    #[test]
+    fn test_numbers_to_digits_sequences_and_punctuation() {
+        assert_eq!(numbers_to_digits("one two three"), "1 2 3");
+        assert_eq!(numbers_to_digits("three two one"), "3 2 1");
+        assert_eq!(
+            numbers_to_digits("call me at five, okay?"),
+            "call me at 5, okay?"
+        );
+        assert_eq!(numbers_to_digits("Twenty-one."), "21.");
+        assert_eq!(numbers_to_digits("three, four"), "3, 4");
+        assert_eq!(numbers_to_digits("zero five"), "0 5");
+    }
+
+    #[test]
+    fn test_numbers_to_digits_leaves_words_alone() {
+        assert_eq!(numbers_to_digits("no one came"), "no one came");
+        assert_eq!(numbers_to_digits("one of them"), "one of them");
+        assert_eq!(numbers_to_digits("rock and roll"), "rock and roll");
+        assert_eq!(numbers_to_digits("a hundred times"), "a hundred times");
+        assert_eq!(numbers_to_digits("well-known"), "well-known");
+        assert_eq!(numbers_to_digits("five and six"), "5 and 6");
+        assert_eq!(numbers_to_digits(""), "");
+    }
+
+    #[test]
+    fn test_numbers_to_digits_keeps_whitespace() {
+        // Untrimmed segments (`trim_result = false`) keep their spacing.
+        let raw = [
+            " I have three cats\n".to_string(),
+            "  and  twenty-five dogs,\n\tone two three ".to_string(),
+        ];
+        let text = join_segments(&raw, false, false);
+        assert_eq!(
+            numbers_to_digits(&text),
+            " I have 3 cats\n  and  25 dogs,\n\t1 2 3 "
+        );
+        assert_eq!(numbers_to_digits("no one\n\ncame "), "no one\n\ncame ");
+    }
+
+    #[test]
+    fn test_normalize_whitespace_basic() {
+        assert_eq!(normalize_whitespace("  hello   world  "), "hello world");
+    }
+
+    #[test]
+    fn test_normalize_whitespace_empty() {
+        assert_eq!(normalize_whitespace(""), "");
+    }
+
+    #[test]
+    fn test_normalize_whitespace_single_word() {
+        assert_eq!(normalize_whitespace("  hello  "), "hello");
+    }
+
+    #[test]
+    fn test_normalize_whitespace_tabs_newlines() {
+        assert_eq!(
+            normalize_whitespace("hello\t\n  world\n\nfoo"),
+            "hello world foo"
+        );
+    }
+
+    #[test]
+    fn test_normalize_whitespace_already_normal() {
+        assert_eq!(normalize_whitespace("hello world"), "hello world");
+    }
+
+    #[test]
+    fn test_preferred_backend() {
+        assert_eq!(preferred_backend(false), ComputeBackend::Cpu);
+        let expected = if GPU_SUPPORTED {
+            ComputeBackend::Gpu
+        } else {
+            ComputeBackend::Cpu
+        };
+        assert_eq!(preferred_backend(true), expected);
+        assert_eq!(ComputeBackend::Gpu.to_string(), "gpu");
+    }
+
+    #[test]
+    fn test_resolve_thread_count_auto() {
+        assert_eq!(resolve_thread_count(0, 4, 8), 4);
+        // Physical count is capped by what this process may use
+        assert_eq!(resolve_thread_count(0, 8, 2), 2);
+        assert_eq!(resolve_thread_count(0, 0, 0), 1);
+    }
+
+    #[test]
+    fn test_resolve_thread_count_explicit() {
+        assert_eq!(resolve_thread_count(3, 4, 8), 3);
+        assert_eq!(resolve_thread_count(64, 4, 8), 8);
+    }
+
+    #[test]
+    fn test_count_physical_cores() {
+        let cpuinfo = "processor\t: 0\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 1\nphysical id\t: 0\ncore id\t\t: 1\n\n\
+                       processor\t: 2\nphysical id\t: 0\ncore id\t\t: 0\n\n\
+                       processor\t: 3\nphysical id\t: 0\ncore id\t\t: 1\n";
+        assert_eq!(count_physical_cores(cpuinfo), Some(2));
+        assert_eq!(count_physical_cores("processor\t: 0\n"), None);
+    }
+
+    #[test]
+    fn test_model_path() {
+        let path = model_path("base.en");
+        assert!(path.to_string_lossy().contains("ggml-base.en.bin"));
+        assert_eq!(path.parent(), Some(default_model_dir().as_path()));
+        assert_eq!(explicit_model_path("base.en"), None);
+    }
+
+    #[test]
+    fn test_model_path_explicit() {
+        assert_eq!(
+            model_path("/mnt/nas/whisper/ggml-small.en.bin"),
+            PathBuf::from("/mnt/nas/whisper/ggml-small.en.bin")
+        );
+        assert_eq!(
+            model_path("models/custom.bin"),
+            PathBuf::from("models/custom.bin")
+        );
+        assert_eq!(model_base_name("/mnt/nas/ggml-small.en.bin"), "small.en");
+        assert_eq!(model_base_name("models/custom.bin"), "custom");
+        assert!(!needs_download("/nonexistent/ggml-small.en.bin"));
+        assert!(!needs_download(""));
+        assert!(needs_download("no-such-model"));
+        assert!(is_english_only("/mnt/nas/ggml-base.en.bin"));
+        assert!(!is_english_only("/mnt/nas/ggml-base.bin"));
+    }
+
+    #[test]
+    fn test_ensure_model_explicit_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ggml-tiny.en.bin");
+        let model = path.to_str().unwrap();
+        let err = ensure_model(model, &DownloadOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("nothing is downloaded"));
+
+        std::fs::write(&path, b"model").unwrap();
+        assert_eq!(
+            ensure_model(model, &DownloadOptions::default()).unwrap(),
+            path
+        );
+
+        // Symlinks are followed.
+        let link = dir.path().join("link.bin");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        let link_model = link.to_str().unwrap();
+        assert_eq!(
+            ensure_model(link_model, &DownloadOptions::default()).unwrap(),
+            link
+        );
+    }
+
+    #[test]
+    fn test_model_url() {
+        let url = model_url("base.en");
+        assert!(url.contains("ggml-base.en.bin"));
+        assert!(url.starts_with("https://huggingface.co/"));
+    }
+
+    #[derive(Default)]
+    struct RecordedParams {
+        temperature: Option<f32>,
+        temperature_inc: Option<f32>,
+        suppress_nst: Option<bool>,
+        token_timestamps: Option<bool>,
+        max_len: Option<i32>,
+        split_on_word: Option<bool>,
+        /// special, progress, realtime, timestamps
+        print: [Option<bool>; 4],
+    }
+
+    impl DecodeParams for RecordedParams {
+        fn set_temperature(&mut self, temperature: f32) {
+            self.temperature = Some(temperature);
+        }
+
+        fn set_temperature_inc(&mut self, temperature_inc: f32) {
+            self.temperature_inc = Some(temperature_inc);
+        }
+
+        fn set_suppress_nst(&mut self, suppress_nst: bool) {
+            self.suppress_nst = Some(suppress_nst);
+        }
+
+        fn set_token_timestamps(&mut self, token_timestamps: bool) {
+            self.token_timestamps = Some(token_timestamps);
+        }
+
+        fn set_max_len(&mut self, max_len: i32) {
+            self.max_len = Some(max_len);
+        }
+
+        fn set_split_on_word(&mut self, split_on_word: bool) {
+            self.split_on_word = Some(split_on_word);
+        }
+
+        fn set_print_special(&mut self, print_special: bool) {
+            self.print[0] = Some(print_special);
+        }
+
+        fn set_print_progress(&mut self, print_progress: bool) {
+            self.print[1] = Some(print_progress);
+        }
+
+        fn set_print_realtime(&mut self, print_realtime: bool) {
+            self.print[2] = Some(print_realtime);
+        }
+
+        fn set_print_timestamps(&mut self, print_timestamps: bool) {
+            self.print[3] = Some(print_timestamps);
+        }
+    }
+
+    #[test]
+    fn test_decode_options_apply() {
+        let settings = crate::config::Settings {
+            temperature: 0.4,
+            temperature_inc: 0.0,
+            ..Default::default()
+        };
+        let mut params = RecordedParams::default();
+        DecodeOptions::from_settings(&settings)
+            .unwrap()
+            .apply(&mut params);
+        assert_eq!(params.temperature, Some(0.4));
+        assert_eq!(params.temperature_inc, Some(0.0));
+        assert_eq!(params.suppress_nst, Some(false));
+        // No segment limit: Whisper's defaults are left alone.
+        assert_eq!(params.max_len, None);
+        assert_eq!(params.token_timestamps, None);
+        // whisper.cpp stays quiet unless debug_print_whisper is set.
+        assert_eq!(params.print, [Some(false); 4]);
+
+        let debug = crate::config::Settings {
+            debug_print_whisper: true,
+            ..Default::default()
+        };
+        assert!(DecodeOptions::from_settings(&debug).unwrap().debug_print);
+        // It would print transcripts to stderr, which privacy mode forbids.
+        let private = crate::config::Settings {
+            privacy_mode: true,
+            ..debug
+        };
+        assert!(!DecodeOptions::from_settings(&private).unwrap().debug_print);
+    }
 
-        let mut text = String::new();
-        for i in 0..num_segments {
-            if let Ok(segment) = state.full_get_segment_text(i) {
-                text.push_str(&segment);
-            }
-        }
+    fn sine(hz: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        let rate = crate::audio::WHISPER_SAMPLE_RATE as f32;
+        (0..(rate * seconds) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * hz * i as f32 / rate).sin())
+            .collect()
+    }
 
-        Ok(normalize_whitespace(&text))
+    fn rms(audio: &[f32]) -> f32 {
+        (audio.iter().map(|s| s * s).sum::<f32>() / audio.len() as f32).sqrt()
     }
-}
 
-/// Load a WAV file as f32 samples at 16kHz mono.
-fn load_wav_f32(path: &Path) -> Result<Vec<f32>> {
-    let reader = hound::WavReader::open(path)
-        .with_context(|| format!("Failed to open WAV file: {}", path.display()))?;
+    #[test]
+    fn test_high_pass_attenuates_hum() {
+        // 20Hz rumble loses most of its level; speech frequencies pass.
+        let rumble = sine(20.0, 0.5, 1.0);
+        assert!(rms(&high_pass(&rumble)) < 0.3 * rms(&rumble));
+        let voice = sine(1000.0, 0.5, 1.0);
+        assert!(rms(&high_pass(&voice)) > 0.95 * rms(&voice));
+        // A DC offset decays away.
+        let offset = high_pass(&[0.2; 16000]);
+        assert!(offset[15999].abs() < 0.001);
+    }
 
-    let spec = reader.spec();
-    let samples: Vec<f32> = match spec.sample_format {
-        hound::SampleFormat::Int => {
-            let max_val = (1 << (spec.bits_per_sample - 1)) as f32;
-            reader
-                .into_samples::<i32>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f32 / max_val)
-                .collect()
-        }
-        hound::SampleFormat::Float => reader
-            .into_samples::<f32>()
-            .filter_map(|s| s.ok())
-            .collect(),
-    };
+    #[test]
+    fn test_noise_gate() {
+        // Quiet hiss, a loud tone, then hiss again.
+        let mut audio = sine(3000.0, 0.001, 0.5);
+        audio.extend(sine(440.0, 0.5, 0.5));
+        audio.extend(sine(3000.0, 0.001, 0.5));
+        let gated = noise_gate(&audio);
+        assert_eq!(gated.len(), audio.len());
+        assert!(rms(&gated[..8000]) < 0.2 * rms(&audio[..8000]));
+        assert_eq!(gated[8000..16000], audio[8000..16000]);
 
-    // If stereo, convert to mono by averaging channels
-    if spec.channels == 2 {
-        let mono: Vec<f32> = samples
-            .chunks(2)
-            .map(|chunk| {
-                if chunk.len() == 2 {
-                    (chunk[0] + chunk[1]) / 2.0
-                } else {
-                    chunk[0]
-                }
-            })
-            .collect();
-        Ok(mono)
-    } else {
-        Ok(samples)
+        // All loud: nothing to gate against.
+        let speech = sine(440.0, 0.5, 0.5);
+        assert_eq!(noise_gate(&speech), speech);
+        assert!(noise_gate(&[]).is_empty());
     }
-}
 
-/// Normalize whitespace: trim and collapse multiple spaces.
-pub fn normalize_whitespace(text: &str) -> String {
-    text.split_whitespace().collect::<Vec<_>>().join(" ")
-}
+    #[test]
+    fn test_normalize_peak() {
+        // A quiet mic peaking around -30 dBFS is boosted to -3 dBFS.
+        let quiet = sine(440.0, 0.03, 0.5);
+        let boosted = normalize_peak(&quiet).unwrap();
+        let peak = boosted.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+        assert!((peak - NORMALIZE_PEAK).abs() < 1e-4, "{peak}");
+        assert!((boosted[100] / quiet[100] - NORMALIZE_PEAK / 0.03).abs() < 0.1);
 
-/// Get the default model directory path.
-pub fn default_model_dir() -> PathBuf {
-    dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("~/.local/share"))
-        .join("escucha")
-        .join("models")
-}
+        // Loud enough already, or essentially silent: left alone.
+        assert_eq!(normalize_peak(&sine(440.0, 0.9, 0.1)), None);
+        assert_eq!(normalize_peak(&sine(440.0, 0.0005, 0.1)), None);
+        assert_eq!(normalize_peak(&[]), None);
+    }
 
-/// Get the path for a model by name.
-pub fn model_path(model_name: &str) -> PathBuf {
-    default_model_dir().join(format!("ggml-{model_name}.bin"))
-}
+    #[test]
+    fn test_decode_options_prepare() {
+        let quiet = sine(440.0, 0.03, 0.5);
+        assert_eq!(DecodeOptions::default().prepare(&quiet), None);
+        let normalize = DecodeOptions {
+            normalize: true,
+            ..DecodeOptions::default()
+        };
+        assert!(normalize.prepare(&quiet).is_some());
+        // Denoised audio is kept even when there's nothing to boost.
+        let both = DecodeOptions {
+            denoise: Denoise::HighPass,
+            ..normalize
+        };
+        assert!(both.prepare(&sine(440.0, 0.9, 0.1)).is_some());
+    }
 
-/// Download URL for a model.
-fn model_url(model_name: &str) -> String {
-    format!("{HF_BASE_URL}/ggml-{model_name}.bin")
-}
+    #[test]
+    fn test_denoise_from_settings() {
+        let mut settings = crate::config::Settings::default();
+        assert_eq!(Denoise::from_settings(&settings).unwrap(), Denoise::Off);
+        assert_eq!(Denoise::Off.apply(&[0.5]), None);
+        settings.denoise = "on".into();
+        assert_eq!(
+            DecodeOptions::from_settings(&settings).unwrap().denoise,
+            Denoise::On
+        );
+        settings.denoise = "loud".into();
+        assert!(DecodeOptions::from_settings(&settings).is_err());
+    }
 
-/// Ensure the model exists locally, downloading it if needed.
-/// Returns the path to the model file.
-pub fn ensure_model(model_name: &str) -> Result<PathBuf> {
-    let path = model_path(model_name);
-    if path.exists() {
-        return Ok(path);
+    #[test]
+    fn test_decode_options_apply_debug_print() {
+        let settings = crate::config::Settings {
+            debug_print_whisper: true,
+            ..Default::default()
+        };
+        let mut params = RecordedParams::default();
+        DecodeOptions::from_settings(&settings)
+            .unwrap()
+            .apply(&mut params);
+        assert_eq!(params.print, [Some(true); 4]);
     }
 
-    let url = model_url(model_name);
-    log::info!("Downloading Whisper model '{model_name}' from {url}");
+    #[test]
+    fn test_decode_options_apply_max_segment_len() {
+        let settings = crate::config::Settings {
+            max_segment_len: 60,
+            ..Default::default()
+        };
+        let mut params = RecordedParams::default();
+        DecodeOptions::from_settings(&settings)
+            .unwrap()
+            .apply(&mut params);
+        assert_eq!(params.max_len, Some(60));
+        assert_eq!(params.token_timestamps, Some(true));
+        assert_eq!(params.split_on_word, Some(true));
 
-    let dir = default_model_dir();
-    std::fs::create_dir_all(&dir)
-        .with_context(|| format!("Failed to create model dir {}", dir.display()))?;
+        let too_long = crate::config::Settings {
+            max_segment_len: 5000,
+            ..Default::default()
+        };
+        assert!(DecodeOptions::from_settings(&too_long).is_err());
+    }
 
-    // Download with curl (available on virtually all Linux systems)
-    let tmp_path = path.with_extension("bin.part");
-    let status = std::process::Command::new("curl")
-        .args([
-            "-L",
-            "--progress-bar",
-            "-o",
-            tmp_path.to_str().unwrap_or(""),
-            &url,
-        ])
-        .status()
-        .context("Failed to run curl. Is curl installed?")?;
+    #[test]
+    fn test_join_segments_trimmed() {
+        let raw: Vec<String> = vec![" Hello there.".into(), "  How are you?".into()];
+        assert_eq!(
+            join_segments(&raw, false, true),
+            "Hello there. How are you?"
+        );
+        let segments: Vec<String> = raw.iter().map(|s| segment_text_for(s, true)).collect();
+        assert_eq!(segments, vec!["Hello there.", "How are you?"]);
+        // A word split across segments isn't broken apart.
+        let split: Vec<String> = vec![" Escu".into(), "cha works.".into()];
+        assert_eq!(join_segments(&split, false, true), "Escucha works.");
+    }
 
-    if !status.success() {
-        // Clean up partial download
-        let _ = std::fs::remove_file(&tmp_path);
-        anyhow::bail!("Failed to download model from {url}");
+    #[test]
+    fn test_join_segments_raw() {
+        let raw: Vec<String> = vec![" Hello there.".into(), " How are you?".into()];
+        assert_eq!(
+            join_segments(&raw, false, false),
+            " Hello there. How are you?"
+        );
+        assert_eq!(segment_text_for(&raw[1], false), " How are you?");
+        let noisy: Vec<String> = vec![" [music]".into(), " Hi".into()];
+        assert_eq!(join_segments(&noisy, true, true), "Hi");
+        assert_eq!(join_segments(&noisy, true, false), "  Hi");
     }
 
-    // Verify we got something reasonable (> 1MB)
-    let metadata = std::fs::metadata(&tmp_path).context("Downloaded file not found")?;
-    if metadata.len() < 1_000_000 {
-        let _ = std::fs::remove_file(&tmp_path);
-        anyhow::bail!(
-            "Downloaded file too small ({}B) - likely a download error",
-            metadata.len()
+    #[test]
+    fn test_strip_annotations() {
+        let text = " [music] Hello there (applause) world ♪ [BLANK_AUDIO]";
+        assert_eq!(
+            normalize_whitespace(&strip_annotations(text)),
+            "Hello there world"
         );
+        assert_eq!(strip_annotations("no annotations"), "no annotations");
+        // An unclosed bracket drops the rest rather than pasting a fragment.
+        assert_eq!(strip_annotations("text [music"), "text ");
     }
 
-    std::fs::rename(&tmp_path, &path).context("Failed to move downloaded model into place")?;
+    #[test]
+    fn test_decode_options_validation() {
+        let defaults = crate::config::Settings::default();
+        assert_eq!(
+            DecodeOptions::from_settings(&defaults).unwrap(),
+            DecodeOptions::default()
+        );
+        for (temperature, temperature_inc) in [(-0.1, 0.2), (1.5, 0.2), (0.0, 2.0), (f32::NAN, 0.2)]
+        {
+            let settings = crate::config::Settings {
+                temperature,
+                temperature_inc,
+                ..Default::default()
+            };
+            assert!(DecodeOptions::from_settings(&settings).is_err());
+        }
+    }
 
-    log::info!("Model downloaded to {}", path.display());
-    Ok(path)
-}
+    #[test]
+    fn test_format_model_list() {
+        let list = format_model_list(|name| name == "base.en");
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), MODELS.len());
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("  * base.en ") && l.contains("148 MB"))
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.starts_with("    base ") && l.ends_with("multilingual"))
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.contains("large-v3 ") && l.contains("3.1 GB"))
+        );
+        assert_eq!(list.matches('*').count(), 1);
+    }
 
-/// Ensure the model exists, with a progress callback for GUI use.
-pub fn ensure_model_with_status(
-    model_name: &str,
-    on_status: &mut dyn FnMut(&str),
-) -> Result<PathBuf> {
-    let path = model_path(model_name);
-    if path.exists() {
-        return Ok(path);
+    #[test]
+    fn test_clip_language() {
+        // Nothing detected yet: auto-detect.
+        assert_eq!(clip_language("auto", true, None), "auto");
+        // Sticky: reuse the detected language.
+        assert_eq!(clip_language("auto", true, Some("de")), "de");
+        // Fresh: detect every clip.
+        assert_eq!(clip_language("auto", false, Some("de")), "auto");
+        assert_eq!(clip_language("en", false, None), "en");
     }
 
-    on_status(&format!("Downloading model '{model_name}'..."));
+    #[test]
+    fn test_language_mismatch() {
+        assert!(language_mismatch("base.en", "en").is_none());
+        assert!(language_mismatch("base.en", "auto").is_none());
+        assert!(language_mismatch("base", "es").is_none());
+        assert!(language_mismatch("", "es").is_none());
+        assert_eq!(
+            language_mismatch("base.en", "es").as_deref(),
+            Some("Model base.en is English-only but language is es; use base instead")
+        );
+        let warning = language_mismatch("small.en-q5_1", "de").unwrap();
+        assert!(warning.ends_with("use small-q5_1 instead"));
+    }
 
-    let url = model_url(model_name);
-    let dir = default_model_dir();
-    std::fs::create_dir_all(&dir)
-        .with_context(|| format!("Failed to create model dir {}", dir.display()))?;
+    #[test]
+    fn test_model_alias_parse() {
+        let alias =
+            ModelAlias::parse("https://example.com/m/ggml-mywork.bin?download=true").unwrap();
+        assert_eq!(alias.path, default_model_dir().join("ggml-mywork.bin"));
+        assert_eq!(
+            alias.url.as_deref(),
+            Some("https://example.com/m/ggml-mywork.bin?download=true")
+        );
 
-    let tmp_path = path.with_extension("bin.part");
+        let alias = ModelAlias::parse("/nas/whisper/custom.bin").unwrap();
+        assert_eq!(alias.path, PathBuf::from("/nas/whisper/custom.bin"));
+        assert_eq!(alias.url, None);
 
-    // Use a simple HTTP download so we can report progress
-    let output = std::process::Command::new("curl")
-        .args([
-            "-L",
-            "--progress-bar",
-            "-o",
-            tmp_path.to_str().unwrap_or(""),
-            &url,
-        ])
-        .stderr(std::process::Stdio::piped())
-        .status()
-        .context("Failed to run curl")?;
+        let alias = ModelAlias::parse("finetuned.bin  http://models.lan/ft.bin").unwrap();
+        assert_eq!(alias.path, default_model_dir().join("finetuned.bin"));
+        assert_eq!(alias.url.as_deref(), Some("http://models.lan/ft.bin"));
 
-    if !output.success() {
-        let _ = std::fs::remove_file(&tmp_path);
-        anyhow::bail!("Download failed");
+        assert!(ModelAlias::parse("").is_err());
+        assert!(ModelAlias::parse("https://example.com/").is_err());
+        assert!(ModelAlias::parse("https://a/x.bin file.bin").is_err());
+        assert!(ModelAlias::parse("a.bin b.bin").is_err());
     }
 
-    let metadata = std::fs::metadata(&tmp_path)?;
-    if metadata.len() < 1_000_000 {
-        let _ = std::fs::remove_file(&tmp_path);
-        anyhow::bail!("Downloaded file too small - likely an error");
+    #[test]
+    fn test_check_model_setting() {
+        for name in ["tiny.en", "large-v3-turbo", "medium"] {
+            assert!(check_model_setting(name).is_ok(), "{name}");
+        }
+        let err = check_model_setting("large").unwrap_err().to_string();
+        assert!(err.starts_with("Unknown model \"large\""));
+        assert!(check_model_setting("").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("ggml-custom.bin");
+        let err = check_model_setting(file.to_str().unwrap()).unwrap_err();
+        assert!(err.to_string().starts_with("Model file not found"));
+        std::fs::write(&file, b"lmgg").unwrap();
+        assert!(check_model_setting(file.to_str().unwrap()).is_ok());
+
+        let choices = model_choices();
+        assert_eq!(
+            choices[..MODELS.len()],
+            MODELS
+                .iter()
+                .map(|m| m.name.to_string())
+                .collect::<Vec<_>>()[..]
+        );
+        assert!(!choices.contains(&"large".to_string()));
     }
 
-    std::fs::rename(&tmp_path, &path)?;
-    on_status("Model downloaded");
+    #[test]
+    fn test_model_aliases_resolve() {
+        // The alias table is process-wide; only this test sets it, with
+        // names no other test uses.
+        set_model_aliases(&[
+            (
+                "alias-test-remote".into(),
+                "https://example.com/ggml-remote.en.bin".into(),
+            ),
+            (
+                "alias-test-local".into(),
+                "/nonexistent/ggml-local.bin".into(),
+            ),
+        ])
+        .unwrap();
 
-    // Flush any buffered output
-    let _ = std::io::stdout().flush();
+        let remote = "alias-test-remote";
+        assert_eq!(
+            model_path(remote),
+            default_model_dir().join("ggml-remote.en.bin")
+        );
+        assert_eq!(explicit_model_path(remote), None);
+        assert_eq!(model_url(remote), "https://example.com/ggml-remote.en.bin");
+        assert!(needs_download(remote));
+        assert!(is_english_only(remote));
+        assert!(
+            language_mismatch(remote, "es")
+                .unwrap()
+                .contains("use remote instead")
+        );
 
-    Ok(path)
-}
+        let local = "alias-test-local";
+        assert_eq!(
+            model_path(local),
+            PathBuf::from("/nonexistent/ggml-local.bin")
+        );
+        assert_eq!(explicit_model_path(local), Some(model_path(local)));
+        assert!(!needs_download(local));
+        // Aliases are accepted and offered by name, even before download.
+        assert!(check_model_setting(local).is_ok());
+        assert!(check_model_setting(remote).is_ok());
+        assert!(model_choices().ends_with(&[remote.to_string(), local.to_string()]));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // Other names keep the catalog naming.
+        assert_eq!(
+            model_path("base.en"),
+            default_model_dir().join("ggml-base.en.bin")
+        );
+        assert_eq!(
+            model_url("base.en"),
+            format!("{HF_BASE_URL}/ggml-base.en.bin")
+        );
+
+        // An invalid entry is reported and leaves the table as it was.
+        let err = set_model_aliases(&[("broken".into(), "a.bin b.bin".into())]).unwrap_err();
+        assert!(format!("{err:#}").contains("Invalid model alias broken"));
+        assert_eq!(
+            model_path(local),
+            PathBuf::from("/nonexistent/ggml-local.bin")
+        );
+
+        set_model_aliases(&[]).unwrap();
+        assert_eq!(
+            model_path(local),
+            default_model_dir().join("ggml-alias-test-local.bin")
+        );
+    }
 
     #[test]
-    fn test_normalize_whitespace_basic() {
-        assert_eq!(normalize_whitespace("  hello   world  "), "hello world");
+    fn test_model_path_large() {
+        let path = model_path("large");
+        assert!(path.to_string_lossy().contains("ggml-large.bin"));
+    }
+
+    fn transcription(text: &str, confidence: f32) -> Transcription {
+        Transcription {
+            text: text.into(),
+            confidence,
+            segments: Vec::new(),
+        }
     }
 
     #[test]
-    fn test_normalize_whitespace_empty() {
-        assert_eq!(normalize_whitespace(""), "");
+    fn test_segment_display() {
+        let segment = Segment {
+            start_ms: 0,
+            end_ms: 2340,
+            text: " hello world".into(),
+            confidence: 0.873,
+        };
+        assert_eq!(segment.to_string(), "[0.00s-2.34s] p=0.87 hello world");
     }
 
     #[test]
-    fn test_normalize_whitespace_single_word() {
-        assert_eq!(normalize_whitespace("  hello  "), "hello");
+    fn test_centis_to_ms() {
+        assert_eq!(centis_to_ms(234), 2340);
+        assert_eq!(centis_to_ms(-1), 0);
     }
 
     #[test]
-    fn test_normalize_whitespace_tabs_newlines() {
-        assert_eq!(
-            normalize_whitespace("hello\t\n  world\n\nfoo"),
-            "hello world foo"
-        );
+    fn test_more_confident() {
+        let base = transcription("wreck a nice beach", 0.4);
+        let large = transcription("recognize speech", 0.9);
+        assert_eq!(more_confident(base.clone(), large.clone()), large);
+        assert_eq!(more_confident(large.clone(), base.clone()), large);
+        // Ties keep the primary result
+        let same = transcription("other", 0.4);
+        assert_eq!(more_confident(base.clone(), same), base);
     }
 
     #[test]
-    fn test_normalize_whitespace_already_normal() {
-        assert_eq!(normalize_whitespace("hello world"), "hello world");
+    fn test_is_low_confidence() {
+        assert!(transcription("mumble", 0.3).is_low_confidence());
+        assert!(!transcription("clear speech", 0.95).is_low_confidence());
+        assert!(!transcription("edge", FALLBACK_CONFIDENCE_THRESHOLD).is_low_confidence());
+        // Silence is not retried
+        assert!(!transcription("", 0.0).is_low_confidence());
     }
 
     #[test]
-    fn test_model_path() {
-        let path = model_path("base.en");
-        assert!(path.to_string_lossy().contains("ggml-base.en.bin"));
+    fn test_samples_to_f32() {
+        assert_eq!(samples_to_f32(&[0, 16384, -32768]), vec![0.0, 0.5, -1.0]);
     }
 
     #[test]
-    fn test_model_url() {
-        let url = model_url("base.en");
-        assert!(url.contains("ggml-base.en.bin"));
-        assert!(url.starts_with("https://huggingface.co/"));
+    fn test_load_wav_from_reader() {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+        writer.write_sample(0i16).unwrap();
+        writer.write_sample(16384i16).unwrap();
+        writer.write_sample(-32768i16).unwrap();
+        writer.finalize().unwrap();
+
+        let samples = load_wav_f32_from_reader(buffer.get_ref().as_slice()).unwrap();
+        assert_eq!(samples, vec![0.0, 0.5, -1.0]);
+
+        assert!(load_wav_f32_from_reader(&b"not a wav file"[..]).is_err());
     }
 
     #[test]
-    fn test_model_path_large() {
-        let path = model_path("large");
-        assert!(path.to_string_lossy().contains("ggml-large.bin"));
+    fn test_check_model_file() {
+        let dir = tempfile::tempdir().unwrap();
+        // An interrupted download: the right header, a fraction of the size.
+        let truncated = dir.path().join("ggml-base.en.bin");
+        std::fs::write(&truncated, [&GGML_MAGIC[..], &[0; 1024]].concat()).unwrap();
+        let err = check_model_file(&truncated).unwrap_err().to_string();
+        assert!(err.contains("truncated"), "{err}");
+        assert!(err.contains("download it again"), "{err}");
+
+        let not_ggml = dir.path().join("ggml-tiny.bin");
+        std::fs::write(&not_ggml, b"<!DOCTYPE html>").unwrap();
+        let err = check_model_file(&not_ggml).unwrap_err().to_string();
+        assert!(err.contains("not a whisper.cpp ggml model"), "{err}");
+
+        // Sizes are only known for catalog models.
+        let custom = dir.path().join("my-finetune.bin");
+        std::fs::write(&custom, GGML_MAGIC).unwrap();
+        assert!(check_model_file(&custom).is_ok());
+        assert!(check_model_file(&dir.path().join("missing.bin")).is_ok());
     }
 
     #[test]
@@ -305,6 +2476,81 @@ mod tests {
         assert!((samples[2] + 0.5).abs() < 0.01);
     }
 
+    fn write_int_wav(path: &Path, bits_per_sample: u16, samples: &[i32]) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_load_wav_24bit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test24.wav");
+        write_int_wav(
+            &path,
+            24,
+            &[0, 4_194_304, -4_194_304, 8_388_607, -8_388_608],
+        );
+
+        let samples = load_wav_f32(&path).unwrap();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[1] - 0.5).abs() < 1e-6);
+        assert!((samples[2] + 0.5).abs() < 1e-6);
+        assert!(samples[3] <= 1.0 && samples[3] > 0.9999);
+        assert_eq!(samples[4], -1.0);
+    }
+
+    #[test]
+    fn test_load_wav_32bit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test32.wav");
+        write_int_wav(&path, 32, &[0, 1 << 30, -(1 << 30), i32::MAX, i32::MIN]);
+
+        let samples = load_wav_f32(&path).unwrap();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0], 0.0);
+        assert!((samples[1] - 0.5).abs() < 1e-6);
+        assert!((samples[2] + 0.5).abs() < 1e-6);
+        assert!(samples[3] <= 1.0 && samples[3] > 0.9999);
+        assert_eq!(samples[4], -1.0);
+    }
+
+    #[test]
+    fn test_load_wav_float() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("testf32.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for s in [0.0f32, 0.25, -0.75, 1.0] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        assert_eq!(load_wav_f32(&path).unwrap(), vec![0.0, 0.25, -0.75, 1.0]);
+    }
+
+    #[test]
+    fn test_int_sample_scale() {
+        assert_eq!(int_sample_scale(16).unwrap(), 32768.0);
+        assert_eq!(int_sample_scale(24).unwrap(), 8_388_608.0);
+        assert_eq!(int_sample_scale(32).unwrap(), 2_147_483_648.0);
+        assert!(int_sample_scale(12).is_err());
+    }
+
     #[test]
     fn test_load_wav_stereo_to_mono() {
         let dir = tempfile::tempdir().unwrap();
@@ -328,4 +2574,25 @@ mod tests {
         assert!((samples[0] - 0.5).abs() < 0.02);
         assert!((samples[1] - 0.5).abs() < 0.02);
     }
+
+    #[test]
+    fn test_load_wav_resamples_to_16k() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+        // 0.1 s of stereo audio at half scale.
+        for _ in 0..9600 {
+            writer.write_sample(16384i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let samples = load_wav_f32_from_reader(buffer.get_ref().as_slice()).unwrap();
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|&s| s == 0.5));
+    }
 }
@@ -0,0 +1,136 @@
+//! `--watch-config`: restart the daemon with the new settings whenever the
+//! config file is saved, to try out models, keys and paste settings quickly.
+
+use anyhow::{Context, Result};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last write before reloading, so an editor's
+/// write-then-rename or a few saves in a row cause a single reload.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often the watcher thread checks for inotify events.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Fires once changes have stopped arriving for `quiet`.
+#[derive(Debug)]
+pub struct Debounce {
+    quiet: Duration,
+    last_change: Option<Instant>,
+}
+
+impl Debounce {
+    pub fn new(quiet: Duration) -> Self {
+        Self {
+            quiet,
+            last_change: None,
+        }
+    }
+
+    pub fn changed(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    /// Whether to act at `now`: true once per burst of changes.
+    pub fn fire(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(last) if now.duration_since(last) >= self.quiet => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Watch `config` from a background thread and set the returned flag each
+/// time it is saved. The directory is watched rather than the file, since
+/// many editors save by replacing the file.
+pub fn watch_config(config: &Path) -> Result<Arc<AtomicBool>> {
+    let dir = config.parent().context("Config path has no directory")?;
+    let name = config
+        .file_name()
+        .context("Config path has no file name")?
+        .to_os_string();
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create config dir {}", dir.display()))?;
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+        .context("Failed to set up inotify")?;
+    inotify
+        .add_watch(
+            dir,
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+        )
+        .with_context(|| format!("Failed to watch {}", dir.display()))?;
+
+    let changed = Arc::new(AtomicBool::new(false));
+    let flag = changed.clone();
+    let config = config.to_path_buf();
+    std::thread::spawn(move || {
+        let mut debounce = Debounce::new(DEBOUNCE);
+        loop {
+            // Fails with EAGAIN when nothing happened since the last read.
+            if let Ok(events) = inotify.read_events()
+                && events
+                    .iter()
+                    .any(|event| event.name.as_deref() == Some(name.as_os_str()))
+            {
+                debounce.changed(Instant::now());
+            }
+            if debounce.fire(Instant::now()) {
+                log::info!("{} changed", config.display());
+                flag.store(true, Ordering::Relaxed);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce() {
+        let start = Instant::now();
+        let ms = |n| start + Duration::from_millis(n);
+        let mut debounce = Debounce::new(Duration::from_millis(300));
+        assert!(!debounce.fire(ms(0)));
+
+        // A burst of saves fires once, 300ms after the last one.
+        debounce.changed(ms(0));
+        debounce.changed(ms(100));
+        debounce.changed(ms(250));
+        assert!(!debounce.fire(ms(400)));
+        assert!(debounce.fire(ms(550)));
+        assert!(!debounce.fire(ms(2000)));
+
+        debounce.changed(ms(3000));
+        assert!(debounce.fire(ms(3300)));
+    }
+
+    #[test]
+    fn test_watch_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let config = dir.path().join("config.ini");
+        let changed = watch_config(&config).unwrap();
+
+        std::fs::write(dir.path().join("other.ini"), "x").unwrap();
+        std::thread::sleep(Duration::from_millis(600));
+        assert!(!changed.load(Ordering::Relaxed));
+
+        // Replace the file the way editors do: write a copy, rename it over.
+        let tmp = dir.path().join(".config.ini.swp");
+        std::fs::write(&tmp, "[escucha]\nmodel = tiny.en\n").unwrap();
+        std::fs::rename(&tmp, &config).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !changed.load(Ordering::Relaxed) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert!(changed.load(Ordering::Relaxed));
+    }
+}